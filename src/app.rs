@@ -1,18 +1,197 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use syntect::{
-    easy::HighlightLines,
-    highlighting::{Style as SyntectStyle, ThemeSet},
+    highlighting::{Theme, ThemeSet},
     parsing::SyntaxSet,
 };
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use image::ImageDecoder;
+use serde::Deserialize;
 use walkdir::WalkDir;
 
-use crate::ops;
+use crate::{
+    archive::{self, ArchiveEntry, ArchiveKind},
+    clipboard::{CommandClipboard, SystemClipboard},
+    config, diff,
+    git::{self, GitState},
+    grep::{self, GrepMatch},
+    graphics::GraphicsProtocol,
+    highlight::HighlightCache,
+    markdown, media, ops,
+    oplog,
+    preview_cache::PreviewCache,
+    rename,
+    thumbnail::{ImageArt, Thumbnail},
+    trash,
+};
+
+// Fuzzy finder limits, to stay responsive on huge trees
+const FUZZY_MAX_DEPTH: usize = 8;
+const FUZZY_MAX_FILES: usize = 50_000;
+const FUZZY_MAX_RESULTS: usize = 200;
+
+// Bigger JSON files are previewed raw; reformatting them costs too much
+const JSON_PRETTY_MAX_BYTES: usize = 1024 * 1024;
+
+// Rows parsed from a CSV/TSV for the table preview
+const TABLE_MAX_ROWS: usize = 1000;
+// Widest a table preview column gets, in characters
+const TABLE_MAX_COL_WIDTH: usize = 30;
+
+// Bounds on the flat view, which lists the whole tree under `cwd`
+const FLATTEN_MAX_DEPTH: usize = 8;
+pub const FLATTEN_MAX_ENTRIES: usize = 10_000;
+
+// How far the file list can be squeezed or stretched, in percent of the
+// width, and how much one key press moves the split
+pub const SPLIT_MIN: u16 = 20;
+pub const SPLIT_MAX: u16 = 80;
+const SPLIT_STEP: u16 = 5;
+
+// Width of the parent column in the three-column layout, in percent
+const PARENT_COLUMN_PERCENT: u16 = 20;
+
+// Bounds on a folder's tree preview. Folders with more children than
+// `TREE_HUGE_DIR` are listed but not opened.
+const TREE_MAX_DEPTH: usize = 3;
+pub const TREE_MAX_LINES: usize = 500;
+pub const TREE_HUGE_DIR: usize = 1000;
+const TREE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Entries listed in an archive preview
+const ARCHIVE_MAX_ENTRIES: usize = 1000;
+// Deleting more than this many files and folders at once asks first
+const DELETE_CONFIRM_ENTRIES: usize = 50;
+// Characters a text preview pans per key press
+const TEXT_HSCROLL_STEP: usize = 4;
+// A page of preview before the first draw says how tall one is
+const PREVIEW_PAGE_UNDRAWN: usize = 10;
+// Lines of the last page still showing after paging
+const PREVIEW_PAGE_OVERLAP: usize = 2;
+// How much of a file is sniffed to tell binary from text
+pub(crate) const BINARY_SNIFF_BYTES: u64 = 8 * 1024;
+// Copying a preview bigger than this to the clipboard asks first
+const CLIPBOARD_CONFIRM_BYTES: usize = 1024 * 1024;
+
+pub const UNDO_LIMIT: usize = 50;
+const RECENT_COMMANDS_LIMIT: usize = 10;
+const HISTORY_LIMIT: usize = 100;
+const RECENT_DIRS_LIMIT: usize = 50;
+const COMMAND_HISTORY_LIMIT: usize = 50;
+// Toasts on screen at once; older ones are dropped early
+const TOAST_LIMIT: usize = 4;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ClipboardOp {
     Copy,
 }
 
+/// A file operation as recorded for `Action::Undo`.
+#[derive(Debug, Clone)]
+pub enum UndoOp {
+    /// Paths created by a copy; undone by trashing them, or deleting them
+    /// without a trash.
+    Copy { created: Vec<PathBuf> },
+    /// Undone by renaming `to` back to `from`.
+    Rename { from: PathBuf, to: PathBuf },
+    /// (source, destination) pairs; undone by moving each back.
+    Move { moves: Vec<(PathBuf, PathBuf)> },
+    /// (original, place in the trash) pairs; undone by restoring each.
+    Trash { items: Vec<(PathBuf, PathBuf)> },
+    /// Something that can't be reversed, e.g. a permanent delete. Kept on the
+    /// stack so undo stops here instead of silently reverting an older op.
+    Irreversible { description: String },
+}
+
+impl UndoOp {
+    /// Every path the op (or undoing it) adds or takes away.
+    fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            UndoOp::Copy { created } => created.clone(),
+            UndoOp::Rename { from, to } => vec![from.clone(), to.clone()],
+            UndoOp::Move { moves: pairs } | UndoOp::Trash { items: pairs } => {
+                pairs.iter().flat_map(|(a, b)| [a.clone(), b.clone()]).collect()
+            }
+            UndoOp::Irreversible { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Bounded stack of undoable operations; the oldest entries fall off first.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    ops: VecDeque<UndoOp>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, op: UndoOp) {
+        if self.ops.len() == UNDO_LIMIT {
+            self.ops.pop_front();
+        }
+        self.ops.push_back(op);
+    }
+
+    pub fn pop(&mut self) -> Option<UndoOp> {
+        self.ops.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    Name,
+    Size,
+    Modified,
+    #[serde(alias = "ext")]
+    Extension,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::Modified,
+            SortMode::Modified => SortMode::Extension,
+            SortMode::Extension => SortMode::Name,
+        }
+    }
+
+    /// The mode named `label` (as shown in the list title), or its long name.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let all = [SortMode::Name, SortMode::Size, SortMode::Modified, SortMode::Extension];
+        match label {
+            "extension" => Some(SortMode::Extension),
+            _ => all.into_iter().find(|mode| mode.label() == label),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "name",
+            SortMode::Size => "size",
+            SortMode::Modified => "modified",
+            SortMode::Extension => "ext",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ActiveFocus {
     FileList,
@@ -23,10 +202,245 @@ pub enum ActiveFocus {
 pub enum PopupState {
     None,
     Chmod {
-        path: PathBuf,
+        paths: Vec<PathBuf>, // the selection, or just the cursor entry
         mode: u32,
-        cursor_idx: usize, // 0-8 for rwx * 3
+        cursor_idx: usize, // 0-8 for rwx * 3, 9-11 for setuid/setgid/sticky
+        input: String,     // typed octal/symbolic mode, applied to `input_base`
+        input_base: u32,
+        input_error: Option<String>,
+    },
+    /// New owner and group names (or ids) for `paths`; blank keeps one as
+    /// it is. `field` is 0 while typing the user, 1 for the group.
+    Chown {
+        paths: Vec<PathBuf>,
+        user: String,
+        group: String,
+        field: usize,
+        error: Option<String>,
+    },
+    FuzzyFind {
+        query: String,
+        candidates: Option<Vec<PathBuf>>, // relative to cwd, None while indexing
+        results: Vec<PathBuf>,
+        cursor_idx: usize,
+    },
+    Properties {
+        path: PathBuf,
+        lines: Vec<(String, String)>, // label, value
+        scroll: usize,
     },
+    Bookmarks {
+        cursor_idx: usize,
+    },
+    /// Picks from `AppState::recent_dirs`.
+    RecentDirs {
+        cursor_idx: usize,
+    },
+    /// Asks for a program to open `path` with. `cursor_idx` points into
+    /// `AppState::recent_commands` while one is picked, None while typing;
+    /// `typed` keeps what was typed for when Up leaves the list again.
+    OpenWith {
+        path: PathBuf,
+        input: String,
+        typed: String,
+        cursor_idx: Option<usize>,
+    },
+    /// Searches file contents under `cwd`. Enter runs the query, or opens the
+    /// picked result once `results` are for the query as typed; `searched` is
+    /// the query (and regex flag) they're for.
+    Grep {
+        query: String,
+        regex: bool,
+        searched: Option<(String, bool)>,
+        results: Vec<GrepMatch>,
+        cursor_idx: usize,
+        running: bool,
+        truncated: bool,
+        error: Option<String>,
+    },
+    /// A directory to jump to, typed out; `error` says why the last try failed.
+    GoToPath {
+        input: String,
+        error: Option<String>,
+    },
+    /// A `rename::RenamePattern` for `paths`, with the old -> new names it
+    /// gives shown as it's typed.
+    BulkRename {
+        paths: Vec<PathBuf>,
+        input: String,
+        renames: Vec<(String, String)>,
+        error: Option<String>, // a bad pattern, or a clash in the new names
+    },
+    /// Asks for the folder to move `paths` into.
+    MoveTo {
+        paths: Vec<PathBuf>,
+        input: String,
+        error: Option<String>,
+    },
+    /// Asks for the name of a new link in `cwd` pointing at `target`.
+    CreateSymlink {
+        target: PathBuf,
+        input: String,
+        error: Option<String>,
+    },
+    /// Asks before quitting would throw away work, e.g. a selection.
+    ConfirmQuit {
+        reason: String,
+    },
+    /// Asks before a delete that would take more than `DELETE_CONFIRM_ENTRIES`
+    /// files and folders with it. Counting stops there, so that's all it knows.
+    ConfirmDelete {
+        paths: Vec<PathBuf>,
+    },
+    /// Asks before putting a big text preview on the clipboard.
+    ConfirmCopyText {
+        bytes: usize,
+    },
+    /// Totals for the `items` selected. `scanned` is the part still being
+    /// walked, on top of what was already `cached`.
+    Summary {
+        items: usize,
+        cached: Tally,
+        scanned: Tally,
+        running: bool,
+    },
+    /// `name` is already in the paste's destination; `AppState::pending_paste`
+    /// waits on the answer.
+    Conflict {
+        name: String,
+    },
+    /// A paste running in the background; `total` is 0 while still counting.
+    Progress {
+        done: usize,
+        total: usize,
+        current: String, // name of the last file copied
+    },
+}
+
+/// An answer to `PopupState::Conflict`. The `All` ones stand for every
+/// conflict left in the paste.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictChoice {
+    Overwrite,
+    Skip,
+    Rename, // paste as `name (copy)`
+    OverwriteAll,
+    SkipAll,
+}
+
+/// A paste stopped at a name that's taken, until it's told what to do.
+#[derive(Debug)]
+pub struct PendingPaste {
+    pub dest_dir: PathBuf,
+    pub todo: Vec<PathBuf>, // sources not looked at yet, the next one last
+    pub jobs: Vec<(PathBuf, PathBuf, bool)>, // source, destination, existed
+    pub rest: Option<ConflictChoice>, // an `All` answer, once given
+}
+
+/// Files, folders and bytes under some paths, folders included in their own
+/// count.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tally {
+    pub files: usize,
+    pub dirs: usize,
+    pub bytes: u64,
+}
+
+impl Tally {
+    pub fn add(&mut self, other: Tally) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.bytes += other.bytes;
+    }
+}
+
+// Entries walked between progress reports
+const TALLY_PROGRESS_EVERY: usize = 1000;
+
+/// Walks `path` without following links, reporting the running total to
+/// `progress` now and then. Stops early, short, once `cancel` is set.
+pub fn tally_tree(path: &Path, cancel: &AtomicBool, mut progress: impl FnMut(Tally)) -> Tally {
+    let mut tally = Tally::default();
+    for (idx, entry) in WalkDir::new(path).into_iter().filter_map(|e| e.ok()).enumerate() {
+        if entry.file_type().is_dir() {
+            tally.dirs += 1;
+        } else {
+            tally.files += 1;
+            if entry.file_type().is_file() {
+                tally.bytes += entry.metadata().map_or(0, |m| m.len());
+            }
+        }
+        if idx % TALLY_PROGRESS_EVERY == TALLY_PROGRESS_EVERY - 1 {
+            if cancel.load(atomic::Ordering::Relaxed) {
+                break;
+            }
+            progress(tally);
+        }
+    }
+    tally
+}
+
+/// The per-tab part of the listing.
+///
+/// The active tab's values live directly on `AppState` (`cwd`, `entries`,
+/// `cursor`, `selected`, `filter`) so the reducer arms can keep using them
+/// as-is; `AppState::tabs` holds the other tabs and is only synced on switch.
+/// The inactive pane of dual-pane mode (`AppState::other_pane`) works the same
+/// way.
+#[derive(Debug, Default)]
+pub struct TabState {
+    pub cwd: PathBuf,
+    pub entries: Vec<FsEntry>,
+    pub cursor: usize,
+    pub selected: HashSet<PathBuf>,
+    pub filter: String,
+}
+
+/// The listing settings kept per directory when `AppState::per_dir_view` is
+/// on. The cursor is remembered either way, in `dir_cursors`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewState {
+    pub sort_mode: SortMode,
+    pub sort_reversed: bool,
+    pub show_hidden: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+/// A short-lived notification about a finished operation, drawn in the
+/// bottom-right corner until it expires.
+#[derive(Debug)]
+pub struct Toast {
+    pub message: String,
+    pub kind: ToastKind,
+    pub created_at: Instant,
+}
+
+impl Toast {
+    /// Errors stay up longer, since they more likely need reading.
+    pub fn lifetime(&self) -> Duration {
+        match self.kind {
+            ToastKind::Info => Duration::from_secs(3),
+            ToastKind::Error => Duration::from_secs(8),
+        }
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created_at) >= self.lifetime()
+    }
+}
+
+/// A search over the text preview. `matches` holds the indices of every line
+/// of the whole file containing `query`, not just the visible ones.
+#[derive(Debug, Default)]
+pub struct PreviewSearch {
+    pub query: String,
+    pub matches: Vec<usize>,
+    pub current: usize, // index into `matches`
 }
 
 pub struct AppState {
@@ -38,11 +452,95 @@ pub struct AppState {
     pub syntax_set: SyntaxSet,
     pub theme_set: ThemeSet,
     pub clipboard: Option<(ClipboardOp, Vec<PathBuf>)>,
+    pub system_clipboard: Box<dyn SystemClipboard>,
+    pub undo_stack: UndoStack,
+    pub bookmarks: Vec<PathBuf>,
+    pub pinned: HashSet<PathBuf>, // listed first in their folder, whatever the sort
+    pub history_back: Vec<PathBuf>,
+    pub history_forward: Vec<PathBuf>,
+    pub dir_cursors: HashMap<PathBuf, usize>, // last cursor per visited directory
+    pub per_dir_view: bool, // sort and hidden files follow the directory, not the session
+    pub dir_views: HashMap<PathBuf, ViewState>, // with `per_dir_view`, per visited directory
+    pub tabs: Vec<TabState>, // tabs[active_tab] is stale while it's active
+    pub active_tab: usize,
+    pub other_pane: Option<TabState>, // the second list; Some in dual-pane mode
+    pub active_pane: usize,           // 0 when the live listing is the left pane
+    pub show_hidden: bool,
+    pub sort_mode: SortMode,
+    pub sort_reversed: bool,
+    pub dirs_first: bool, // or mixed in with the files; `:set dirsfirst`
+    pub filter: String,
+    pub glob_filter: Option<String>, // e.g. `*.{png,jpg}`; directories always show
+    pub glob_patterns: Vec<glob::Pattern>, // `glob_filter` compiled, braces expanded
+    pub wrap_navigation: bool,
+    pub show_line_numbers: bool,
+    pub split_ratio: u16, // the file list's share of the width, in percent
+    pub miller: bool,     // parent | listing | preview columns, ranger-style
+    pub parent_entries: Vec<FsEntry>, // the parent's listing, while `miller` is on
+    pub theme_name: String, // syntect theme for the preview
+    pub dir_sizes: HashMap<PathBuf, Option<u64>>, // None while still being summed
+    pub file_sizes: HashMap<PathBuf, u64>, // selected files outside `entries`, cached
+    pub tallies: HashMap<PathBuf, Tally>, // what `SummarizeSelection` found, per path
+    pub summary_cancel: Option<Arc<AtomicBool>>, // set while a summary is walking
+    pub summary_id: u64, // tags the running summary's reports, so stale ones are dropped
+    pub show_owner: bool, // the owner column; `:set owner`
+    pub size_colors: bool, // sizes shaded green to red; `:set sizecolors`
+    pub clear_selection_on_nav: bool, // otherwise the selection follows you around
+    pub flatten: bool,    // list the whole tree under `cwd`; off again on leaving it
+    #[cfg(unix)]
+    pub accounts: crate::users::Accounts, // read once, for uid/gid names
+    pub git_status: Option<HashMap<String, GitState>>, // by entry name; None outside a repo
 
     // UI State
     pub active_focus: ActiveFocus,
     pub preview_scroll: usize,
+    pub preview_hscroll: usize, // table columns, or text chars, scrolled off the left
+    pub preview_page_step: usize, // lines per page; 0 follows `preview_height`
+    pub preview_wrap: bool,     // wrap long lines of a text preview
     pub popup: PopupState,
+    pub filter_mode: bool,
+    pub command_mode: bool, // typing a `:` command into `command_input`
+    pub command_input: String,
+    pub command_history: Vec<String>,          // oldest first
+    pub command_history_idx: Option<usize>,    // entry recalled with Up/Down
+    pub command_completion: Option<(Vec<String>, usize)>, // Tab cycles these lines
+    pub preview_search: Option<PreviewSearch>,
+    pub preview_search_mode: bool, // typing into `preview_search`
+    pub status: Option<String>,
+    pub toasts: Vec<Toast>, // oldest first; expired by `run_app`
+    pub should_quit: bool, // checked by `run_app` after each key
+    pub copy_cancel: Option<Arc<AtomicBool>>, // set while a paste is running
+    pub confirm_overwrite: bool, // ask about each name a paste would land on
+    pub pending_paste: Option<PendingPaste>,
+    pub grep_cancel: Option<Arc<AtomicBool>>, // set while a content search is running
+    pub grep_id: u64, // tags the running search's results, so stale ones are dropped
+    pub preview_jump: Option<(PathBuf, usize, Option<String>)>, // file, line (and query) to open at
+    pub preview_cache: PreviewCache,
+    pub ops_log: Option<PathBuf>, // where file operations get logged; `ops_log` in the config
+    pub trash: Option<PathBuf>, // where deletes go; None deletes for good
+    pub dry_run: bool, // report file operations instead of doing them
+    pub readonly: bool, // refuse file operations altogether; `--readonly`
+    pub run_request: Option<ExternalCommand>, // taken and run by `run_app`
+    pub recent_commands: Vec<String>,         // "open with" history, newest first
+    pub recent_dirs: Vec<PathBuf>,            // visited directories, newest first
+    pub visual_anchor: Option<usize>,
+    pub visual_base: HashSet<PathBuf>, // selection from before visual mode started
+    pub graphics: Option<GraphicsProtocol>, // None: images are described as text
+    pub image_art: ImageArt,                // how images are drawn without `graphics`
+    pub image_area: Option<Rect>,           // where the inline image goes; set by `ui`
+    // Also set by `ui`, for mouse hit-testing
+    pub list_area: Rect,
+    pub list_offset: usize, // first entry drawn in the list
+    pub other_list_offset: usize, // same, for `other_pane`
+    pub preview_area: Rect,
+    pub clicked_entry: Option<usize>, // the row the last `MouseClick` hit, if any
+    pub preview_height: u16, // lines of content the preview last had room for
+
+    // Background work reports back through this channel; see `run_app`
+    pub action_tx: Sender<Action>,
+    pub action_rx: Receiver<Action>,
+
+    pub highlight_cache: HighlightCache,
 }
 
 impl std::fmt::Debug for AppState {
@@ -54,8 +552,38 @@ impl std::fmt::Debug for AppState {
             .field("selected", &self.selected)
             .field("preview", &self.preview)
             .field("clipboard", &self.clipboard)
+            .field("undo_stack", &self.undo_stack)
+            .field("bookmarks", &self.bookmarks)
+            .field("pinned", &self.pinned)
+            .field("history_back", &self.history_back)
+            .field("history_forward", &self.history_forward)
+            .field("tabs", &self.tabs)
+            .field("active_tab", &self.active_tab)
+            .field("other_pane", &self.other_pane)
+            .field("active_pane", &self.active_pane)
+            .field("show_hidden", &self.show_hidden)
+            .field("sort_mode", &self.sort_mode)
+            .field("sort_reversed", &self.sort_reversed)
+            .field("filter", &self.filter)
+            .field("glob_filter", &self.glob_filter)
+            .field("wrap_navigation", &self.wrap_navigation)
+            .field("show_line_numbers", &self.show_line_numbers)
+            .field("theme_name", &self.theme_name)
             .field("active_focus", &self.active_focus)
             .field("preview_scroll", &self.preview_scroll)
+            .field("preview_hscroll", &self.preview_hscroll)
+            .field("preview_wrap", &self.preview_wrap)
+            .field("popup", &self.popup)
+            .field("filter_mode", &self.filter_mode)
+            .field("command_mode", &self.command_mode)
+            .field("command_input", &self.command_input)
+            .field("command_history", &self.command_history)
+            .field("preview_search", &self.preview_search)
+            .field("status", &self.status)
+            .field("toasts", &self.toasts)
+            .field("should_quit", &self.should_quit)
+            .field("visual_anchor", &self.visual_anchor)
+            .field("graphics", &self.graphics)
             .finish()
     }
 }
@@ -65,8 +593,19 @@ pub struct FsEntry {
     pub path: PathBuf,
     pub name: String,
     pub is_dir: bool,
-    pub _size: u64,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
     pub permissions: String,
+    pub symlink_target: Option<PathBuf>,
+    pub uid: Option<u32>, // None off Unix, and for `..`
+    pub gid: Option<u32>,
+}
+
+impl FsEntry {
+    /// The `..` row at the top of the list. No real file can have that name.
+    pub fn is_parent_link(&self) -> bool {
+        self.name == ".."
+    }
 }
 
 #[derive(Debug)]
@@ -82,34 +621,165 @@ pub enum PreviewContent {
     Text {
         title: String,
         content: String,
+        encoding: Option<String>, // set when the file wasn't UTF-8
+    },
+    /// Markdown, already rendered to styled lines by `markdown::render`.
+    Markdown {
+        title: String,
+        lines: Vec<Line<'static>>,
+    },
+    /// Two files compared by `diff::diff_files`.
+    Diff {
+        title: String,
+        lines: Vec<Line<'static>>,
+    },
+    /// A CSV/TSV file; `truncated` when rows past `TABLE_MAX_ROWS` were dropped.
+    Table {
+        title: String,
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+        truncated: bool,
+    },
+    /// The entries of a zip/tar; `truncated` past `ARCHIVE_MAX_ENTRIES`.
+    Archive {
+        title: String,
+        entries: Vec<ArchiveEntry>,
+        truncated: bool,
     },
     Binary {
         title: String,
         size: u64,
     },
+    /// Audio or video, described by `media::probe`.
+    Media {
+        title: String,
+        fields: Vec<(String, String)>,
+    },
     Image {
         title: String,
+        path: PathBuf,
         width: u32,
         height: u32,
+        format: String, // "PNG", "JPEG", ...
         color_type: String,
+        thumbnail: Option<Thumbnail>, // for drawing as text, without graphics
     },
 }
 
+/// A program the reducer wants run. The reducer can't touch the terminal, so
+/// it leaves this in `AppState::run_request` for `run_app` to pick up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Detached, with the TUI left running (GUI apps). Otherwise the TUI is
+    /// suspended and the program gets the terminal until it exits.
+    pub background: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum Action {
     CursorMoveUp,
     CursorMoveDown,
+    CursorTop,
+    CursorBottom,
     RequestPreview(PathBuf),
     ToggleSelect,
+    ToggleVisualMode,
+    SelectAll,
+    InvertSelection,
     EnterDir,
     GoBack,
+    HistoryBack,
+    HistoryForward,
     PreviewReady(PreviewContent),
     PreviewError { path: PathBuf, error: String },
     Yank,
+    CopyPath,
+    CopyPreviewText, // the text preview's contents, onto the system clipboard
     Paste,
+    PasteInto, // into the folder under the cursor, or `cwd` on a file
+    CopyProgress { done: usize, total: usize, current: String },
+    /// The background paste ended; `created` are the new top-level paths.
+    CopyFinished {
+        created: Vec<PathBuf>,
+        pasted: Vec<PathBuf>, // where each copied item ended up
+        errors: Vec<String>,
+        cancelled: bool,
+    },
+    /// The background part of a move ended, with the moves made before it.
+    MoveFinished {
+        moves: Vec<(PathBuf, PathBuf)>,
+        errors: Vec<String>,
+        cancelled: bool,
+    },
+    CancelCopy,
+    ResolveConflict(ConflictChoice),
     Delete,
+    Undo,
+    Duplicate, // copies the current entry next to itself
+    Diff,      // compares the two selected files in the preview
     Chmod, // Opens Popup
+    Chown, // Opens Popup; Unix only
+    GoToPath, // Opens Popup
+    CreateSymlink, // Opens Popup; Unix only
+    BulkRename,    // Opens Popup
+    MoveTo,        // Opens Popup
+    ExtractArchive,
+    CompressSelection,
+    /// A background extract/compress finished; `created` is the new path.
+    ArchiveDone { created: PathBuf, result: Result<String, String> },
     Open,
+    OpenWith, // Opens Popup
+    EditFile,
+    /// `run_app` finished running `run_request`.
+    ExternalCommandDone { program: String, result: Result<(), String> },
+    ToggleHidden,
+    ToggleFlatten,
+    ToggleMiller,
+    /// Widens (or with `false`, narrows) the file list by `SPLIT_STEP`.
+    ResizeSplit { grow: bool },
+    ToggleLineNumbers,
+    TogglePreviewWrap,
+    CycleTheme,
+    CycleSort,
+    ToggleSortReverse,
+
+    // Tabs
+    NewTab,
+    CloseTab,
+    NextTab,
+    SwitchTab(usize),
+
+    // Dual-pane mode
+    ToggleDualPane,
+    SwitchPane,
+    CopyToPane, // selection (or cursor) into the other pane's directory
+    MoveToPane,
+
+    // Filter
+    StartFilter,
+    FilterInput(char),
+    FilterBackspace,
+    FilterConfirm,
+    FilterCancel,
+
+    // `:` command line
+    StartCommand,
+    CommandInput(char),
+    CommandBackspace,
+    CommandHistoryPrev,
+    CommandHistoryNext,
+    CommandComplete,
+    CommandSubmit,
+    CommandCancel,
+    StartPreviewSearch,
+    PreviewSearchInput(char),
+    PreviewSearchBackspace,
+    PreviewSearchConfirm,
+    PreviewSearchCancel,
+    PreviewSearchNext,
+    PreviewSearchPrev,
     
     // Focus & Scroll
     SwitchFocus,
@@ -117,8 +787,34 @@ pub enum Action {
     ScrollPreviewDown,
     ScrollPreviewPageUp,
     ScrollPreviewPageDown,
+    ScrollPreviewHalfPageUp,
+    ScrollPreviewHalfPageDown,
+    ScrollPreviewLeft,
+    ScrollPreviewRight,
+
+    FuzzyFind, // Opens Popup
+    Properties, // Opens Popup
+    ComputeDirSize,
+    SummarizeSelection, // Opens Popup
+    SummaryProgress { id: u64, scanned: Tally },
+    SummaryReady { id: u64, tallies: Vec<(PathBuf, Tally)> },
+    /// Something changed inside this directory; sent by `watcher::DirWatcher`.
+    DirChanged(PathBuf),
+    DirSizeReady { path: PathBuf, size: u64 },
+    GitStatusReady { dir: PathBuf, status: Option<HashMap<String, GitState>> },
+    Bookmark,
+    OpenBookmarks, // Opens Popup
+    TogglePin,
+    OpenRecentDirs, // Opens Popup
+    RemoveBookmark,
+    FuzzyIndexReady { root: PathBuf, paths: Vec<PathBuf> },
+    Grep, // Opens Popup
+    GrepResults { id: u64, matches: Vec<GrepMatch> },
+    GrepDone { id: u64, truncated: bool },
 
     // Popup Actions
+    PopupInput(char),
+    PopupBackspace,
     PopupUp,
     PopupDown,
     PopupLeft,
@@ -126,478 +822,4837 @@ pub enum Action {
     PopupToggle,
     PopupSubmit,
     PopupCancel,
+    RequestQuit,
+    MouseClick { column: u16, row: u16 },
+    MouseScroll { column: u16, row: u16, down: bool },
 }
 
-pub trait Reducer {
-    fn reduce(&mut self, action: Action);
-}
+impl AppState {
+    pub fn new(cwd: PathBuf) -> std::io::Result<Self> {
+        let entries = read_entries(&cwd, false, SortMode::Name, false, true, &HashSet::new())?;
+        let (action_tx, action_rx) = mpsc::channel();
 
-impl Reducer for AppState {
-    fn reduce(&mut self, action: Action) {
-        match action {
-            Action::CursorMoveUp => {
-                if self.active_focus == ActiveFocus::FileList {
-                    if self.cursor > 0 {
-                        self.cursor -= 1;
-                    }
-                }
-            }
-            Action::CursorMoveDown => {
-                if self.active_focus == ActiveFocus::FileList {
-                    if self.cursor + 1 < self.entries.len() {
-                        self.cursor += 1;
-                    }
-                }
+        Ok(AppState {
+            cwd,
+            cursor: first_entry(&entries),
+            entries,
+            selected: HashSet::new(),
+            preview: PreviewState::None,
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+            clipboard: None,
+            system_clipboard: Box::new(CommandClipboard),
+            undo_stack: UndoStack::default(),
+            bookmarks: Vec::new(),
+            pinned: HashSet::new(),
+            history_back: Vec::new(),
+            history_forward: Vec::new(),
+            dir_cursors: HashMap::new(),
+            per_dir_view: false,
+            dir_views: HashMap::new(),
+            tabs: vec![TabState::default()],
+            active_tab: 0,
+            other_pane: None,
+            active_pane: 0,
+            show_hidden: false,
+            sort_mode: SortMode::Name,
+            sort_reversed: false,
+            dirs_first: true,
+            filter: String::new(),
+            glob_filter: None,
+            glob_patterns: Vec::new(),
+            wrap_navigation: false,
+            show_line_numbers: true,
+            split_ratio: 50,
+            miller: false,
+            parent_entries: Vec::new(),
+            theme_name: config::DEFAULT_THEME.to_string(),
+            dir_sizes: HashMap::new(),
+            file_sizes: HashMap::new(),
+            tallies: HashMap::new(),
+            summary_cancel: None,
+            summary_id: 0,
+            show_owner: false,
+            size_colors: false,
+            clear_selection_on_nav: false,
+            flatten: false,
+            #[cfg(unix)]
+            accounts: crate::users::Accounts::load(),
+            git_status: None,
+            active_focus: ActiveFocus::FileList,
+            preview_scroll: 0,
+            preview_hscroll: 0,
+            preview_page_step: 0,
+            preview_wrap: false,
+            popup: PopupState::None,
+            filter_mode: false,
+            command_mode: false,
+            command_input: String::new(),
+            command_history: Vec::new(),
+            command_history_idx: None,
+            command_completion: None,
+            preview_search: None,
+            preview_search_mode: false,
+            status: None,
+            toasts: Vec::new(),
+            copy_cancel: None,
+            confirm_overwrite: false,
+            pending_paste: None,
+            grep_cancel: None,
+            grep_id: 0,
+            preview_jump: None,
+            preview_cache: PreviewCache::default(),
+            ops_log: None,
+            trash: None,
+            dry_run: false,
+            readonly: false,
+            run_request: None,
+            recent_commands: Vec::new(),
+            recent_dirs: Vec::new(),
+            should_quit: false,
+            visual_anchor: None,
+            visual_base: HashSet::new(),
+            graphics: None,
+            image_art: ImageArt::Color,
+            image_area: None,
+            list_area: Rect::default(),
+            list_offset: 0,
+            other_list_offset: 0,
+            preview_area: Rect::default(),
+            clicked_entry: None,
+            preview_height: 0,
+            action_tx,
+            action_rx,
+            highlight_cache: HighlightCache::default(),
+        })
+    }
+
+    /// Entries shown in the file list, i.e. `entries` narrowed by `filter`.
+    ///
+    /// The cursor indexes into this view, so every cursor-based action (enter,
+    /// preview, chmod, and yank/delete without a selection) acts on the
+    /// filtered list. An explicit selection is kept as-is and may still contain
+    /// paths the filter currently hides.
+    pub fn visible_entries(&self) -> Vec<&FsEntry> {
+        let mut entries = filter_entries(&self.entries, &self.filter);
+        if !self.glob_patterns.is_empty() {
+            let options = glob::MatchOptions {
+                case_sensitive: false,
+                ..glob::MatchOptions::new()
+            };
+            entries.retain(|e| {
+                e.is_dir || self.glob_patterns.iter().any(|p| p.matches_with(&e.name, options))
+            });
+        }
+        entries
+    }
+
+    /// Shows only files matching `glob` (None shows everything again).
+    pub fn set_glob_filter(&mut self, glob: Option<String>) -> Result<(), String> {
+        self.glob_patterns = match &glob {
+            Some(glob) => expand_braces(glob)
+                .iter()
+                .map(|p| glob::Pattern::new(p).map_err(|e| format!("Bad glob {}: {}", glob, e)))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        self.glob_filter = glob;
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    pub fn current_entry(&self) -> Option<&FsEntry> {
+        self.visible_entries().get(self.cursor).copied()
+    }
+
+    /// The entry under the cursor as something to operate on, i.e. never `..`.
+    pub fn current_target(&self) -> Option<&FsEntry> {
+        self.current_entry().filter(|e| !e.is_parent_link())
+    }
+
+    /// Paths an operation acts on: the selection, or else the entry under the
+    /// cursor. Sorted, so multi-file results come out in a stable order.
+    fn target_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = if self.selected.is_empty() {
+            self.current_target().map(|e| e.path.clone()).into_iter().collect()
+        } else {
+            self.selected.iter().cloned().collect()
+        };
+        paths.sort();
+        paths
+    }
+
+    /// How many of `paths` aren't in the current listing, e.g. selected in
+    /// another directory before navigating here.
+    pub fn outside_listing(&self, paths: &[PathBuf]) -> usize {
+        let listed: HashSet<&PathBuf> = self.entries.iter().map(|e| &e.path).collect();
+        paths.iter().filter(|p| !listed.contains(p)).count()
+    }
+
+    /// Paths of the visible entries, minus `..`; what bulk selection works on.
+    fn selectable_paths(&self) -> Vec<PathBuf> {
+        self.visible_entries()
+            .iter()
+            .filter(|e| !e.is_parent_link())
+            .map(|e| e.path.clone())
+            .collect()
+    }
+
+    fn clamp_cursor(&mut self) {
+        let len = self.visible_entries().len();
+        if self.cursor >= len {
+            self.cursor = len.saturating_sub(1);
+        }
+    }
+
+    /// Lists `path` with the current hidden-file and sort settings.
+    pub fn list_dir(&self, path: &std::path::Path) -> std::io::Result<Vec<FsEntry>> {
+        read_entries(
+            path,
+            self.show_hidden,
+            self.sort_mode,
+            self.sort_reversed,
+            self.dirs_first,
+            &self.pinned,
+        )
+    }
+
+    /// `cwd`'s listing, the whole tree of it in the flat view.
+    fn list_cwd(&self) -> std::io::Result<Vec<FsEntry>> {
+        if self.flatten {
+            read_flat_entries(
+                &self.cwd,
+                self.show_hidden,
+                self.sort_mode,
+                self.sort_reversed,
+                self.dirs_first,
+                &self.pinned,
+            )
+        } else {
+            self.list_dir(&self.cwd)
+        }
+    }
+
+    /// The current listing settings.
+    pub fn view(&self) -> ViewState {
+        ViewState {
+            sort_mode: self.sort_mode,
+            sort_reversed: self.sort_reversed,
+            show_hidden: self.show_hidden,
+        }
+    }
+
+    /// The settings to list `dir` with: the ones it was left with when
+    /// `per_dir_view` is on and it's been visited, otherwise the current ones.
+    fn view_for(&self, dir: &Path) -> ViewState {
+        match self.dir_views.get(dir) {
+            Some(view) if self.per_dir_view => *view,
+            _ => self.view(),
+        }
+    }
+
+    /// Remembers the current settings for `cwd`, before leaving it.
+    fn save_view(&mut self) {
+        if self.per_dir_view {
+            self.dir_views.insert(self.cwd.clone(), self.view());
+        }
+    }
+
+    fn apply_view(&mut self, view: ViewState) {
+        self.sort_mode = view.sort_mode;
+        self.sort_reversed = view.sort_reversed;
+        self.show_hidden = view.show_hidden;
+    }
+
+    /// Moves the active tab's live fields out into a `TabState`.
+    fn take_tab(&mut self) -> TabState {
+        self.save_view();
+        TabState {
+            cwd: self.cwd.clone(),
+            entries: std::mem::take(&mut self.entries),
+            cursor: self.cursor,
+            selected: std::mem::take(&mut self.selected),
+            filter: std::mem::take(&mut self.filter),
+        }
+    }
+
+    /// Makes `tab` the live listing, re-reading it in case it went stale.
+    fn load_tab(&mut self, tab: TabState) {
+        self.apply_view(self.view_for(&tab.cwd));
+        self.cwd = tab.cwd;
+        self.entries = tab.entries;
+        self.cursor = tab.cursor;
+        self.selected = tab.selected;
+        self.filter = tab.filter;
+        self.flatten = false;
+        self.preview = PreviewState::None;
+        self.preview_scroll = 0;
+        self.preview_hscroll = 0;
+        self.preview_search = None;
+        self.git_status = None; // the other tab's
+        self.reload_entries();
+        self.refresh_git_status();
+    }
+
+    pub fn switch_tab(&mut self, idx: usize) {
+        if idx == self.active_tab || idx >= self.tabs.len() {
+            return;
+        }
+        self.tabs[self.active_tab] = self.take_tab();
+        let next = std::mem::take(&mut self.tabs[idx]);
+        self.active_tab = idx;
+        self.load_tab(next);
+    }
+
+    /// Makes the other pane the live listing; the current one takes its place.
+    fn switch_pane(&mut self) {
+        let Some(other) = self.other_pane.take() else {
+            return;
+        };
+        self.other_pane = Some(self.take_tab());
+        std::mem::swap(&mut self.list_offset, &mut self.other_list_offset);
+        self.active_pane = 1 - self.active_pane;
+        self.visual_anchor = None;
+        self.load_tab(other);
+    }
+
+    /// Directory of the inactive pane, if dual-pane mode is on.
+    pub fn other_pane_cwd(&self) -> Option<&Path> {
+        self.other_pane.as_ref().map(|pane| pane.cwd.as_path())
+    }
+
+    /// Directory shown by tab `idx`, which for the active tab is `cwd`.
+    pub fn tab_cwd(&self, idx: usize) -> &Path {
+        if idx == self.active_tab {
+            &self.cwd
+        } else {
+            &self.tabs[idx].cwd
+        }
+    }
+
+    /// Re-selects everything between the visual anchor and the cursor.
+    fn update_visual_selection(&mut self) {
+        let Some(anchor) = self.visual_anchor else {
+            return;
+        };
+        let (lo, hi) = (anchor.min(self.cursor), anchor.max(self.cursor));
+        let range: Vec<PathBuf> = self
+            .visible_entries()
+            .iter()
+            .skip(lo)
+            .take(hi - lo + 1)
+            .filter(|e| !e.is_parent_link())
+            .map(|e| e.path.clone())
+            .collect();
+        self.selected = self.visual_base.clone();
+        self.selected.extend(range);
+    }
+
+    /// Navigates to `dir`, recording the move in the back/forward history.
+    fn change_dir(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        let previous = self.cwd.clone();
+        self.load_dir(dir)?;
+        self.history_back.push(previous);
+        if self.history_back.len() > HISTORY_LIMIT {
+            self.history_back.remove(0);
+        }
+        self.history_forward.clear();
+        Ok(())
+    }
+
+    /// Switches the listing to `dir`, resetting per-directory view state and
+    /// restoring the cursor (and with `per_dir_view`, sort and hidden files)
+    /// from the last visit.
+    fn load_dir(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        let view = self.view_for(&dir);
+        let entries = read_entries(
+            &dir,
+            view.show_hidden,
+            view.sort_mode,
+            view.sort_reversed,
+            self.dirs_first,
+            &self.pinned,
+        )?;
+        self.save_view();
+        self.apply_view(view);
+        self.dir_cursors.insert(self.cwd.clone(), self.cursor);
+        self.cursor = match self.dir_cursors.get(&dir) {
+            Some(&cursor) => cursor,
+            None => first_entry(&entries),
+        };
+        self.cwd = dir;
+        self.entries = entries;
+        if self.clear_selection_on_nav {
+            self.selected.clear();
+        }
+        self.flatten = false;
+        self.filter.clear();
+        self.visual_anchor = None;
+        self.preview = PreviewState::None;
+        self.preview_scroll = 0;
+        self.preview_hscroll = 0;
+        self.preview_search = None;
+        self.git_status = None; // the old directory's
+        self.clamp_cursor();
+        self.refresh_git_status();
+        self.refresh_parent_entries();
+        self.remember_dir();
+        Ok(())
+    }
+
+    /// Moves `cwd` to the top of the recent directories.
+    pub fn remember_dir(&mut self) {
+        self.recent_dirs.retain(|dir| *dir != self.cwd);
+        self.recent_dirs.insert(0, self.cwd.clone());
+        self.recent_dirs.truncate(RECENT_DIRS_LIMIT);
+    }
+
+    /// Re-reads the parent column for the three-column layout. Left empty
+    /// when the layout is off, and at the root.
+    fn refresh_parent_entries(&mut self) {
+        self.parent_entries = match self.cwd.parent() {
+            Some(parent) if self.miller => self
+                .list_dir(parent)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|e| !e.is_parent_link())
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+
+    /// Re-queries `git status` for `cwd` in the background; big repos can
+    /// take a while. The answer comes back as `Action::GitStatusReady`.
+    pub fn refresh_git_status(&self) {
+        let (dir, tx) = (self.cwd.clone(), self.action_tx.clone());
+        std::thread::spawn(move || {
+            let status = git::status(&dir);
+            let _ = tx.send(Action::GitStatusReady { dir, status });
+        });
+    }
+
+    fn save_bookmarks(&mut self) {
+        if let Err(e) = config::save_bookmarks(&self.bookmarks) {
+            self.status = Some(format!("Couldn't save bookmarks: {}", e));
+        }
+    }
+
+    /// Changes into `path`'s parent directory and puts the cursor on `path`.
+    fn reveal_path(&mut self, path: &Path) {
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        if self.change_dir(parent.to_path_buf()).is_ok() {
+            self.cursor = self
+                .entries
+                .iter()
+                .position(|e| e.path == path)
+                .unwrap_or(0);
+        }
+    }
+
+    /// Opens the chown popup, filled in with the first target's owner.
+    #[cfg(unix)]
+    fn open_chown(&mut self) {
+        use std::os::unix::fs::MetadataExt;
+
+        let paths = self.target_paths();
+        let Some(meta) = paths.first().and_then(|p| std::fs::metadata(p).ok()) else {
+            return;
+        };
+        let user = self.accounts.user_label(meta.uid());
+        let group = self.accounts.group_label(meta.gid());
+        self.popup = PopupState::Chown {
+            paths,
+            user,
+            group,
+            field: 0,
+            error: None,
+        };
+    }
+
+    /// Applies the chown popup. False when a name doesn't resolve, which is
+    /// left in the popup to be fixed.
+    #[cfg(unix)]
+    fn apply_chown(&mut self) -> bool {
+        let PopupState::Chown {
+            paths,
+            user,
+            group,
+            error,
+            ..
+        } = &mut self.popup
+        else {
+            return true;
+        };
+        let (uid, gid) = match (self.accounts.uid(user), self.accounts.gid(group)) {
+            (Ok(uid), Ok(gid)) => (uid, gid),
+            (Err(e), _) | (_, Err(e)) => {
+                *error = Some(e);
+                return false;
             }
-            Action::EnterDir => {
-                let mut new_cwd = self.cwd.clone();
-                if let Some(entry) = self.entries.get(self.cursor) {
-                    if entry.is_dir {
-                        new_cwd = entry.path.clone();
-                    }
-                }
+        };
+        if uid.is_none() && gid.is_none() {
+            *error = Some("enter a user or a group".to_string());
+            return false;
+        }
 
-                if new_cwd != self.cwd {
-                    if let Ok(entries) = read_entries(&new_cwd) {
-                        self.cwd = new_cwd;
-                        self.entries = entries;
-                        self.cursor = 0;
-                        self.preview = PreviewState::None;
-                        self.preview_scroll = 0;
-                        // Keep focus on FileList or reset? Let's keep it.
-                    }
-                }
+        let label = format!("{}:{}", user.trim(), group.trim());
+        let action = format!("chown {}", label);
+        let paths = paths.clone();
+        if self.dry_run_skip(format!("{} {} item(s)", action, paths.len())) {
+            return true;
+        }
+        let mut failed = 0;
+        let mut first_error = None;
+        for path in paths.iter() {
+            let result = ops::set_owner(path, uid, gid);
+            oplog::log_op(self.ops_log.as_deref(), &action, path, None, &result);
+            if let Err(e) = result {
+                failed += 1;
+                first_error.get_or_insert(e);
             }
-            Action::GoBack => {
-                if let Some(parent) = self.cwd.parent() {
-                    let new_cwd = parent.to_path_buf();
-                    if let Ok(entries) = read_entries(&new_cwd) {
-                        self.cwd = new_cwd;
-                        self.entries = entries;
-                        self.cursor = 0;
-                        self.preview = PreviewState::None;
-                        self.preview_scroll = 0;
-                    }
-                }
+        }
+        let total = paths.len();
+        match first_error {
+            None => self.toast(ToastKind::Info, format!("chown {}: {} updated", label, total)),
+            // Most likely EPERM: only root may give files away
+            Some(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                self.status = Some(format!(
+                    "chown {}: not permitted for {} of {} (changing owners needs root)",
+                    label, failed, total
+                ));
             }
-            Action::RequestPreview(path) => {
-                self.preview = PreviewState::Loading { _path: path };
-                self.preview_scroll = 0;
-            }
-            Action::ToggleSelect => {
-                if let Some(entry) = self.entries.get(self.cursor) {
-                    let path = entry.path.clone();
-                    if !self.selected.insert(path.clone()) {
-                        self.selected.remove(&path);
-                    }
-                }
+            Some(e) => {
+                let message = format!("chown {}: {} of {} failed: {}", label, failed, total, e);
+                self.status = Some(message);
             }
-            Action::Yank => {
-                let paths: Vec<PathBuf> = if self.selected.is_empty() {
-                    if let Some(entry) = self.entries.get(self.cursor) {
-                        vec![entry.path.clone()]
-                    } else {
-                        Vec::new()
-                    }
-                } else {
-                    self.selected.iter().cloned().collect()
-                };
+        }
+        self.reload_after_change(&[]); // sizes are as they were
+        true
+    }
 
-                if !paths.is_empty() {
-                    self.clipboard = Some((ClipboardOp::Copy, paths));
-                    self.selected.clear(); // Clear selection after yank
+    fn copy_preview_text(&mut self) {
+        let PreviewState::Ready(PreviewContent::Text { content, .. }) = &self.preview else {
+            return;
+        };
+        self.status = Some(match self.system_clipboard.set_text(content) {
+            Ok(()) => format!("Copied {} lines", content.lines().count()),
+            Err(e) => format!("Clipboard error: {}", e),
+        });
+    }
+
+    /// Shows `message` as a toast. Errors are drawn in red and linger.
+    pub fn toast(&mut self, kind: ToastKind, message: String) {
+        if self.toasts.len() == TOAST_LIMIT {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast {
+            message,
+            kind,
+            created_at: Instant::now(),
+        });
+    }
+
+    /// Drops toasts that have been up long enough.
+    pub fn expire_toasts(&mut self, now: Instant) {
+        self.toasts.retain(|toast| !toast.is_expired(now));
+    }
+
+    /// A path as typed on the command line: `~` is the home directory and
+    /// relative paths start from `cwd`.
+    fn resolve_arg(&self, arg: &str) -> Option<PathBuf> {
+        Some(self.cwd.join(expand_tilde(arg)?))
+    }
+
+    /// Lines a page scroll moves the preview: `preview_page_step` if set,
+    /// else what fits on screen less a little overlap to keep your place.
+    pub fn preview_page(&self) -> usize {
+        match (self.preview_page_step, self.preview_height) {
+            (0, 0) => PREVIEW_PAGE_UNDRAWN,
+            (0, height) => (height as usize).saturating_sub(PREVIEW_PAGE_OVERLAP).max(1),
+            (step, _) => step,
+        }
+    }
+
+    /// Lines a half-page scroll moves the preview, vim's Ctrl+d/Ctrl+u.
+    pub fn preview_half_page(&self) -> usize {
+        let lines = match (self.preview_page_step, self.preview_height) {
+            (0, 0) => PREVIEW_PAGE_UNDRAWN,
+            (0, height) => height as usize,
+            (step, _) => step,
+        };
+        (lines / 2).max(1)
+    }
+
+    /// Stops a text preview scrolling past its last line. The scroll counts
+    /// logical lines whether or not they're wrapped: a wrapped line just takes
+    /// more rows below it. That keeps highlighting and search on line indices
+    /// and lets the last line reach the top either way. Other previews aren't
+    /// clamped; wrapped markdown scrolls by rows, which aren't known here.
+    fn clamp_preview_scroll(&mut self) {
+        let count = match &self.preview {
+            PreviewState::Ready(PreviewContent::Text { content, .. }) => content.lines().count(),
+            PreviewState::Ready(PreviewContent::Diff { lines, .. }) => lines.len(),
+            _ => return,
+        };
+        self.preview_scroll = self.preview_scroll.min(count.saturating_sub(1));
+    }
+
+    /// Records a file operation in the ops log, when there is one.
+    fn log_op<T, E: std::fmt::Display>(
+        &self,
+        action: &str,
+        src: &Path,
+        dst: Option<&Path>,
+        result: &Result<T, E>,
+    ) {
+        oplog::log_op(self.ops_log.as_deref(), action, src, dst, result);
+    }
+
+    /// In dry-run mode, says what an operation would have done (`would`,
+    /// e.g. "delete 3 item(s)"), and returns true for the caller to skip it.
+    fn dry_run_skip(&mut self, would: String) -> bool {
+        if self.dry_run {
+            self.toast(ToastKind::Info, format!("[dry-run] would {}", would));
+        }
+        self.dry_run
+    }
+
+    /// In readonly mode, says so and returns true for the caller to leave
+    /// everything as it is.
+    fn readonly_refuse(&mut self) -> bool {
+        if self.readonly {
+            self.status = Some("Readonly mode: nothing can be changed".to_string());
+        }
+        self.readonly
+    }
+
+    /// Moves `paths` to the trash when there's a trash directory, otherwise
+    /// deletes them for good (an `UndoOp::Irreversible`). Reports how it went.
+    fn delete_paths(&mut self, paths: Vec<PathBuf>) {
+        if self.dry_run_skip(format!("delete {} item(s)", paths.len())) {
+            return;
+        }
+        if let Some(trash) = self.trash.clone() {
+            self.trash_paths(&trash, paths);
+            return;
+        }
+        if !paths.is_empty() {
+            self.undo_stack.push(UndoOp::Irreversible {
+                description: format!("delete of {} item(s)", paths.len()),
+            });
+        }
+        let (mut deleted, mut errors) = (0, Vec::new());
+        for path in &paths {
+            let result = ops::delete_path(path);
+            self.log_op("delete", path, None, &result);
+            match result {
+                Ok(()) => deleted += 1,
+                Err(e) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    errors.push(format!("{}: {}", name, e));
                 }
             }
-            Action::Paste => {
-                if let Some((op, entries)) = &self.clipboard {
-                    match op {
-                        ClipboardOp::Copy => {
-                            for src in entries {
-                                let file_name = src.file_name().unwrap_or_default();
-                                let dest = self.cwd.join(file_name);
-                                // Logic to avoid overwriting or handle collision?
-                                // For now, simple copy.
-                                let _ = ops::copy_recursive(src, &dest);
-                            }
-                        }
-                    }
-                    // Reload entries
-                    if let Ok(entries) = read_entries(&self.cwd) {
-                        self.entries = entries;
-                    }
+        }
+        match errors.first() {
+            None if deleted == 0 => {}
+            None => self.toast(ToastKind::Info, format!("Deleted {} item(s)", deleted)),
+            Some(first) => self.toast(
+                ToastKind::Error,
+                format!("Delete failed for {} item(s) (first: {})", errors.len(), first),
+            ),
+        }
+        self.selected.clear();
+        self.reload_after_change(&paths);
+    }
+
+    /// Moves `paths` to `trash`, where undo can get them back from.
+    fn trash_paths(&mut self, trash: &Path, paths: Vec<PathBuf>) {
+        let (mut items, mut errors) = (Vec::new(), Vec::new());
+        for path in paths {
+            let result = trash::trash(trash, &path);
+            self.log_op("trash", &path, result.as_ref().ok().map(PathBuf::as_path), &result);
+            match result {
+                Ok(trashed) => items.push((path, trashed)),
+                Err(e) => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    errors.push(format!("{}: {}", name, e));
                 }
             }
-            Action::Delete => {
-                let paths: Vec<PathBuf> = if self.selected.is_empty() {
-                    if let Some(entry) = self.entries.get(self.cursor) {
-                        vec![entry.path.clone()]
-                    } else {
-                        Vec::new()
-                    }
-                } else {
-                    self.selected.iter().cloned().collect()
-                };
+        }
+        match errors.first() {
+            None if items.is_empty() => {}
+            None => self.toast(ToastKind::Info, format!("Trashed {} item(s)", items.len())),
+            Some(first) => self.toast(
+                ToastKind::Error,
+                format!("Trash failed for {} item(s) (first: {})", errors.len(), first),
+            ),
+        }
+        let trashed: Vec<PathBuf> = items.iter().map(|(path, _)| path.clone()).collect();
+        if !items.is_empty() {
+            self.undo_stack.push(UndoOp::Trash { items });
+        }
+        self.selected.clear();
+        self.reload_after_change(&trashed);
+    }
 
-                for path in paths {
-                    let _ = ops::delete_path(&path);
-                }
-                self.selected.clear();
-                if let Ok(entries) = read_entries(&self.cwd) {
-                    self.entries = entries;
-                    // Adjust cursor if out of bounds
-                    if self.cursor >= self.entries.len() && !self.entries.is_empty() {
-                        self.cursor = self.entries.len() - 1;
-                    }
-                }
+    /// Enter in the grep popup. A new query starts a search in the background
+    /// and keeps the popup open (false); with results for the query as typed,
+    /// it reveals the picked match and has its preview open at the line.
+    fn submit_grep(&mut self) -> bool {
+        let PopupState::Grep {
+            query,
+            regex,
+            searched,
+            results,
+            cursor_idx,
+            running,
+            truncated,
+            error,
+        } = &mut self.popup
+        else {
+            return true;
+        };
+        let typed = (query.clone(), *regex);
+        if searched.as_ref() == Some(&typed) {
+            let Some(found) = results.get(*cursor_idx) else {
+                return false;
+            };
+            let target = self.cwd.join(&found.path);
+            // The preview search is plain text, so a regex only gets the scroll
+            let jump = (target.clone(), found.line - 1, (!typed.1).then_some(typed.0));
+            if let Some(cancel) = self.grep_cancel.take() {
+                cancel.store(true, atomic::Ordering::Relaxed);
             }
-            Action::Chmod => {
-                 if let Some(entry) = self.entries.get(self.cursor) {
-                     if let Ok(meta) = std::fs::metadata(&entry.path) {
-                         use std::os::unix::fs::PermissionsExt;
-                         let mode = meta.permissions().mode();
-                         self.popup = PopupState::Chmod {
-                             path: entry.path.clone(),
-                             mode,
-                             cursor_idx: 0,
-                         };
-                     }
-                 }
+            self.reveal_path(&target);
+            self.preview_jump = Some(jump);
+            return true;
+        }
+        if query.is_empty() {
+            return false;
+        }
+        let matcher = match grep::Matcher::new(query, *regex) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                *error = Some(e);
+                return false;
             }
-            Action::Open => {
-                if let Some(entry) = self.entries.get(self.cursor) {
-                    // Use xdg-open on Linux
-                    let _ = std::process::Command::new("xdg-open")
-                        .arg(&entry.path)
-                        .spawn();
+        };
+        *searched = Some(typed);
+        results.clear();
+        *cursor_idx = 0;
+        *running = true;
+        *truncated = false;
+
+        // Only one search at a time; the old one's results are dropped by id
+        if let Some(cancel) = self.grep_cancel.take() {
+            cancel.store(true, atomic::Ordering::Relaxed);
+        }
+        self.grep_id += 1;
+        let (id, root, tx) = (self.grep_id, self.cwd.clone(), self.action_tx.clone());
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.grep_cancel = Some(cancel.clone());
+        std::thread::spawn(move || {
+            let truncated = grep::search(&root, &matcher, &cancel, |matches| {
+                let _ = tx.send(Action::GrepResults { id, matches });
+            });
+            let _ = tx.send(Action::GrepDone { id, truncated });
+        });
+        false
+    }
+
+    /// Renames `paths` by `pattern`, for the bulk rename popup. Nothing is
+    /// touched unless every new name is free; if a rename still fails midway
+    /// the ones already done are put back.
+    fn bulk_rename(&mut self, paths: &[PathBuf], pattern: &str) -> Result<(), String> {
+        let pattern = rename::RenamePattern::parse(pattern)?;
+        let moves = rename::check(&rename::plan(paths, &pattern))?;
+        if moves.is_empty() {
+            return Err("That leaves every name as it is".to_string());
+        }
+        if self.dry_run_skip(format!("rename {} item(s)", moves.len())) {
+            return Ok(());
+        }
+        for (idx, (from, to)) in moves.iter().enumerate() {
+            let result = ops::rename(from, to);
+            self.log_op("rename", from, Some(to), &result);
+            if let Err(e) = result {
+                for (from, to) in moves[..idx].iter().rev() {
+                    let result = ops::rename(to, from);
+                    self.log_op("rename", to, Some(from), &result);
                 }
+                let name = from.file_name().unwrap_or_default().to_string_lossy();
+                return Err(format!("{}: {}", name, e));
             }
-            Action::PreviewReady(content) => {
-                self.preview = PreviewState::Ready(content);
+        }
+        let renamed: Vec<PathBuf> = moves.iter().map(|(_, to)| to.clone()).collect();
+        self.toast(ToastKind::Info, format!("Renamed {} item(s)", moves.len()));
+        self.undo_stack.push(UndoOp::Move { moves });
+        self.selected.clear();
+        self.reload_after_change(&renamed);
+        self.reveal_paths(&renamed);
+        Ok(())
+    }
+
+    /// Moves `paths` into `dest_dir`, skipping any whose name is taken there.
+    /// Renames happen here; moves to another filesystem are copied in the
+    /// background, like a paste, and the sources deleted once each is done.
+    fn move_paths(&mut self, paths: Vec<PathBuf>, dest_dir: &Path) {
+        if self.copy_cancel.is_some() {
+            return; // one at a time; the progress popup blocks this anyway
+        }
+        let would = format!("move {} item(s) to {}", paths.len(), dest_dir.display());
+        if self.dry_run_skip(would) {
+            return;
+        }
+
+        let (mut moves, mut errors, mut jobs) = (Vec::new(), Vec::new(), Vec::new());
+        for src in paths {
+            let name = src.file_name().unwrap_or_default().to_owned();
+            let dest = dest_dir.join(&name);
+            // A dangling link still takes the name
+            let result = if std::fs::symlink_metadata(&dest).is_ok() {
+                Err("already exists".to_string())
+            } else {
+                match ops::rename(&src, &dest) {
+                    Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                        jobs.push((src, dest));
+                        continue;
+                    }
+                    result => result.map_err(|e| e.to_string()),
+                }
+            };
+            self.log_op("move", &src, Some(&dest), &result);
+            match result {
+                Ok(()) => moves.push((src, dest)),
+                Err(e) => errors.push(format!("{}: {}", name.to_string_lossy(), e)),
             }
-            Action::PreviewError { path, error } => {
-                self.preview = PreviewState::Error {
-                    _path: path,
-                    message: error,
+        }
+        if jobs.is_empty() {
+            self.finish_move(moves, errors, false);
+        } else {
+            self.run_move(jobs, moves, errors);
+        }
+    }
+
+    /// Copies each source to its destination in the background, behind the
+    /// progress popup, deleting the source once its copy is complete. The
+    /// moves and errors so far are handed back with `Action::MoveFinished`.
+    fn run_move(
+        &mut self,
+        jobs: Vec<(PathBuf, PathBuf)>,
+        mut moves: Vec<(PathBuf, PathBuf)>,
+        mut errors: Vec<String>,
+    ) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.copy_cancel = Some(cancel.clone());
+        self.popup = PopupState::Progress {
+            done: 0,
+            total: 0,
+            current: String::new(),
+        };
+        let (tx, log) = (self.action_tx.clone(), self.ops_log.clone());
+        std::thread::spawn(move || {
+            let total = jobs.iter().map(|(src, _)| ops::count_files(src)).sum();
+            let mut done = 0;
+            let mut cancelled = false;
+            for (src, dest) in jobs {
+                let mut on_file = |file: &Path| {
+                    done += 1;
+                    let current = file.file_name().unwrap_or_default();
+                    let _ = tx.send(Action::CopyProgress {
+                        done,
+                        total,
+                        current: current.to_string_lossy().into_owned(),
+                    });
                 };
-            }
-            Action::SwitchFocus => {
-                self.active_focus = match self.active_focus {
-                    ActiveFocus::FileList => ActiveFocus::Preview,
-                    ActiveFocus::Preview => ActiveFocus::FileList,
+                let copied = ops::copy_with_progress(&src, &dest, &cancel, &mut on_file);
+                let result = match copied {
+                    Ok(()) => ops::delete_path(&src),
+                    Err(e) => {
+                        // Drop the half-copied item; the source is untouched
+                        let _ = ops::delete_path(&dest);
+                        Err(e)
+                    }
                 };
-            }
-            Action::ScrollPreviewUp => {
-                if self.active_focus == ActiveFocus::Preview {
-                    if self.preview_scroll > 0 {
-                        self.preview_scroll -= 1;
+                oplog::log_op(log.as_deref(), "move", &src, Some(&dest), &result);
+                match result {
+                    Ok(()) => moves.push((src, dest)),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                        cancelled = true;
+                        break;
+                    }
+                    Err(e) => {
+                        let name = src.file_name().unwrap_or_default().to_string_lossy();
+                        errors.push(format!("{}: {}", name, e));
+                        // Copied whole but not all deleted: undo can take it back
+                        if std::fs::symlink_metadata(&dest).is_ok() {
+                            moves.push((src, dest));
+                        }
                     }
                 }
             }
-            Action::ScrollPreviewDown => {
-                if self.active_focus == ActiveFocus::Preview {
-                    self.preview_scroll += 1;
-                }
+            let _ = tx.send(Action::MoveFinished {
+                moves,
+                errors,
+                cancelled,
+            });
+        });
+    }
+
+    /// Reports a move, records it for undo and reloads what it touched.
+    fn finish_move(
+        &mut self,
+        moves: Vec<(PathBuf, PathBuf)>,
+        errors: Vec<String>,
+        cancelled: bool,
+    ) {
+        let message = match errors.first() {
+            _ if cancelled => format!("Move cancelled after {} item(s)", moves.len()),
+            None => format!("Moved {} item(s)", moves.len()),
+            Some(first) => format!(
+                "Moved {} item(s), {} failed (first: {})",
+                moves.len(),
+                errors.len(),
+                first
+            ),
+        };
+        let kind = if errors.is_empty() { ToastKind::Info } else { ToastKind::Error };
+        self.toast(kind, message);
+        let op = UndoOp::Move { moves };
+        let changed = op.paths();
+        if !changed.is_empty() {
+            self.undo_stack.push(op);
+        }
+        self.selected.clear();
+        self.reload_after_change(&changed);
+    }
+
+    /// Moves `paths` into the folder `typed` names, for the move popup. It
+    /// can't be one of `paths`, or inside one.
+    fn move_to(&mut self, paths: Vec<PathBuf>, typed: &str) -> Result<(), String> {
+        if typed.is_empty() {
+            return Err("Type a folder".to_string());
+        }
+        let path = self.resolve_arg(typed).ok_or("HOME is not set")?;
+        let dir = std::fs::canonicalize(&path).map_err(|e| format!("{}: {}", typed, e))?;
+        if !dir.is_dir() {
+            return Err(format!("{}: Not a directory", typed));
+        }
+        let mut already_there = true;
+        for src in &paths {
+            // Resolve the parent only: moving a link to a folder into that
+            // folder is fine
+            let parent = src.parent().and_then(|p| std::fs::canonicalize(p).ok());
+            let name = src.file_name().unwrap_or_default();
+            if let Some(parent) = &parent
+                && dir.starts_with(parent.join(name))
+            {
+                return Err(format!("Can't move {} into itself", name.to_string_lossy()));
             }
-            Action::ScrollPreviewPageUp => {
-                if self.active_focus == ActiveFocus::Preview {
-                    self.preview_scroll = self.preview_scroll.saturating_sub(10);
-                }
+            already_there &= parent.as_deref() == Some(dir.as_path());
+        }
+        if already_there {
+            return Err(format!("Already in {}", typed));
+        }
+        self.move_paths(paths, &dir);
+        Ok(())
+    }
+
+    /// Makes `name` in `cwd` a link to `target`, for the symlink popup.
+    #[cfg(unix)]
+    fn create_symlink(&mut self, target: &Path, name: &str) -> Result<(), String> {
+        if name.is_empty() || name == "." || name == ".." {
+            return Err("Type a name for the link".to_string());
+        }
+        if name.contains(std::path::is_separator) {
+            return Err("The link goes in this folder; leave out any /".to_string());
+        }
+        let link = self.cwd.join(name);
+        if std::fs::symlink_metadata(&link).is_ok() {
+            return Err(format!("{} already exists", name));
+        }
+        if std::fs::symlink_metadata(target).is_err() {
+            return Err(format!("{} no longer exists", target.display()));
+        }
+        // Relative when it can be, so the pair can move together
+        let stored = match target.parent() {
+            Some(parent) if parent == self.cwd => {
+                PathBuf::from(target.file_name().unwrap_or_default())
             }
-            Action::ScrollPreviewPageDown => {
-                 if self.active_focus == ActiveFocus::Preview {
-                    self.preview_scroll += 10;
+            _ => target.to_path_buf(),
+        };
+        if self.dry_run_skip(format!("link {} -> {}", name, stored.display())) {
+            return Ok(());
+        }
+        let result = ops::create_symlink(&stored, &link);
+        self.log_op("symlink", target, Some(&link), &result);
+        result.map_err(|e| format!("{}: {}", name, e))?;
+        self.undo_stack.push(UndoOp::Copy {
+            created: vec![link.clone()],
+        });
+        self.reload_after_change(&[link]);
+        self.select_entry_by_name(name);
+        Ok(())
+    }
+
+    /// Jumps to the directory `typed` names, for the go-to-path popup.
+    fn go_to_path(&mut self, typed: &str) -> Result<(), String> {
+        if typed.is_empty() {
+            return Err("Type a path".to_string());
+        }
+        let path = self.resolve_arg(typed).ok_or("HOME is not set")?;
+        let dir = std::fs::canonicalize(&path).map_err(|e| format!("{}: {}", typed, e))?;
+        if !dir.is_dir() {
+            return Err(format!("{}: Not a directory", typed));
+        }
+        self.change_dir(dir).map_err(|e| format!("{}: {}", typed, e))
+    }
+
+    /// Tab in the command line: completes the argument to the longest prefix
+    /// all matching paths share, then cycles through them on further presses.
+    fn complete_command(&mut self) {
+        if let Some((candidates, idx)) = &mut self.command_completion {
+            *idx = (*idx + 1) % candidates.len();
+            self.command_input = candidates[*idx].clone();
+            return;
+        }
+        // Only arguments are paths; command names don't complete
+        let Some((name, arg)) = self.command_input.split_once(' ') else {
+            return;
+        };
+        let Some(lookup) = self.resolve_arg(arg) else {
+            return;
+        };
+        // Keep the directory part as typed, e.g. `~/` or `../`
+        let typed_dir = &arg[..arg.rfind(std::path::is_separator).map_or(0, |i| i + 1)];
+        let candidates: Vec<String> = ops::complete_path(&lookup)
+            .iter()
+            .map(|path| {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let slash = if path.is_dir() { "/" } else { "" };
+                format!("{} {}{}{}", name, typed_dir, file_name, slash)
+            })
+            .collect();
+
+        let common = common_prefix(&candidates);
+        if candidates.len() == 1 || common.len() > self.command_input.len() {
+            self.command_input = common;
+        } else if let Some(first) = candidates.first() {
+            self.command_input = first.clone();
+            self.command_completion = Some((candidates, 0));
+        }
+    }
+
+    /// Runs one `:` command line (without the colon).
+    fn run_ex_command(&mut self, line: &str) -> Result<(), String> {
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+        match name {
+            "q" | "quit" => self.reduce(Action::RequestQuit),
+            "cd" => {
+                let dir = match arg {
+                    "" => home_dir(),
+                    _ => self.resolve_arg(arg),
+                };
+                let dir = dir.ok_or("cd: HOME is not set")?;
+                // Resolves `..` and checks the directory exists in one go
+                let dir = std::fs::canonicalize(&dir).map_err(|e| format!("cd: {}: {}", arg, e))?;
+                if !dir.is_dir() {
+                    return Err(format!("cd: {}: Not a directory", arg));
                 }
+                self.change_dir(dir).map_err(|e| format!("cd: {}: {}", arg, e))?;
             }
-            Action::PopupUp => {
-                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup {
-                    if *cursor_idx >= 3 {
-                        *cursor_idx -= 3;
-                    }
+            "mkdir" => {
+                if arg.is_empty() {
+                    return Err("mkdir: missing directory name".to_string());
                 }
-            }
-            Action::PopupDown => {
-                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup {
-                    if *cursor_idx < 6 {
-                        *cursor_idx += 3;
-                    }
+                if self.readonly_refuse() {
+                    return Ok(());
                 }
-            }
-            Action::PopupLeft => {
-                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup {
-                    if *cursor_idx % 3 > 0 {
-                        *cursor_idx -= 1;
-                    }
+                let dir = self.cwd.join(arg);
+                if self.dry_run_skip(format!("create {}/", arg)) {
+                    return Ok(());
                 }
-            }
-            Action::PopupRight => {
-                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup {
-                    if *cursor_idx % 3 < 2 {
-                        *cursor_idx += 1;
-                    }
+                std::fs::create_dir_all(&dir).map_err(|e| format!("mkdir: {}: {}", arg, e))?;
+                self.reload_after_change(&[dir]);
+                // Land on the new directory (or the first new component of it)
+                if let Some(first) = Path::new(arg).components().next() {
+                    self.select_entry_by_name(&first.as_os_str().to_string_lossy());
                 }
             }
-            Action::PopupToggle => {
-                if let PopupState::Chmod { mode, cursor_idx, .. } = &mut self.popup {
-                    // Mapping idx 0-8 to mode bits
-                    // Grid:
-                    // Owner: R(0), W(1), X(2) -> 400, 200, 100
-                    // Group: R(3), W(4), X(5) -> 040, 020, 010
-                    // Other: R(6), W(7), X(8) -> 004, 002, 001
-                    
-                    let bit = match cursor_idx {
-                        0 => 0o400, 1 => 0o200, 2 => 0o100,
-                        3 => 0o040, 4 => 0o020, 5 => 0o010,
-                        6 => 0o004, 7 => 0o002, 8 => 0o001,
-                        _ => 0,
-                    };
-                    
-                    if bit != 0 {
-                        *mode ^= bit; // Toggle bit
-                    }
-                }
+            "glob" => {
+                let glob = (!arg.is_empty()).then(|| arg.to_string());
+                self.set_glob_filter(glob)?;
             }
-            Action::PopupSubmit => {
-                if let PopupState::Chmod { path, mode, .. } = &self.popup {
-                     let _ = ops::set_permissions(path, *mode);
-                     // Reload to update UI
-                     if let Ok(entries) = read_entries(&self.cwd) {
-                        self.entries = entries;
-                     }
-                }
-                self.popup = PopupState::None;
+            "sort" => {
+                let (label, reversed) = match arg.strip_suffix('!') {
+                    Some(label) => (label, true),
+                    None => (arg, false),
+                };
+                self.sort_mode = SortMode::from_label(label).ok_or_else(|| {
+                    format!("sort: unknown mode '{}' (name, size, modified, ext)", arg)
+                })?;
+                self.sort_reversed = reversed;
+                self.reload_entries();
             }
-            Action::PopupCancel => {
-                self.popup = PopupState::None;
+            "set" => {
+                // `set hidden`, `set nohidden` and `set hidden!` (toggle)
+                let (option, value) = match (arg.strip_prefix("no"), arg.strip_suffix('!')) {
+                    (_, Some(option)) => (option, None),
+                    (Some(option), _) => (option, Some(false)),
+                    _ => (arg, Some(true)),
+                };
+                let flag = match option {
+                    "hidden" => &mut self.show_hidden,
+                    "dirsfirst" => &mut self.dirs_first,
+                    "wrap" => &mut self.wrap_navigation,
+                    "number" | "nu" => &mut self.show_line_numbers,
+                    "owner" => &mut self.show_owner,
+                    "sizecolors" => &mut self.size_colors,
+                    _ => return Err(format!("set: unknown option '{}'", arg)),
+                };
+                *flag = value.unwrap_or(!*flag);
+                self.reload_entries();
             }
+            _ => return Err(format!("Not a command: {}", name)),
         }
+        Ok(())
     }
-}
 
-pub trait PreviewLoader {
-    fn load(&self, path: PathBuf) -> Result<PreviewContent, String>;
-}
+    /// Why quitting right now needs confirming, if it does: anything that
+    /// would be lost on exit. An idle session quits straight away.
+    fn quit_warning(&self) -> Option<String> {
+        if self.copy_cancel.is_some() {
+            return Some("A copy or move is still running.".to_string());
+        }
+        if self.pending_paste.is_some() {
+            return Some("A paste is waiting on a name conflict.".to_string());
+        }
+        let selected = self.selected.len()
+            + self
+                .tabs
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| *idx != self.active_tab)
+                .map(|(_, tab)| tab.selected.len())
+                .sum::<usize>();
+        (selected > 0).then(|| format!("{} selected item(s) will be lost.", selected))
+    }
 
-pub struct DefaultPreviewLoader;
+    /// Re-runs the preview search over the whole text and scrolls to the first
+    /// match at or below the current position (wrapping to the top).
+    fn update_preview_search(&mut self) {
+        let (Some(search), PreviewState::Ready(PreviewContent::Text { content, .. })) =
+            (&mut self.preview_search, &self.preview)
+        else {
+            return;
+        };
+        let query = search.query.to_lowercase();
+        search.matches = if query.is_empty() {
+            Vec::new()
+        } else {
+            content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.to_lowercase().contains(&query))
+                .map(|(idx, _)| idx)
+                .collect()
+        };
+        search.current = search
+            .matches
+            .iter()
+            .position(|&line| line >= self.preview_scroll)
+            .unwrap_or(0);
+        if let Some(&line) = search.matches.get(search.current) {
+            self.preview_scroll = line;
+        }
+    }
 
-impl PreviewLoader for DefaultPreviewLoader {
-    fn load(&self, path: PathBuf) -> Result<PreviewContent, String> {
-        let title = path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .into_owned();
+    /// The image to draw over the preview pane and where, if any.
+    pub fn image_target(&self) -> Option<(PathBuf, Rect)> {
+        match (&self.preview, self.image_area) {
+            (PreviewState::Ready(PreviewContent::Image { path, .. }), Some(area)) => {
+                Some((path.clone(), area))
+            }
+            _ => None,
+        }
+    }
 
-        if path.is_dir() {
-            let mut tree = String::new();
-            for entry in WalkDir::new(&path)
-                .min_depth(1)
-                .max_depth(3)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                let depth = entry.depth();
-                let indent = "  ".repeat(depth - 1);
-                let name = entry.file_name().to_string_lossy();
-                tree.push_str(&format!("{}|-- {}\n", indent, name));
+    /// Copies `sources` into `dest_dir` on a background thread, showing the
+    /// progress popup until `Action::CopyFinished` comes back.
+    fn start_copy(&mut self, sources: &[PathBuf], dest_dir: &Path) {
+        if self.copy_cancel.is_some() {
+            return; // one at a time; the progress popup blocks this anyway
+        }
+        if let Some(src) = sources.iter().find(|src| dest_dir.starts_with(src)) {
+            let name = src.file_name().unwrap_or_default().to_string_lossy();
+            self.status = Some(format!("Can't copy {} into itself", name));
+            return;
+        }
+        let would = format!("copy {} item(s) to {}", sources.len(), dest_dir.display());
+        if self.dry_run_skip(would) {
+            return;
+        }
+        self.pending_paste = Some(PendingPaste {
+            dest_dir: dest_dir.to_path_buf(),
+            todo: sources.iter().rev().cloned().collect(),
+            jobs: Vec::new(),
+            rest: None,
+        });
+        self.resume_paste(None);
+    }
+
+    /// Works through the pending paste until a name is taken and
+    /// `confirm_overwrite` wants it asked about; `choice` answers the one it
+    /// stopped at last time. Once all are settled, the copy starts.
+    fn resume_paste(&mut self, mut choice: Option<ConflictChoice>) {
+        let Some(mut paste) = self.pending_paste.take() else {
+            return;
+        };
+        while let Some(src) = paste.todo.last().cloned() {
+            let dest = paste.dest_dir.join(src.file_name().unwrap_or_default());
+            let existed = dest.exists();
+            let answer = if dest == src {
+                Some(ConflictChoice::Rename) // copying over itself would empty it
+            } else if !existed || !self.confirm_overwrite {
+                Some(ConflictChoice::Overwrite)
+            } else {
+                choice.take().or(paste.rest)
+            };
+            let Some(answer) = answer else {
+                let name = dest.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                self.popup = PopupState::Conflict { name };
+                self.pending_paste = Some(paste);
+                return;
+            };
+            paste.todo.pop();
+            match answer {
+                ConflictChoice::Overwrite | ConflictChoice::OverwriteAll => {
+                    paste.jobs.push((src, dest, existed));
+                }
+                ConflictChoice::Rename => {
+                    let dest = ops::unique_copy_path(&dest);
+                    paste.jobs.push((src, dest, false));
+                }
+                ConflictChoice::Skip | ConflictChoice::SkipAll => {}
             }
-            return Ok(PreviewContent::Text {
-                title,
-                content: tree,
-            });
+            if matches!(answer, ConflictChoice::OverwriteAll | ConflictChoice::SkipAll) {
+                paste.rest = Some(answer);
+            }
+        }
+        self.popup = PopupState::None;
+        if paste.jobs.is_empty() {
+            self.toast(ToastKind::Info, "Nothing pasted".to_string());
+            return;
         }
+        self.run_copy(paste.jobs);
+    }
 
-        // Try to load as image first
-        if let Ok(reader) = image::ImageReader::open(&path) {
-            if let Ok(dims) = reader.with_guessed_format() {
-                if let Ok(img_dims) = dims.into_dimensions() {
-                    return Ok(PreviewContent::Image {
-                        title: title.clone(),
-                        width: img_dims.0,
-                        height: img_dims.1,
-                        color_type: "Unknown".to_string(),
+    /// Copies each source to its destination in the background, behind the
+    /// progress popup. `existed` destinations are merged into, and kept on undo.
+    fn run_copy(&mut self, jobs: Vec<(PathBuf, PathBuf, bool)>) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.copy_cancel = Some(cancel.clone());
+        self.popup = PopupState::Progress {
+            done: 0,
+            total: 0,
+            current: String::new(),
+        };
+        let (tx, log) = (self.action_tx.clone(), self.ops_log.clone());
+        std::thread::spawn(move || {
+            let total = jobs.iter().map(|(src, ..)| ops::count_files(src)).sum();
+            let mut done = 0;
+            let (mut created, mut pasted, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+            let mut cancelled = false;
+            for (src, dest, existed) in jobs {
+                let mut on_file = |file: &Path| {
+                    done += 1;
+                    let current = file.file_name().unwrap_or_default();
+                    let _ = tx.send(Action::CopyProgress {
+                        done,
+                        total,
+                        current: current.to_string_lossy().into_owned(),
                     });
+                };
+                let result = ops::copy_with_progress(&src, &dest, &cancel, &mut on_file);
+                oplog::log_op(log.as_deref(), "copy", &src, Some(&dest), &result);
+                match result {
+                    Ok(()) => pasted.push(dest.clone()),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                        // Drop the half-copied item, unless it merged into an old one
+                        if !existed {
+                            let _ = ops::delete_path(&dest);
+                        }
+                        cancelled = true;
+                        break;
+                    }
+                    Err(e) => {
+                        let name = src.file_name().unwrap_or_default().to_string_lossy();
+                        errors.push(format!("{}: {}", name, e));
+                    }
                 }
+                // Only brand-new paths are safe to remove on undo
+                if !existed && dest.exists() {
+                    created.push(dest);
+                }
+            }
+            let _ = tx.send(Action::CopyFinished {
+                created,
+                pasted,
+                errors,
+                cancelled,
+            });
+        });
+    }
+
+    /// The summed size of the selection. Directories count once `DirSize`
+    /// has summed them; files elsewhere are looked up once and cached.
+    pub fn selection_size(&mut self) -> u64 {
+        let mut total = 0;
+        for path in &self.selected {
+            if let Some(Some(size)) = self.dir_sizes.get(path) {
+                total += size;
+            } else if let Some(entry) = self.entries.iter().find(|e| &e.path == path) {
+                total += if entry.is_dir { 0 } else { entry.size };
+            } else {
+                total += *self.file_sizes.entry(path.clone()).or_insert_with(|| {
+                    std::fs::metadata(path).map_or(0, |m| if m.is_dir() { 0 } else { m.len() })
+                });
             }
         }
+        total
+    }
 
-        // Fallback: Check extension if image loading failed/wasn't supported format
+    /// Puts the cursor on the visible entry called `name`. False, with the
+    /// cursor left alone, if there's no such entry.
+    pub fn select_entry_by_name(&mut self, name: &str) -> bool {
+        match self.visible_entries().iter().position(|e| e.name == name) {
+            Some(idx) => {
+                self.cursor = idx;
+                self.update_visual_selection();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lands the cursor on the first of `paths` (in listing order) that's in
+    /// `cwd`, e.g. after a paste. Paths elsewhere are ignored.
+    fn reveal_paths(&mut self, paths: &[PathBuf]) {
+        let first = self
+            .visible_entries()
+            .iter()
+            .find(|e| !e.is_parent_link() && paths.contains(&e.path))
+            .map(|e| e.name.clone());
+        if let Some(name) = first {
+            self.select_entry_by_name(&name);
+        }
+    }
+
+    /// Re-reads `cwd`, keeping the cursor on the same file name. If that file
+    /// is gone the cursor stays at its index, i.e. on the next file.
+    ///
+    /// In dual-pane mode the other pane is re-read too, since copies and moves
+    /// land there.
+    pub fn reload_entries(&mut self) {
+        self.file_sizes.clear();
+        if let Some(pane) = &mut self.other_pane
+            && let Ok(entries) = read_entries(
+                &pane.cwd,
+                self.show_hidden,
+                self.sort_mode,
+                self.sort_reversed,
+                self.dirs_first,
+                &self.pinned,
+            )
+        {
+            pane.entries = entries;
+            let len = filter_entries(&pane.entries, &pane.filter).len();
+            pane.cursor = pane.cursor.min(len.saturating_sub(1));
+        }
+
+        let current = self.current_entry().map(|e| e.name.clone());
+        if let Ok(entries) = self.list_cwd() {
+            self.entries = entries;
+            self.visual_anchor = None;
+            if let Some(name) = current {
+                self.select_entry_by_name(&name);
+            }
+            self.clamp_cursor();
+        }
+        self.refresh_parent_entries();
+    }
+
+    /// `reload_entries` after changing files, which may change `git status`
+    /// too. Plain re-sorts and watcher events leave git alone.
+    ///
+    /// Summed sizes of the `changed` paths, of anything in them and of the
+    /// directories holding them are dropped, to be summed again when asked.
+    fn reload_after_change(&mut self, changed: &[PathBuf]) {
+        let stale = |path: &PathBuf| {
+            changed.iter().any(|c| c.starts_with(path) || path.starts_with(c))
+        };
+        self.tallies.retain(|path, _| !stale(path));
+        self.dir_sizes.retain(|path, size| size.is_none() || !stale(path));
+        self.reload_entries();
+        self.refresh_git_status();
+    }
+}
+
+pub trait Reducer {
+    fn reduce(&mut self, action: Action);
+}
+
+impl Reducer for AppState {
+    fn reduce(&mut self, action: Action) {
+        match action {
+            Action::CursorMoveUp => {
+                let len = self.visible_entries().len();
+                if self.active_focus == ActiveFocus::FileList && len > 0 {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                    } else if self.wrap_navigation {
+                        self.cursor = len - 1;
+                    }
+                    self.update_visual_selection();
+                }
+            }
+            Action::CursorMoveDown => {
+                let len = self.visible_entries().len();
+                if self.active_focus == ActiveFocus::FileList && len > 0 {
+                    if self.cursor + 1 < len {
+                        self.cursor += 1;
+                    } else if self.wrap_navigation {
+                        self.cursor = 0;
+                    }
+                    self.update_visual_selection();
+                }
+            }
+            Action::CursorTop => {
+                if self.active_focus == ActiveFocus::FileList {
+                    self.cursor = 0;
+                    self.update_visual_selection();
+                }
+            }
+            Action::CursorBottom => {
+                if self.active_focus == ActiveFocus::FileList {
+                    self.cursor = self.visible_entries().len().saturating_sub(1);
+                    self.update_visual_selection();
+                }
+            }
+            Action::EnterDir if self.current_entry().is_some_and(FsEntry::is_parent_link) => {
+                self.reduce(Action::GoBack);
+            }
+            Action::EnterDir => {
+                let mut new_cwd = self.cwd.clone();
+                if let Some(entry) = self.current_entry()
+                    && entry.is_dir
+                {
+                    new_cwd = entry.path.clone();
+                }
+
+                if new_cwd != self.cwd {
+                    // Keep focus on FileList or reset? Let's keep it.
+                    let _ = self.change_dir(new_cwd);
+                }
+            }
+            Action::GoBack => {
+                if let Some(parent) = self.cwd.parent() {
+                    let _ = self.change_dir(parent.to_path_buf());
+                }
+            }
+            Action::HistoryBack => {
+                if let Some(dir) = self.history_back.pop() {
+                    let current = self.cwd.clone();
+                    match self.load_dir(dir.clone()) {
+                        Ok(()) => self.history_forward.push(current),
+                        Err(e) => {
+                            self.status = Some(format!("Can't go back to {}: {}", dir.display(), e));
+                        }
+                    }
+                }
+            }
+            Action::HistoryForward => {
+                if let Some(dir) = self.history_forward.pop() {
+                    let current = self.cwd.clone();
+                    match self.load_dir(dir.clone()) {
+                        Ok(()) => self.history_back.push(current),
+                        Err(e) => {
+                            self.status =
+                                Some(format!("Can't go forward to {}: {}", dir.display(), e));
+                        }
+                    }
+                }
+            }
+            Action::RequestPreview(path) => {
+                // The jump was for another file; don't land this one at its line
+                if self.preview_jump.as_ref().is_some_and(|(target, ..)| *target != path) {
+                    self.preview_jump = None;
+                }
+                self.preview = PreviewState::Loading { _path: path };
+                self.preview_scroll = 0;
+                self.preview_hscroll = 0;
+                self.preview_search = None;
+                self.preview_search_mode = false;
+            }
+            Action::ToggleSelect => {
+                if let Some(entry) = self.current_target() {
+                    let path = entry.path.clone();
+                    if !self.selected.insert(path.clone()) {
+                        self.selected.remove(&path);
+                    }
+                }
+            }
+            Action::ToggleVisualMode => {
+                if self.visual_anchor.take().is_none() && self.current_entry().is_some() {
+                    self.visual_anchor = Some(self.cursor);
+                    self.visual_base = self.selected.clone();
+                    self.update_visual_selection();
+                }
+            }
+            // Both act on the filtered view, like the cursor does
+            Action::SelectAll => {
+                let paths = self.selectable_paths();
+                self.selected.extend(paths);
+            }
+            Action::InvertSelection => {
+                let paths = self.selectable_paths();
+                for path in paths {
+                    if !self.selected.remove(&path) {
+                        self.selected.insert(path);
+                    }
+                }
+            }
+            Action::Yank => {
+                let paths = self.target_paths();
+
+                if !paths.is_empty() {
+                    self.clipboard = Some((ClipboardOp::Copy, paths));
+                    self.selected.clear(); // Clear selection after yank
+                }
+            }
+            Action::CopyPath => {
+                let paths = self.target_paths();
+
+                if !paths.is_empty() {
+                    let text = paths
+                        .iter()
+                        .map(|p| p.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    self.status = Some(match self.system_clipboard.set_text(&text) {
+                        Ok(()) if paths.len() == 1 => "Copied path".to_string(),
+                        Ok(()) => format!("Copied {} paths", paths.len()),
+                        Err(e) => format!("Clipboard error: {}", e),
+                    });
+                }
+            }
+            Action::CopyPreviewText => {
+                if let PreviewState::Ready(PreviewContent::Text { content, .. }) = &self.preview {
+                    if content.len() > CLIPBOARD_CONFIRM_BYTES {
+                        self.popup = PopupState::ConfirmCopyText {
+                            bytes: content.len(),
+                        };
+                    } else {
+                        self.copy_preview_text();
+                    }
+                }
+            }
+            Action::Paste => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                if let Some((ClipboardOp::Copy, entries)) = &self.clipboard {
+                    let (sources, dest_dir) = (entries.clone(), self.cwd.clone());
+                    self.start_copy(&sources, &dest_dir);
+                }
+            }
+            Action::PasteInto => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let dest_dir = match self.current_target() {
+                    Some(entry) if entry.is_dir => entry.path.clone(),
+                    _ => self.cwd.clone(),
+                };
+                if let Some((ClipboardOp::Copy, entries)) = &self.clipboard {
+                    let sources = entries.clone();
+                    self.start_copy(&sources, &dest_dir);
+                }
+            }
+            Action::CopyProgress {
+                done,
+                total,
+                current,
+            } => {
+                if let PopupState::Progress { .. } = self.popup {
+                    self.popup = PopupState::Progress {
+                        done,
+                        total,
+                        current,
+                    };
+                }
+            }
+            Action::ResolveConflict(choice) => {
+                if let PopupState::Conflict { .. } = self.popup {
+                    self.resume_paste(Some(choice));
+                }
+            }
+            Action::CancelCopy => {
+                if let Some(cancel) = &self.copy_cancel {
+                    cancel.store(true, atomic::Ordering::Relaxed);
+                    self.status = Some("Cancelling...".to_string());
+                }
+            }
+            Action::CopyFinished {
+                created,
+                pasted,
+                errors,
+                cancelled,
+            } => {
+                self.copy_cancel = None;
+                if let PopupState::Progress { .. } = self.popup {
+                    self.popup = PopupState::None;
+                }
+                if !created.is_empty() {
+                    self.undo_stack.push(UndoOp::Copy { created });
+                }
+                self.status = None; // "Cancelling..."
+                let copied = pasted.len();
+                let kind = if errors.is_empty() { ToastKind::Info } else { ToastKind::Error };
+                let message = match errors.first() {
+                    _ if cancelled => format!("Paste cancelled after {} item(s)", copied),
+                    None => format!("Pasted {} item(s)", copied),
+                    Some(first) if errors.len() == 1 => {
+                        format!("Pasted {} item(s), 1 failed: {}", copied, first)
+                    }
+                    Some(first) => format!(
+                        "Pasted {} item(s), {} failed (first: {})",
+                        copied,
+                        errors.len(),
+                        first
+                    ),
+                };
+                self.toast(kind, message);
+                self.reload_after_change(&pasted);
+                self.reveal_paths(&pasted);
+            }
+            Action::MoveFinished {
+                moves,
+                errors,
+                cancelled,
+            } => {
+                self.copy_cancel = None;
+                if let PopupState::Progress { .. } = self.popup {
+                    self.popup = PopupState::None;
+                }
+                self.status = None; // "Cancelling..."
+                self.finish_move(moves, errors, cancelled);
+            }
+            Action::Delete => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let paths = self.target_paths();
+                // What goes with the targets, counted only up to the limit
+                let mut count = 0;
+                for path in &paths {
+                    if count > DELETE_CONFIRM_ENTRIES {
+                        break;
+                    }
+                    count += 1 + ops::count_entries_capped(path, DELETE_CONFIRM_ENTRIES - count);
+                }
+                // Also ask when some of it is out of sight, in other directories
+                if count > DELETE_CONFIRM_ENTRIES || self.outside_listing(&paths) > 0 {
+                    self.popup = PopupState::ConfirmDelete { paths };
+                    return;
+                }
+                self.delete_paths(paths);
+            }
+            Action::Diff => {
+                let paths = self.target_paths();
+                let [old, new] = paths.as_slice() else {
+                    self.status = Some("Select two files to compare".to_string());
+                    return;
+                };
+                if old.is_dir() || new.is_dir() {
+                    self.status = Some("Only files can be compared".to_string());
+                    return;
+                }
+                let name = |p: &Path| {
+                    p.file_name().unwrap_or_default().to_string_lossy().into_owned()
+                };
+                let title = format!("{} vs {}", name(old), name(new));
+                match diff::diff_files(old, new) {
+                    Ok(lines) => {
+                        self.reduce(Action::RequestPreview(new.clone()));
+                        self.preview = PreviewState::Ready(PreviewContent::Diff { title, lines });
+                    }
+                    Err(e) => self.status = Some(format!("Can't compare: {}", e)),
+                }
+            }
+            Action::Duplicate => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let Some(entry) = self.current_target() else { return };
+                let (src, dest) = (entry.path.clone(), ops::unique_copy_path(&entry.path));
+                let name = dest.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                if self.dry_run_skip(format!("duplicate {} as {}", src.display(), name)) {
+                    return;
+                }
+                let result = ops::copy_recursive(&src, &dest);
+                self.log_op("copy", &src, Some(&dest), &result);
+                if let Err(e) = result {
+                    // Don't leave half a copy behind
+                    let _ = ops::delete_path(&dest);
+                    let name = src.file_name().unwrap_or_default().to_string_lossy();
+                    self.status = Some(format!("Couldn't duplicate {}: {}", name, e));
+                    return;
+                }
+                self.undo_stack.push(UndoOp::Copy {
+                    created: vec![dest.clone()],
+                });
+                self.reload_after_change(std::slice::from_ref(&dest));
+                self.reveal_paths(&[dest]); // by path, so it works in the flat view too
+                self.status = Some(format!("Duplicated as {}", name));
+            }
+            Action::Undo => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                match self.undo_stack.pop() {
+                    None => self.status = Some("Nothing to undo".to_string()),
+                    Some(UndoOp::Irreversible { description }) => {
+                        self.status =
+                            Some(format!("Nothing to undo: {} can't be reverted", description));
+                    }
+                    Some(op) => {
+                        let changed = op.paths();
+                        match undo_op(op, self.ops_log.as_deref(), self.trash.as_deref()) {
+                            Ok(()) => self.toast(ToastKind::Info, "Undone".to_string()),
+                            Err((e, left)) => {
+                                // What's left can be tried again once fixed
+                                self.undo_stack.push(left);
+                                self.toast(ToastKind::Error, format!("Undo failed: {}", e));
+                            }
+                        }
+                        self.reload_after_change(&changed);
+                    }
+                }
+            }
+            Action::Chmod => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let paths = self.target_paths();
+
+                // The grid starts from the first file's mode
+                if let Some(first) = paths.first()
+                    && let Ok(meta) = std::fs::metadata(first)
+                {
+                    let mode = ops::mode_of(&meta);
+                    self.popup = PopupState::Chmod {
+                        paths,
+                        mode,
+                        cursor_idx: 0,
+                        input: String::new(),
+                        input_base: mode,
+                        input_error: None,
+                    };
+                }
+            }
+            Action::Chown => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                #[cfg(unix)]
+                self.open_chown();
+                #[cfg(not(unix))]
+                {
+                    self.status = Some("Changing owners needs a Unix system".to_string());
+                }
+            }
+            Action::CreateSymlink => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                if !cfg!(unix) {
+                    self.status = Some("Creating links needs a Unix system".to_string());
+                    return;
+                }
+                if let Some(entry) = self.current_target() {
+                    self.popup = PopupState::CreateSymlink {
+                        target: entry.path.clone(),
+                        input: String::new(),
+                        error: None,
+                    };
+                }
+            }
+            Action::MoveTo => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let paths = self.target_paths();
+                if !paths.is_empty() {
+                    self.popup = PopupState::MoveTo {
+                        paths,
+                        input: String::new(),
+                        error: None,
+                    };
+                }
+            }
+            Action::BulkRename => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let paths = self.target_paths();
+                if !paths.is_empty() {
+                    self.popup = PopupState::BulkRename {
+                        paths,
+                        input: String::new(),
+                        renames: Vec::new(),
+                        error: None,
+                    };
+                }
+            }
+            Action::GoToPath => {
+                self.popup = PopupState::GoToPath {
+                    input: String::new(),
+                    error: None,
+                };
+            }
+            Action::ExtractArchive => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let Some(entry) = self.current_target() else { return };
+                let Some(stem) = archive::stem(&entry.path) else {
+                    self.status = Some(format!("{} is not a zip or tar archive", entry.name));
+                    return;
+                };
+                let (src, dest) = (entry.path.clone(), self.cwd.join(&stem));
+                if dest.exists() {
+                    self.status = Some(format!("{} already exists", stem));
+                    return;
+                }
+                let name = entry.name.clone();
+                if self.dry_run_skip(format!("extract {} into {}/", name, stem)) {
+                    return;
+                }
+                self.status = Some(format!("Extracting {}...", name));
+                let tx = self.action_tx.clone();
+                std::thread::spawn(move || {
+                    let result = match ops::extract_archive(&src, &dest) {
+                        Ok(()) => Ok(format!("Extracted into {}/", stem)),
+                        Err(e) => {
+                            let _ = ops::delete_path(&dest); // drop the partial folder
+                            Err(format!("Extract failed: {}", e))
+                        }
+                    };
+                    let _ = tx.send(Action::ArchiveDone { created: dest, result });
+                });
+            }
+            Action::CompressSelection => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let paths = self.target_paths();
+                if paths.is_empty() {
+                    return;
+                }
+                let dest = self.cwd.join("archive.zip");
+                if dest.exists() {
+                    self.status = Some("archive.zip already exists".to_string());
+                    return;
+                }
+                if self.dry_run_skip(format!("compress {} item(s) into archive.zip", paths.len())) {
+                    return;
+                }
+                self.status = Some(format!("Compressing {} item(s)...", paths.len()));
+                self.selected.clear();
+                let tx = self.action_tx.clone();
+                std::thread::spawn(move || {
+                    let result = ops::create_archive(&paths, &dest, ArchiveKind::Zip)
+                        .map(|()| format!("Compressed {} item(s) into archive.zip", paths.len()))
+                        .map_err(|e| format!("Compress failed: {}", e));
+                    let _ = tx.send(Action::ArchiveDone { created: dest, result });
+                });
+            }
+            Action::ArchiveDone { created, result } => {
+                self.status = None; // "Extracting..." or "Compressing..."
+                let ok = result.is_ok();
+                match result {
+                    Ok(message) => {
+                        self.undo_stack.push(UndoOp::Copy {
+                            created: vec![created.clone()],
+                        });
+                        self.toast(ToastKind::Info, message);
+                    }
+                    Err(message) => self.toast(ToastKind::Error, message),
+                }
+                self.reload_after_change(std::slice::from_ref(&created));
+                if ok {
+                    self.reveal_paths(&[created]);
+                }
+            }
+            Action::Open => {
+                if let Some(entry) = self.current_target()
+                    && let Err(e) = ops::open_external(&entry.path)
+                {
+                    self.status = Some(format!("Couldn't open {}: {}", entry.name, e));
+                }
+            }
+            Action::OpenWith => {
+                if let Some(entry) = self.current_target() {
+                    self.popup = PopupState::OpenWith {
+                        path: entry.path.clone(),
+                        input: String::new(),
+                        typed: String::new(),
+                        cursor_idx: None,
+                    };
+                }
+            }
+            Action::EditFile => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let Some(entry) = self.current_target() else { return };
+                if entry.is_dir {
+                    self.status = Some(format!("{} is a directory", entry.name));
+                    return;
+                }
+                // $EDITOR may carry arguments, e.g. `code --wait`
+                let editor = std::env::var("EDITOR")
+                    .ok()
+                    .filter(|e| !e.trim().is_empty())
+                    .unwrap_or_else(|| "vi".to_string());
+                let mut words = editor.split_whitespace().map(str::to_string);
+                let program = words.next().unwrap_or_default();
+                let mut args: Vec<String> = words.collect();
+                args.push(entry.path.to_string_lossy().into_owned());
+                self.run_request = Some(ExternalCommand {
+                    program,
+                    args,
+                    background: false,
+                });
+            }
+            Action::ExternalCommandDone { program, result } => {
+                if let Err(e) = result {
+                    self.status = Some(format!("{}: {}", program, e));
+                }
+                // The program may well have changed files here
+                let cwd = self.cwd.clone();
+                self.reload_after_change(&[cwd]);
+            }
+            Action::ToggleHidden => {
+                self.show_hidden = !self.show_hidden;
+                self.reload_entries();
+            }
+            Action::ToggleFlatten => {
+                self.flatten = !self.flatten;
+                self.reload_entries();
+                let listed = self.entries.iter().filter(|e| !e.is_parent_link()).count();
+                if self.flatten && listed >= FLATTEN_MAX_ENTRIES {
+                    self.status =
+                        Some(format!("Flat view stops at {} entries", FLATTEN_MAX_ENTRIES));
+                }
+            }
+            Action::ToggleMiller => {
+                self.miller = !self.miller;
+                self.refresh_parent_entries();
+            }
+            Action::ResizeSplit { grow } => {
+                self.split_ratio = if grow {
+                    self.split_ratio.saturating_add(SPLIT_STEP)
+                } else {
+                    self.split_ratio.saturating_sub(SPLIT_STEP)
+                }
+                .clamp(SPLIT_MIN, SPLIT_MAX);
+            }
+            Action::ToggleLineNumbers => self.show_line_numbers = !self.show_line_numbers,
+            Action::TogglePreviewWrap => self.preview_wrap = !self.preview_wrap,
+            Action::CycleTheme => {
+                // Themes are kept sorted by name, so this walks them alphabetically
+                let names: Vec<&String> = self.theme_set.themes.keys().collect();
+                let next = names
+                    .iter()
+                    .position(|name| **name == self.theme_name)
+                    .map_or(0, |idx| (idx + 1) % names.len());
+                if let Some(name) = names.get(next) {
+                    self.theme_name = name.to_string();
+                    self.highlight_cache = HighlightCache::default();
+                    self.status = Some(format!("Theme: {}", self.theme_name));
+                }
+            }
+            Action::CycleSort => {
+                self.sort_mode = self.sort_mode.next();
+                self.reload_entries();
+            }
+            Action::ToggleSortReverse => {
+                self.sort_reversed = !self.sort_reversed;
+                self.reload_entries();
+            }
+            Action::NewTab => {
+                let tab = TabState {
+                    cwd: self.cwd.clone(),
+                    cursor: first_entry(&self.entries),
+                    ..TabState::default()
+                };
+                self.tabs.insert(self.active_tab + 1, tab);
+                self.switch_tab(self.active_tab + 1);
+            }
+            Action::CloseTab => {
+                if self.tabs.len() == 1 {
+                    self.status = Some("Can't close the last tab".to_string());
+                } else {
+                    self.tabs.remove(self.active_tab);
+                    let idx = self.active_tab.min(self.tabs.len() - 1);
+                    let next = std::mem::take(&mut self.tabs[idx]);
+                    self.active_tab = idx;
+                    self.load_tab(next);
+                }
+            }
+            Action::NextTab => {
+                self.switch_tab((self.active_tab + 1) % self.tabs.len());
+            }
+            Action::SwitchTab(idx) => self.switch_tab(idx),
+            Action::ToggleDualPane => {
+                if self.other_pane.take().is_some() {
+                    self.active_pane = 0;
+                } else {
+                    // Both panes start out in the current directory
+                    self.other_pane = Some(TabState {
+                        cwd: self.cwd.clone(),
+                        cursor: first_entry(&self.entries),
+                        ..TabState::default()
+                    });
+                    self.other_list_offset = 0;
+                    self.active_focus = ActiveFocus::FileList;
+                    self.preview = PreviewState::None;
+                    self.preview_search = None;
+                    self.reload_entries();
+                }
+            }
+            Action::SwitchPane => self.switch_pane(),
+            Action::CopyToPane => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let Some(dest_dir) = self.other_pane_cwd().map(Path::to_path_buf) else {
+                    return;
+                };
+                let paths = self.target_paths();
+                if !paths.is_empty() {
+                    self.selected.clear();
+                    self.start_copy(&paths, &dest_dir);
+                }
+            }
+            Action::MoveToPane => {
+                if self.readonly_refuse() {
+                    return;
+                }
+                let Some(dest_dir) = self.other_pane_cwd().map(Path::to_path_buf) else {
+                    return;
+                };
+                self.move_paths(self.target_paths(), &dest_dir);
+            }
+            Action::StartFilter => {
+                self.filter_mode = true;
+                self.visual_anchor = None;
+            }
+            Action::FilterInput(c) => {
+                self.filter.push(c);
+                self.clamp_cursor();
+            }
+            Action::FilterBackspace => {
+                self.filter.pop();
+                self.clamp_cursor();
+            }
+            Action::FilterConfirm => {
+                self.filter_mode = false;
+            }
+            Action::FilterCancel => {
+                self.filter_mode = false;
+                self.filter.clear();
+                self.clamp_cursor();
+            }
+            Action::StartCommand => {
+                self.command_mode = true;
+                self.command_input.clear();
+                self.command_history_idx = None;
+                self.command_completion = None;
+            }
+            Action::CommandInput(c) => {
+                self.command_input.push(c);
+                self.command_completion = None;
+            }
+            Action::CommandBackspace => {
+                self.command_completion = None;
+                // Backspacing past the `:` leaves command mode, as in vim
+                if self.command_input.pop().is_none() {
+                    self.command_mode = false;
+                }
+            }
+            Action::CommandHistoryPrev => {
+                self.command_completion = None;
+                let idx = match self.command_history_idx {
+                    Some(idx) => idx.saturating_sub(1),
+                    None => match self.command_history.len().checked_sub(1) {
+                        Some(last) => last,
+                        None => return,
+                    },
+                };
+                self.command_history_idx = Some(idx);
+                self.command_input = self.command_history[idx].clone();
+            }
+            Action::CommandHistoryNext => {
+                self.command_completion = None;
+                let Some(idx) = self.command_history_idx else { return };
+                if idx + 1 < self.command_history.len() {
+                    self.command_history_idx = Some(idx + 1);
+                    self.command_input = self.command_history[idx + 1].clone();
+                } else {
+                    // Past the newest entry is a blank line again
+                    self.command_history_idx = None;
+                    self.command_input.clear();
+                }
+            }
+            Action::CommandComplete => self.complete_command(),
+            Action::CommandSubmit => {
+                self.command_mode = false;
+                self.command_history_idx = None;
+                self.command_completion = None;
+                let line = std::mem::take(&mut self.command_input);
+                let line = line.trim();
+                if line.is_empty() {
+                    return;
+                }
+                self.command_history.retain(|old| old != line);
+                self.command_history.push(line.to_string());
+                if self.command_history.len() > COMMAND_HISTORY_LIMIT {
+                    self.command_history.remove(0);
+                }
+                if let Err(e) = self.run_ex_command(line) {
+                    self.status = Some(e);
+                }
+            }
+            Action::CommandCancel => {
+                self.command_mode = false;
+                self.command_input.clear();
+                self.command_history_idx = None;
+                self.command_completion = None;
+            }
+            Action::StartPreviewSearch => {
+                if let PreviewState::Ready(PreviewContent::Text { .. }) = self.preview {
+                    self.preview_search = Some(PreviewSearch::default());
+                    self.preview_search_mode = true;
+                }
+            }
+            Action::PreviewSearchInput(c) => {
+                if let Some(search) = &mut self.preview_search {
+                    search.query.push(c);
+                    self.update_preview_search();
+                }
+            }
+            Action::PreviewSearchBackspace => {
+                if let Some(search) = &mut self.preview_search {
+                    search.query.pop();
+                    self.update_preview_search();
+                }
+            }
+            Action::PreviewSearchConfirm => {
+                self.preview_search_mode = false;
+                if self.preview_search.as_ref().is_some_and(|s| s.query.is_empty()) {
+                    self.preview_search = None;
+                }
+            }
+            Action::PreviewSearchCancel => {
+                self.preview_search_mode = false;
+                self.preview_search = None;
+            }
+            Action::PreviewSearchNext | Action::PreviewSearchPrev => {
+                if let Some(search) = &mut self.preview_search
+                    && !search.matches.is_empty()
+                {
+                    let len = search.matches.len();
+                    search.current = if matches!(action, Action::PreviewSearchNext) {
+                        (search.current + 1) % len
+                    } else {
+                        (search.current + len - 1) % len
+                    };
+                    self.preview_scroll = search.matches[search.current];
+                }
+            }
+            Action::PreviewReady(content) => {
+                self.preview = PreviewState::Ready(content);
+                // Opened from a grep result: show the line, with the query marked
+                if let Some((_, line, query)) = self.preview_jump.take() {
+                    self.preview_scroll = line;
+                    self.clamp_preview_scroll();
+                    if let Some(query) = query {
+                        self.preview_search = Some(PreviewSearch {
+                            query,
+                            matches: Vec::new(),
+                            current: 0,
+                        });
+                        self.update_preview_search();
+                    }
+                }
+            }
+            Action::PreviewError { path, error } => {
+                self.preview_jump = None;
+                self.preview = PreviewState::Error {
+                    _path: path,
+                    message: error,
+                };
+            }
+            Action::SwitchFocus => {
+                self.active_focus = match self.active_focus {
+                    ActiveFocus::FileList => ActiveFocus::Preview,
+                    ActiveFocus::Preview => ActiveFocus::FileList,
+                };
+            }
+            Action::ScrollPreviewUp => {
+                if self.active_focus == ActiveFocus::Preview && self.preview_scroll > 0 {
+                    self.preview_scroll -= 1;
+                }
+            }
+            Action::ScrollPreviewDown => {
+                if self.active_focus == ActiveFocus::Preview {
+                    self.preview_scroll += 1;
+                    self.clamp_preview_scroll();
+                }
+            }
+            Action::ScrollPreviewPageUp => {
+                if self.active_focus == ActiveFocus::Preview {
+                    self.preview_scroll = self.preview_scroll.saturating_sub(self.preview_page());
+                }
+            }
+            Action::ScrollPreviewPageDown => {
+                if self.active_focus == ActiveFocus::Preview {
+                    self.preview_scroll += self.preview_page();
+                    self.clamp_preview_scroll();
+                }
+            }
+            Action::ScrollPreviewHalfPageUp => {
+                if self.active_focus == ActiveFocus::Preview {
+                    let half = self.preview_half_page();
+                    self.preview_scroll = self.preview_scroll.saturating_sub(half);
+                }
+            }
+            Action::ScrollPreviewHalfPageDown => {
+                if self.active_focus == ActiveFocus::Preview {
+                    self.preview_scroll += self.preview_half_page();
+                    self.clamp_preview_scroll();
+                }
+            }
+            Action::ScrollPreviewLeft => {
+                if self.active_focus == ActiveFocus::Preview {
+                    let step = match self.preview {
+                        PreviewState::Ready(PreviewContent::Text { .. }) => TEXT_HSCROLL_STEP,
+                        _ => 1,
+                    };
+                    self.preview_hscroll = self.preview_hscroll.saturating_sub(step);
+                }
+            }
+            Action::ScrollPreviewRight => {
+                if self.active_focus != ActiveFocus::Preview {
+                    return;
+                }
+                match &self.preview {
+                    // Keep at least one column of a table on screen
+                    PreviewState::Ready(PreviewContent::Table { headers, .. })
+                        if self.preview_hscroll + 1 < headers.len() =>
+                    {
+                        self.preview_hscroll += 1;
+                    }
+                    // ...and the end of the longest line. Wrapped text has nothing to pan
+                    PreviewState::Ready(PreviewContent::Text { content, .. })
+                        if !self.preview_wrap =>
+                    {
+                        let longest = content.lines().map(|l| l.chars().count()).max();
+                        let last = longest.unwrap_or(0).saturating_sub(1);
+                        self.preview_hscroll = (self.preview_hscroll + TEXT_HSCROLL_STEP).min(last);
+                    }
+                    _ => {}
+                }
+            }
+            Action::FuzzyFind => {
+                self.popup = PopupState::FuzzyFind {
+                    query: String::new(),
+                    candidates: None,
+                    results: Vec::new(),
+                    cursor_idx: 0,
+                };
+
+                let root = self.cwd.clone();
+                let tx = self.action_tx.clone();
+                std::thread::spawn(move || {
+                    let paths = WalkDir::new(&root)
+                        .min_depth(1)
+                        .max_depth(FUZZY_MAX_DEPTH)
+                        .into_iter()
+                        .filter_map(|e| e.ok())
+                        .take(FUZZY_MAX_FILES)
+                        .filter_map(|e| e.path().strip_prefix(&root).ok().map(Path::to_path_buf))
+                        .collect();
+                    let _ = tx.send(Action::FuzzyIndexReady { root, paths });
+                });
+            }
+            Action::Grep => {
+                self.popup = PopupState::Grep {
+                    query: String::new(),
+                    regex: false,
+                    searched: None,
+                    results: Vec::new(),
+                    cursor_idx: 0,
+                    running: false,
+                    truncated: false,
+                    error: None,
+                };
+            }
+            Action::GrepResults { id, matches } => {
+                if id == self.grep_id
+                    && let PopupState::Grep { results, .. } = &mut self.popup
+                {
+                    results.extend(matches);
+                }
+            }
+            Action::GrepDone { id, truncated } => {
+                if id != self.grep_id {
+                    return;
+                }
+                self.grep_cancel = None;
+                if let PopupState::Grep {
+                    running,
+                    truncated: cut,
+                    ..
+                } = &mut self.popup
+                {
+                    *running = false;
+                    *cut = truncated;
+                }
+            }
+            Action::Properties => {
+                if let Some(entry) = self.current_target()
+                    && let Ok(lines) = file_properties(
+                        &entry.path,
+                        &entry.permissions,
+                        #[cfg(unix)]
+                        &self.accounts,
+                    )
+                {
+                    self.popup = PopupState::Properties {
+                        path: entry.path.clone(),
+                        lines,
+                        scroll: 0,
+                    };
+                }
+            }
+            Action::ComputeDirSize => {
+                let Some(entry) = self.current_target().filter(|e| e.is_dir) else {
+                    return;
+                };
+                let path = entry.path.clone();
+                if self.dir_sizes.get(&path) == Some(&None) {
+                    return; // already running
+                }
+                self.status = Some(format!("Calculating size of {}...", entry.name));
+                self.dir_sizes.insert(path.clone(), None);
+                let tx = self.action_tx.clone();
+                std::thread::spawn(move || {
+                    let size = WalkDir::new(&path)
+                        .into_iter()
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.file_type().is_file())
+                        .filter_map(|e| e.metadata().ok())
+                        .map(|meta| meta.len())
+                        .sum();
+                    let _ = tx.send(Action::DirSizeReady { path, size });
+                });
+            }
+            Action::SummarizeSelection => {
+                let paths = self.target_paths();
+                if paths.is_empty() {
+                    return;
+                }
+                let mut cached = Tally::default();
+                let mut todo = Vec::new();
+                for path in &paths {
+                    match self.tallies.get(path) {
+                        Some(tally) => cached.add(*tally),
+                        None => todo.push(path.clone()),
+                    }
+                }
+                self.popup = PopupState::Summary {
+                    items: paths.len(),
+                    cached,
+                    scanned: Tally::default(),
+                    running: !todo.is_empty(),
+                };
+                if let Some(cancel) = self.summary_cancel.take() {
+                    cancel.store(true, atomic::Ordering::Relaxed);
+                }
+                if todo.is_empty() {
+                    return;
+                }
+                self.summary_id += 1;
+                let (id, tx) = (self.summary_id, self.action_tx.clone());
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.summary_cancel = Some(cancel.clone());
+                std::thread::spawn(move || {
+                    let mut done = Tally::default();
+                    let mut tallies = Vec::new();
+                    for path in todo {
+                        let tally = tally_tree(&path, &cancel, |partial| {
+                            let mut scanned = done;
+                            scanned.add(partial);
+                            let _ = tx.send(Action::SummaryProgress { id, scanned });
+                        });
+                        // A cut-short walk isn't worth keeping
+                        if cancel.load(atomic::Ordering::Relaxed) {
+                            return;
+                        }
+                        done.add(tally);
+                        tallies.push((path, tally));
+                    }
+                    let _ = tx.send(Action::SummaryReady { id, tallies });
+                });
+            }
+            Action::SummaryProgress { id, scanned: partial } => {
+                if let PopupState::Summary { scanned, .. } = &mut self.popup
+                    && id == self.summary_id
+                {
+                    *scanned = partial;
+                }
+            }
+            Action::SummaryReady { id, tallies } => {
+                for (path, tally) in &tallies {
+                    if tally.dirs > 0 {
+                        self.dir_sizes.insert(path.clone(), Some(tally.bytes));
+                    }
+                }
+                if id != self.summary_id {
+                    self.tallies.extend(tallies);
+                    return;
+                }
+                self.summary_cancel = None;
+                if let PopupState::Summary {
+                    cached,
+                    scanned,
+                    running,
+                    ..
+                } = &mut self.popup
+                {
+                    for (_, tally) in &tallies {
+                        cached.add(*tally);
+                    }
+                    *scanned = Tally::default();
+                    *running = false;
+                }
+                self.tallies.extend(tallies);
+            }
+            Action::DirChanged(dir) => {
+                // A late event for a directory we've since left
+                if dir != self.cwd {
+                    return;
+                }
+                self.reload_entries();
+            }
+            Action::GitStatusReady { dir, status } => {
+                if dir == self.cwd {
+                    self.git_status = status;
+                }
+            }
+            Action::DirSizeReady { path, size } => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.status = Some(format!("{}: {}", name, format_size(size)));
+                self.dir_sizes.insert(path, Some(size));
+            }
+            Action::TogglePin => {
+                let Some(entry) = self.current_entry().filter(|e| !e.is_parent_link()) else {
+                    return;
+                };
+                let (path, name) = (entry.path.clone(), entry.name.clone());
+                if self.pinned.remove(&path) {
+                    self.status = Some(format!("Unpinned {}", name));
+                } else {
+                    self.pinned.insert(path);
+                    self.status = Some(format!("Pinned {}", name));
+                }
+                if let Err(e) = config::save_pinned(&self.pinned) {
+                    self.status = Some(format!("Couldn't save pins: {}", e));
+                }
+                self.reload_entries();
+            }
+            Action::Bookmark => {
+                if self.bookmarks.contains(&self.cwd) {
+                    self.status = Some("Already bookmarked".to_string());
+                } else {
+                    self.bookmarks.push(self.cwd.clone());
+                    self.status = Some(format!("Bookmarked {}", self.cwd.display()));
+                    self.save_bookmarks();
+                }
+            }
+            Action::OpenBookmarks => {
+                if self.bookmarks.is_empty() {
+                    self.status = Some("No bookmarks yet (press m to add one)".to_string());
+                } else {
+                    self.popup = PopupState::Bookmarks { cursor_idx: 0 };
+                }
+            }
+            Action::OpenRecentDirs => {
+                // The top one is usually where we are; start on the one before
+                let skip = usize::from(self.recent_dirs.first() == Some(&self.cwd));
+                if self.recent_dirs.len() > skip {
+                    self.popup = PopupState::RecentDirs { cursor_idx: skip };
+                } else {
+                    self.status = Some("No other directories visited yet".to_string());
+                }
+            }
+            Action::RemoveBookmark => {
+                if let PopupState::Bookmarks { cursor_idx } = &mut self.popup
+                    && *cursor_idx < self.bookmarks.len()
+                {
+                    self.bookmarks.remove(*cursor_idx);
+                    *cursor_idx = (*cursor_idx).min(self.bookmarks.len().saturating_sub(1));
+                    if self.bookmarks.is_empty() {
+                        self.popup = PopupState::None;
+                    }
+                    self.save_bookmarks();
+                }
+            }
+            Action::FuzzyIndexReady { root, paths } => {
+                // Ignore a late index from a finder that has since been closed
+                if root == self.cwd
+                    && let PopupState::FuzzyFind {
+                        query,
+                        candidates,
+                        results,
+                        cursor_idx,
+                    } = &mut self.popup
+                {
+                    *results = fuzzy_filter(query, &paths);
+                    *candidates = Some(paths);
+                    *cursor_idx = 0;
+                }
+            }
+            Action::PopupInput(c) => {
+                if let PopupState::Chmod {
+                    mode,
+                    input,
+                    input_base,
+                    input_error,
+                    ..
+                } = &mut self.popup
+                {
+                    input.push(c);
+                    apply_mode_input(mode, input, *input_base, input_error);
+                }
+                if let PopupState::Chown {
+                    user,
+                    group,
+                    field,
+                    error,
+                    ..
+                } = &mut self.popup
+                {
+                    if *field == 0 { user } else { group }.push(c);
+                    *error = None;
+                }
+                if let PopupState::FuzzyFind {
+                    query,
+                    candidates,
+                    results,
+                    cursor_idx,
+                } = &mut self.popup
+                {
+                    query.push(c);
+                    if let Some(candidates) = candidates {
+                        *results = fuzzy_filter(query, candidates);
+                    }
+                    *cursor_idx = 0;
+                }
+                if let PopupState::OpenWith {
+                    input, cursor_idx, ..
+                } = &mut self.popup
+                {
+                    input.push(c);
+                    *cursor_idx = None;
+                }
+                if let PopupState::GoToPath { input, error }
+                | PopupState::MoveTo { input, error, .. }
+                | PopupState::CreateSymlink { input, error, .. } = &mut self.popup
+                {
+                    input.push(c);
+                    *error = None;
+                }
+                if let PopupState::BulkRename {
+                    paths,
+                    input,
+                    renames,
+                    error,
+                } = &mut self.popup
+                {
+                    input.push(c);
+                    preview_renames(paths, input, renames, error);
+                }
+                if let PopupState::Grep {
+                    query,
+                    cursor_idx,
+                    error,
+                    ..
+                } = &mut self.popup
+                {
+                    query.push(c);
+                    *cursor_idx = 0;
+                    *error = None;
+                }
+            }
+            Action::PopupBackspace => {
+                if let PopupState::Chmod {
+                    mode,
+                    input,
+                    input_base,
+                    input_error,
+                    ..
+                } = &mut self.popup
+                {
+                    input.pop();
+                    apply_mode_input(mode, input, *input_base, input_error);
+                }
+                if let PopupState::Chown {
+                    user,
+                    group,
+                    field,
+                    error,
+                    ..
+                } = &mut self.popup
+                {
+                    if *field == 0 { user } else { group }.pop();
+                    *error = None;
+                }
+                if let PopupState::FuzzyFind {
+                    query,
+                    candidates,
+                    results,
+                    cursor_idx,
+                } = &mut self.popup
+                {
+                    query.pop();
+                    if let Some(candidates) = candidates {
+                        *results = fuzzy_filter(query, candidates);
+                    }
+                    *cursor_idx = 0;
+                }
+                if let PopupState::OpenWith {
+                    input, cursor_idx, ..
+                } = &mut self.popup
+                {
+                    input.pop();
+                    *cursor_idx = None;
+                }
+                if let PopupState::GoToPath { input, error }
+                | PopupState::MoveTo { input, error, .. }
+                | PopupState::CreateSymlink { input, error, .. } = &mut self.popup
+                {
+                    input.pop();
+                    *error = None;
+                }
+                if let PopupState::BulkRename {
+                    paths,
+                    input,
+                    renames,
+                    error,
+                } = &mut self.popup
+                {
+                    input.pop();
+                    preview_renames(paths, input, renames, error);
+                }
+                if let PopupState::Grep {
+                    query,
+                    cursor_idx,
+                    error,
+                    ..
+                } = &mut self.popup
+                {
+                    query.pop();
+                    *cursor_idx = 0;
+                    *error = None;
+                }
+            }
+            Action::PopupUp => match &mut self.popup {
+                PopupState::Chmod { cursor_idx, .. } if *cursor_idx >= 3 => {
+                    *cursor_idx -= 3;
+                }
+                PopupState::Chown { field, .. } => *field = 0,
+                PopupState::Grep { cursor_idx, .. } => {
+                    *cursor_idx = cursor_idx.saturating_sub(1);
+                }
+                PopupState::FuzzyFind { cursor_idx, .. } => {
+                    *cursor_idx = cursor_idx.saturating_sub(1);
+                }
+                PopupState::Properties { scroll, .. } => {
+                    *scroll = scroll.saturating_sub(1);
+                }
+                PopupState::Bookmarks { cursor_idx } | PopupState::RecentDirs { cursor_idx } => {
+                    *cursor_idx = cursor_idx.saturating_sub(1);
+                }
+                // Up from the top of the list goes back to what was typed
+                PopupState::OpenWith {
+                    input,
+                    typed,
+                    cursor_idx: cursor_idx @ Some(_),
+                    ..
+                } => {
+                    *cursor_idx = cursor_idx.and_then(|idx| idx.checked_sub(1));
+                    *input = match *cursor_idx {
+                        Some(idx) => self.recent_commands[idx].clone(),
+                        None => typed.clone(),
+                    };
+                }
+                _ => {}
+            },
+            Action::PopupDown => match &mut self.popup {
+                PopupState::Chmod { cursor_idx, .. } if *cursor_idx < 9 => {
+                    *cursor_idx += 3;
+                }
+                PopupState::Chown { field, .. } => *field = 1,
+                PopupState::Grep {
+                    results,
+                    cursor_idx,
+                    ..
+                } if *cursor_idx + 1 < results.len() => {
+                    *cursor_idx += 1;
+                }
+                PopupState::FuzzyFind {
+                    results,
+                    cursor_idx,
+                    ..
+                } if *cursor_idx + 1 < results.len() => {
+                    *cursor_idx += 1;
+                }
+                PopupState::Properties { lines, scroll, .. } if *scroll + 1 < lines.len() => {
+                    *scroll += 1;
+                }
+                PopupState::Bookmarks { cursor_idx } if *cursor_idx + 1 < self.bookmarks.len() => {
+                    *cursor_idx += 1;
+                }
+                PopupState::RecentDirs { cursor_idx }
+                    if *cursor_idx + 1 < self.recent_dirs.len() =>
+                {
+                    *cursor_idx += 1;
+                }
+                PopupState::OpenWith {
+                    input,
+                    typed,
+                    cursor_idx,
+                    ..
+                } => {
+                    let next = cursor_idx.map_or(0, |idx| idx + 1);
+                    if let Some(command) = self.recent_commands.get(next) {
+                        if cursor_idx.is_none() {
+                            *typed = input.clone();
+                        }
+                        *cursor_idx = Some(next);
+                        *input = command.clone();
+                    }
+                }
+                _ => {}
+            },
+            Action::PopupLeft => {
+                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup
+                    && *cursor_idx % 3 > 0
+                {
+                    *cursor_idx -= 1;
+                }
+            }
+            Action::PopupRight => {
+                if let PopupState::Chmod { cursor_idx, .. } = &mut self.popup
+                    && *cursor_idx % 3 < 2
+                {
+                    *cursor_idx += 1;
+                }
+            }
+            Action::PopupToggle => {
+                if let PopupState::Grep { regex, error, .. } = &mut self.popup {
+                    *regex = !*regex;
+                    *error = None;
+                }
+                if let PopupState::Chmod {
+                    mode,
+                    cursor_idx,
+                    input,
+                    input_base,
+                    input_error,
+                    ..
+                } = &mut self.popup
+                {
+                    // Mapping idx 0-8 to mode bits
+                    // Grid:
+                    // Owner: R(0), W(1), X(2) -> 400, 200, 100
+                    // Group: R(3), W(4), X(5) -> 040, 020, 010
+                    // Other: R(6), W(7), X(8) -> 004, 002, 001
+                    // Special: SUID(9), SGID(10), sticky(11) -> 4000, 2000, 1000
+                    
+                    let bit = match cursor_idx {
+                        0 => 0o400, 1 => 0o200, 2 => 0o100,
+                        3 => 0o040, 4 => 0o020, 5 => 0o010,
+                        6 => 0o004, 7 => 0o002, 8 => 0o001,
+                        9 => 0o4000, 10 => 0o2000, 11 => 0o1000,
+                        _ => 0,
+                    };
+                    
+                    if bit != 0 {
+                        *mode ^= bit; // Toggle bit
+                        // The grid wins over whatever was typed
+                        input.clear();
+                        *input_base = *mode;
+                        *input_error = None;
+                    }
+                }
+            }
+            Action::PopupSubmit => {
+                if let PopupState::FuzzyFind {
+                    results,
+                    cursor_idx,
+                    ..
+                } = &self.popup
+                    && let Some(rel) = results.get(*cursor_idx)
+                {
+                    let target = self.cwd.join(rel);
+                    self.reveal_path(&target);
+                }
+                if let PopupState::Bookmarks { cursor_idx } = &self.popup
+                    && let Some(dir) = self.bookmarks.get(*cursor_idx).cloned()
+                {
+                    // Leave the popup open on a dead bookmark so it can be removed
+                    if !dir.is_dir() {
+                        self.status = Some(format!("{} no longer exists", dir.display()));
+                        return;
+                    }
+                    if let Err(e) = self.change_dir(dir) {
+                        self.status = Some(format!("Can't open bookmark: {}", e));
+                    }
+                }
+                if let PopupState::RecentDirs { cursor_idx } = &mut self.popup
+                    && let Some(dir) = self.recent_dirs.get(*cursor_idx).cloned()
+                {
+                    // Drop a dead one, and stay open to pick another
+                    if !dir.is_dir() {
+                        self.recent_dirs.remove(*cursor_idx);
+                        *cursor_idx = (*cursor_idx).min(self.recent_dirs.len().saturating_sub(1));
+                        if self.recent_dirs.is_empty() {
+                            self.popup = PopupState::None;
+                        }
+                        self.status = Some(format!("{} no longer exists", dir.display()));
+                        return;
+                    }
+                    if let Err(e) = self.change_dir(dir) {
+                        self.status = Some(format!("Can't open directory: {}", e));
+                    }
+                }
+                if let PopupState::ConfirmQuit { .. } = &self.popup {
+                    self.should_quit = true;
+                }
+                if let PopupState::ConfirmCopyText { .. } = &self.popup {
+                    self.copy_preview_text();
+                }
+                if let PopupState::ConfirmDelete { paths } = &self.popup {
+                    self.delete_paths(paths.clone());
+                }
+                if let PopupState::Grep { .. } = &self.popup
+                    && !self.submit_grep()
+                {
+                    return; // searching, or a bad regex; either way it stays open
+                }
+                if let PopupState::BulkRename { paths, input, .. } = &self.popup {
+                    let (paths, input) = (paths.clone(), input.clone());
+                    if let Err(e) = self.bulk_rename(&paths, &input)
+                        && let PopupState::BulkRename { error, .. } = &mut self.popup
+                    {
+                        *error = Some(e);
+                        return;
+                    }
+                }
+                #[cfg(unix)]
+                if let PopupState::CreateSymlink { target, input, .. } = &self.popup {
+                    let (target, name) = (target.clone(), input.trim().to_string());
+                    if let Err(e) = self.create_symlink(&target, &name)
+                        && let PopupState::CreateSymlink { error, .. } = &mut self.popup
+                    {
+                        *error = Some(e);
+                        return;
+                    }
+                }
+                if let PopupState::MoveTo { paths, input, .. } = &self.popup {
+                    let (paths, typed) = (paths.clone(), input.trim().to_string());
+                    match self.move_to(paths, &typed) {
+                        Err(e) => {
+                            if let PopupState::MoveTo { error, .. } = &mut self.popup {
+                                *error = Some(e);
+                            }
+                            return;
+                        }
+                        // Carrying on in the background, behind the progress popup
+                        Ok(()) if self.copy_cancel.is_some() => return,
+                        Ok(()) => {}
+                    }
+                }
+                if let PopupState::GoToPath { input, .. } = &self.popup {
+                    let typed = input.trim().to_string();
+                    if let Err(e) = self.go_to_path(&typed)
+                        && let PopupState::GoToPath { error, .. } = &mut self.popup
+                    {
+                        *error = Some(e);
+                        return; // stays open to fix the path
+                    }
+                }
+                if let PopupState::OpenWith { path, input, .. } = &self.popup {
+                    // A trailing `&` detaches, like in a shell
+                    let command = input.trim();
+                    let (command, background) = match command.strip_suffix('&') {
+                        Some(rest) => (rest.trim_end(), true),
+                        None => (command, false),
+                    };
+                    let mut words = command.split_whitespace().map(str::to_string);
+                    let Some(program) = words.next() else {
+                        return; // nothing typed yet
+                    };
+                    let mut args: Vec<String> = words.collect();
+                    args.push(path.to_string_lossy().into_owned());
+                    self.run_request = Some(ExternalCommand {
+                        program,
+                        args,
+                        background,
+                    });
+
+                    let input = input.trim().to_string();
+                    self.recent_commands.retain(|c| *c != input);
+                    self.recent_commands.insert(0, input);
+                    self.recent_commands.truncate(RECENT_COMMANDS_LIMIT);
+                    if let Err(e) = config::save_recent_commands(&self.recent_commands) {
+                        self.status = Some(format!("Couldn't save recent commands: {}", e));
+                    }
+                }
+                #[cfg(unix)]
+                if let PopupState::Chown { .. } = &self.popup
+                    && !self.apply_chown()
+                {
+                    return; // bad name; the popup shows why
+                }
+                if let PopupState::Chmod { input_error: Some(_), .. } = &self.popup {
+                    // Keep the popup open until the typed mode is fixed or cleared
+                    return;
+                }
+                if let PopupState::Chmod { paths, mode, .. } = &self.popup {
+                    let (paths, mode) = (paths.clone(), *mode);
+                    let action = format!("chmod {:o}", mode & 0o7777);
+                    if self.dry_run_skip(format!("{} {} item(s)", action, paths.len())) {
+                        self.popup = PopupState::None;
+                        return;
+                    }
+                    let failed = paths
+                        .iter()
+                        .filter(|path| {
+                            let result = ops::set_permissions(path, mode);
+                            self.log_op(&action, path, None, &result);
+                            result.is_err()
+                        })
+                        .count();
+                    let (kind, message) = if failed == 0 {
+                        let message =
+                            format!("chmod {:o}: {} updated", mode & 0o7777, paths.len());
+                        (ToastKind::Info, message)
+                    } else {
+                        let message = format!(
+                            "chmod {:o}: {} updated, {} failed",
+                            mode & 0o7777,
+                            paths.len() - failed,
+                            failed
+                        );
+                        (ToastKind::Error, message)
+                    };
+                    self.toast(kind, message);
+                    // Reload to update UI
+                    self.reload_after_change(&[]);
+                }
+                self.popup = PopupState::None;
+            }
+            Action::PopupCancel => {
+                // Staying puts back whatever the quit prompt covered
+                if let PopupState::ConfirmQuit { .. } = self.popup {
+                    self.popup = PopupState::None;
+                    if self.copy_cancel.is_some() {
+                        self.popup = PopupState::Progress {
+                            done: 0,
+                            total: 0,
+                            current: String::new(),
+                        };
+                    } else if self.pending_paste.is_some() {
+                        self.resume_paste(None);
+                    }
+                    return;
+                }
+                let running = [self.grep_cancel.take(), self.summary_cancel.take()];
+                for cancel in running.into_iter().flatten() {
+                    cancel.store(true, atomic::Ordering::Relaxed);
+                }
+                // Nothing is copied until every conflict is settled
+                if self.pending_paste.take().is_some() {
+                    self.toast(ToastKind::Info, "Paste cancelled".to_string());
+                }
+                self.popup = PopupState::None;
+            }
+            Action::MouseClick { column, row } => {
+                self.clicked_entry = None;
+                if contains(self.list_area, column, row) {
+                    self.active_focus = ActiveFocus::FileList;
+                    // Skip the top border and the header row, and stop above the bottom one
+                    let first_row = self.list_area.y + 2;
+                    let last_row = self.list_area.bottom().saturating_sub(2);
+                    if (first_row..=last_row).contains(&row) {
+                        let idx = self.list_offset + (row - first_row) as usize;
+                        if idx < self.visible_entries().len() {
+                            self.cursor = idx;
+                            self.clicked_entry = Some(idx);
+                            self.update_visual_selection();
+                        }
+                    }
+                } else if contains(self.preview_area, column, row) {
+                    // In dual-pane mode that's the other list
+                    if self.other_pane.is_some() {
+                        self.switch_pane();
+                    } else {
+                        self.active_focus = ActiveFocus::Preview;
+                    }
+                }
+            }
+            Action::MouseScroll { column, row, down } => {
+                // The wheel works on whichever pane is under the pointer
+                if contains(self.list_area, column, row) {
+                    self.active_focus = ActiveFocus::FileList;
+                    self.reduce(if down { Action::CursorMoveDown } else { Action::CursorMoveUp });
+                } else if self.other_pane.is_none() && contains(self.preview_area, column, row) {
+                    self.active_focus = ActiveFocus::Preview;
+                    for _ in 0..3 {
+                        self.reduce(if down {
+                            Action::ScrollPreviewDown
+                        } else {
+                            Action::ScrollPreviewUp
+                        });
+                    }
+                }
+            }
+            Action::RequestQuit => match (&self.popup, self.quit_warning()) {
+                // A second `q` confirms
+                (PopupState::ConfirmQuit { .. }, _) | (_, None) => self.should_quit = true,
+                (_, Some(reason)) => self.popup = PopupState::ConfirmQuit { reason },
+            },
+        }
+    }
+}
+
+/// Re-derives the bulk rename popup's old -> new list from the typed pattern.
+/// A bad pattern empties the list; a clash keeps it so the culprit shows.
+fn preview_renames(
+    paths: &[PathBuf],
+    input: &str,
+    renames: &mut Vec<(String, String)>,
+    error: &mut Option<String>,
+) {
+    renames.clear();
+    *error = None;
+    if input.is_empty() {
+        return;
+    }
+    let pattern = match rename::RenamePattern::parse(input) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            *error = Some(e);
+            return;
+        }
+    };
+    let plan = rename::plan(paths, &pattern);
+    for (path, new_name) in &plan {
+        let old = path.file_name().unwrap_or_default().to_string_lossy();
+        renames.push((old.into_owned(), new_name.clone()));
+    }
+    *error = rename::check(&plan).err();
+}
+
+/// Re-derives the chmod popup's mode from the typed expression. An invalid
+/// expression leaves `mode` at its last valid value and reports the error.
+fn apply_mode_input(mode: &mut u32, input: &str, base: u32, error: &mut Option<String>) {
+    if input.is_empty() {
+        *mode = base;
+        *error = None;
+        return;
+    }
+    match ops::parse_symbolic(base, input) {
+        Ok(new_mode) => {
+            *mode = new_mode;
+            *error = None;
+        }
+        Err(e) => *error = Some(e),
+    }
+}
+
+/// Reverts `op`, last item first. Copies go to `trash` when there is one,
+/// so edits made to them since aren't lost. On a failure the error comes
+/// back with what's left to revert, the failed item included.
+fn undo_op(
+    op: UndoOp,
+    log: Option<&Path>,
+    trash: Option<&Path>,
+) -> Result<(), (std::io::Error, UndoOp)> {
+    match op {
+        UndoOp::Copy { mut created } => {
+            while let Some(path) = created.last() {
+                let result = match trash {
+                    Some(trash) => trash::trash(trash, path).map(|_| ()),
+                    None => ops::delete_path(path),
+                };
+                oplog::log_op(log, "undo copy", path, None, &result);
+                if let Err(e) = result {
+                    return Err((e, UndoOp::Copy { created }));
+                }
+                created.pop();
+            }
+        }
+        UndoOp::Rename { from, to } => {
+            let result = ops::rename(&to, &from);
+            oplog::log_op(log, "undo rename", &to, Some(&from), &result);
+            if let Err(e) = result {
+                return Err((e, UndoOp::Rename { from, to }));
+            }
+        }
+        UndoOp::Move { mut moves } => {
+            while let Some((src, dst)) = moves.last() {
+                let result = ops::move_path(dst, src);
+                oplog::log_op(log, "undo move", dst, Some(src), &result);
+                if let Err(e) = result {
+                    return Err((e, UndoOp::Move { moves }));
+                }
+                moves.pop();
+            }
+        }
+        UndoOp::Trash { mut items } => {
+            while let Some((original, trashed)) = items.last() {
+                let result = trash::restore(trashed, original);
+                oplog::log_op(log, "undo trash", trashed, Some(original), &result);
+                if let Err(e) = result {
+                    return Err((e, UndoOp::Trash { items }));
+                }
+                items.pop();
+            }
+        }
+        UndoOp::Irreversible { .. } => {}
+    }
+    Ok(())
+}
+
+pub trait PreviewLoader {
+    fn load(&self, path: PathBuf) -> Result<PreviewContent, String>;
+}
+
+pub struct DefaultPreviewLoader {
+    pub max_bytes: u64, // longer files are cut off
+    pub thumbnails: bool, // decode images for `Thumbnail::render`
+}
+
+impl Default for DefaultPreviewLoader {
+    fn default() -> Self {
+        DefaultPreviewLoader {
+            max_bytes: config::DEFAULT_PREVIEW_MAX_BYTES,
+            thumbnails: true,
+        }
+    }
+}
+
+impl PreviewLoader for DefaultPreviewLoader {
+    fn load(&self, path: PathBuf) -> Result<PreviewContent, String> {
+        let title = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        if path.is_dir() {
+            return Ok(PreviewContent::Text {
+                title,
+                content: dir_tree(&path),
+                encoding: None,
+            });
+        }
+
+        // Try to load as image first
+        if let Ok(reader) = image::ImageReader::open(&path)
+            && let Ok(reader) = reader.with_guessed_format()
+            && let Some(format) = reader.format()
+            && let Ok(decoder) = reader.into_decoder()
+        {
+            // Only the header is read here, not the pixel data
+            let (width, height) = decoder.dimensions();
+            return Ok(PreviewContent::Image {
+                title: title.clone(),
+                path: path.clone(),
+                width,
+                height,
+                format: format!("{:?}", format).to_uppercase(),
+                color_type: color_type_label(decoder.color_type()).to_string(),
+                thumbnail: self.thumbnails.then(|| Thumbnail::load(&path)).flatten(),
+            });
+        }
+
+        // Fallback: Check extension if image loading failed/wasn't supported format
         if let Some(ext) = path
             .extension()
             .and_then(|e| e.to_str())
             .map(|e| e.to_lowercase())
         {
-            match as_ref(ext.as_str()) {
-                "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "ico" | "tiff" => {
-                    return Ok(PreviewContent::Image {
-                        title,
-                        width: 0,  // Unknown
-                        height: 0, // Unknown
-                        color_type: "Unknown (Metadata Load Failed)".to_string(),
-                    });
+            match as_ref(ext.as_str()) {
+                "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "ico" | "tiff" => {
+                    return Ok(PreviewContent::Image {
+                        title,
+                        path: path.clone(),
+                        width: 0,  // Unknown
+                        height: 0, // Unknown
+                        format: ext.to_uppercase(),
+                        color_type: "Unknown (Metadata Load Failed)".to_string(),
+                        thumbnail: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        fn as_ref(s: &str) -> &str {
+            s
+        }
+
+        if let Some(kind) = ArchiveKind::from_path(&path) {
+            let (entries, truncated) = archive::list(&path, kind, ARCHIVE_MAX_ENTRIES)
+                .map_err(|e| format!("Cannot read archive: {}", e))?;
+            return Ok(PreviewContent::Archive {
+                title,
+                entries,
+                truncated,
+            });
+        }
+
+        if media::is_media(&path)
+            && let Some(fields) = media::probe(&path)
+        {
+            return Ok(PreviewContent::Media { title, fields });
+        }
+
+        // A quick look at the start settles most binaries without a full read,
+        // including ones that happen to be valid UTF-8
+        let head = read_prefix(&path, BINARY_SNIFF_BYTES).map_err(|e| e.to_string())?;
+        if looks_binary(&head) {
+            let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+            return Ok(PreviewContent::Binary {
+                title,
+                size: meta.len(),
+            });
+        }
+
+        match read_text_prefix(&path, self.max_bytes) {
+            Ok(content) if is_markdown(&path) => Ok(PreviewContent::Markdown {
+                title,
+                lines: markdown::render(&content),
+            }),
+            Ok(content) if let Some(delim) = table_delimiter(&path) => {
+                let (mut rows, truncated) = parse_delimited(&content, delim, TABLE_MAX_ROWS + 1);
+                let headers = if rows.is_empty() { Vec::new() } else { rows.remove(0) };
+                Ok(PreviewContent::Table {
+                    title,
+                    headers,
+                    rows,
+                    truncated,
+                })
+            }
+            Ok(content) => {
+                let content = pretty_json(&path, &content).unwrap_or(content);
+                // Return raw content regardless of extension for now.
+                // draw_preview handles highlighting.
+                Ok(PreviewContent::Text {
+                    title,
+                    content,
+                    encoding: None,
+                })
+            }
+            Err(_) => {
+                // Not UTF-8: try the other encodings before calling it binary
+                if let Ok(bytes) = read_prefix(&path, self.max_bytes)
+                    && let Some((content, encoding)) = decode_non_utf8(&bytes)
+                {
+                    return Ok(PreviewContent::Text {
+                        title,
+                        content,
+                        encoding: Some(encoding.to_string()),
+                    });
+                }
+                let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+
+                Ok(PreviewContent::Binary {
+                    title,
+                    size: meta.len(),
+                })
+            }
+        }
+    }
+}
+
+/// The tree under `root`, a few levels deep, as preview text. Stops at
+/// `TREE_MAX_LINES` (counting what's left over) or `TREE_TIMEOUT`, whichever
+/// comes first, so `node_modules` and friends don't stall the preview.
+fn dir_tree(root: &Path) -> String {
+    let deadline = Instant::now() + TREE_TIMEOUT;
+    let mut tree = String::new();
+    let (mut lines, mut more, mut timed_out) = (0, 0, false);
+    let mut walk = WalkDir::new(root).min_depth(1).max_depth(TREE_MAX_DEPTH).into_iter();
+    while let Some(entry) = walk.next() {
+        let Ok(entry) = entry else { continue };
+        if Instant::now() > deadline {
+            timed_out = true;
+            break;
+        }
+        let huge = entry.file_type().is_dir()
+            && entry.depth() < TREE_MAX_DEPTH
+            && std::fs::read_dir(entry.path())
+                .is_ok_and(|dir| dir.take(TREE_HUGE_DIR + 1).count() > TREE_HUGE_DIR);
+        if huge {
+            walk.skip_current_dir();
+        }
+        if lines == TREE_MAX_LINES {
+            more += 1;
+            continue;
+        }
+        lines += 1;
+        let indent = "  ".repeat(entry.depth() - 1);
+        let name = entry.file_name().to_string_lossy();
+        let note = if huge {
+            format!(" ({}+ items, not opened)", TREE_HUGE_DIR)
+        } else {
+            String::new()
+        };
+        tree.push_str(&format!("{}|-- {}{}\n", indent, name, note));
+    }
+    match (more, timed_out) {
+        (0, false) => {}
+        (_, false) => tree.push_str(&format!("…and {} more\n", more)),
+        (0, true) => tree.push_str("…and more, not listed in time\n"),
+        (_, true) => tree.push_str(&format!("…and over {} more, not listed in time\n", more)),
+    }
+    tree
+}
+
+/// Reads at most `limit` bytes of `path`.
+fn read_prefix(path: &Path, limit: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?.take(limit).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Like `fs::read_to_string`, but stops after `limit` bytes. A character the
+/// limit cuts in half is dropped instead of making the file look non-UTF-8.
+fn read_text_prefix(path: &Path, limit: u64) -> std::io::Result<String> {
+    String::from_utf8(read_prefix(path, limit)?).or_else(|e| {
+        let error = e.utf8_error();
+        match error.error_len() {
+            None => Ok(String::from_utf8_lossy(&e.as_bytes()[..error.valid_up_to()]).into_owned()),
+            Some(_) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error)),
+        }
+    })
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.x + area.width).contains(&column) && (area.y..area.y + area.height).contains(&row)
+}
+
+/// Whether `bytes` (usually the start of a file) look like binary data: any
+/// NUL, or more than one control character in ten. UTF-8 and the legacy
+/// encodings never produce NULs; UTF-16 does, but is let through on its BOM.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    if encoding_rs::Encoding::for_bom(bytes).is_some_and(|(enc, _)| enc != encoding_rs::UTF_8) {
+        return false;
+    }
+    let controls = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    bytes.contains(&0) || controls * 10 > bytes.len()
+}
+
+/// Decodes text that isn't UTF-8: UTF-16 and friends when there's a BOM,
+/// otherwise the legacy encoding chardetng guesses (Latin-1, Shift_JIS, ...).
+/// `None` when the bytes look like binary data rather than text.
+fn decode_non_utf8(bytes: &[u8]) -> Option<(String, &'static str)> {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+        return (!had_errors).then(|| (text.into_owned(), encoding.name()));
+    }
+
+    if looks_binary(bytes) {
+        return None;
+    }
+
+    // UTF-8 has already failed by the time we get here
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Allow);
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, Utf8Detection::Deny);
+    let (text, _, had_errors) = encoding.decode(bytes);
+    (!had_errors).then(|| (text.into_owned(), encoding.name()))
+}
+
+/// Re-indents JSON with two spaces, keeping key order. `None` for files that
+/// aren't JSON, are too big, or don't parse; those are shown as-is.
+fn pretty_json(path: &Path, content: &str) -> Option<String> {
+    let json_ext = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let looks_like_json = matches!(content.trim_start().chars().next(), Some('{' | '['));
+    if content.len() > JSON_PRETTY_MAX_BYTES || !(json_ext || looks_like_json) {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+fn table_delimiter(path: &Path) -> Option<char> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Splits delimiter-separated text into records, honouring double-quoted
+/// fields (which may contain the delimiter, newlines and `""` escapes).
+/// Stops after `max_rows` records; the flag says whether anything was left.
+pub fn parse_delimited(text: &str, delim: char, max_rows: usize) -> (Vec<Vec<String>>, bool) {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                if rows.len() == max_rows {
+                    return (rows, chars.next().is_some());
+                }
+            }
+            c if c == delim => row.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    (rows, false)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_lowercase().as_str(), "md" | "markdown"))
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence match.
+/// Returns `None` when not every query character appears in order. Higher is
+/// better: consecutive runs and matches right after a separator score extra,
+/// and gaps cost a little.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let chars: Vec<char> = candidate.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut score = 0i64;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+
+    for q in query.chars().map(|c| c.to_ascii_lowercase()) {
+        let idx = pos + chars[pos..].iter().position(|&c| c == q)?;
+        score += 1;
+        match prev_match {
+            Some(p) if p + 1 == idx => score += 5,
+            Some(p) => score -= (idx - p - 1).min(5) as i64,
+            None => {}
+        }
+        if idx == 0 || matches!(chars[idx - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        prev_match = Some(idx);
+        pos = idx + 1;
+    }
+    Some(score)
+}
+
+/// Ranks `candidates` against `query`, best first, capped at `FUZZY_MAX_RESULTS`.
+fn fuzzy_filter(query: &str, candidates: &[PathBuf]) -> Vec<PathBuf> {
+    let mut scored: Vec<(i64, &PathBuf)> = candidates
+        .iter()
+        .filter_map(|p| fuzzy_score(query, &p.to_string_lossy()).map(|s| (s, p)))
+        .collect();
+    // Prefer shorter paths among equal scores
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(a.1.as_os_str().len().cmp(&b.1.as_os_str().len()))
+    });
+    scored
+        .into_iter()
+        .take(FUZZY_MAX_RESULTS)
+        .map(|(_, p)| p.clone())
+        .collect()
+}
+
+/// Human-readable name for a decoded pixel layout, e.g. `RGBA8`.
+fn color_type_label(color: image::ColorType) -> &'static str {
+    use image::ColorType;
+    match color {
+        ColorType::L8 => "Grayscale",
+        ColorType::La8 => "Grayscale + Alpha",
+        ColorType::Rgb8 => "RGB8",
+        ColorType::Rgba8 => "RGBA8",
+        ColorType::L16 => "Grayscale16",
+        ColorType::La16 => "Grayscale16 + Alpha",
+        ColorType::Rgb16 => "RGB16",
+        ColorType::Rgba16 => "RGBA16",
+        ColorType::Rgb32F => "RGB32F",
+        ColorType::Rgba32F => "RGBA32F",
+        _ => "Other",
+    }
+}
+
+/// `ls -l` style permissions, e.g. `drwxr-xr-x`. Setuid/setgid/sticky show
+/// in the execute slots as `s`/`t`, or `S`/`T` when execute itself is off.
+pub fn format_permissions(mode: u32, is_dir: bool) -> String {
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    let exec = |mask: u32, special: u32, c: char| match (mode & mask != 0, mode & special != 0) {
+        (true, true) => c,
+        (false, true) => c.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+    [
+        if is_dir { 'd' } else { '-' },
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        exec(0o100, 0o4000, 's'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        exec(0o010, 0o2000, 's'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        exec(0o001, 0o1000, 't'),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Formats a byte count the way `ls -h` does: `512B`, `4.0K`, `1.2M`, `3.4G`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Compact age of `t` relative to now: `42s ago`, `5m ago`, `2h ago`, `3d ago`,
+/// then `Jan 12` within the last year and `Jan 2023` beyond that.
+pub fn format_relative_time(t: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    // Timestamps in the future (clock skew) count as just modified
+    let age = SystemTime::now()
+        .duration_since(t)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    match age {
+        0..60 => return format!("{}s ago", age),
+        60..3600 => return format!("{}m ago", age / 60),
+        3600..86_400 => return format!("{}h ago", age / 3600),
+        86_400..604_800 => return format!("{}d ago", age / 86_400),
+        _ => {}
+    }
+
+    let secs = t
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_else(|e| -(e.duration().as_secs() as i64));
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    if age < 365 * 86_400 {
+        format!("{} {}", MONTHS[month as usize - 1], day)
+    } else {
+        format!("{} {}", MONTHS[month as usize - 1], year)
+    }
+}
+
+/// Formats `t` as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_timestamp(t: SystemTime) -> String {
+    let secs = t
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_else(|e| -(e.duration().as_secs() as i64));
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// Collects the label/value rows shown in the properties popup.
+fn file_properties(
+    path: &Path,
+    permissions: &str,
+    #[cfg(unix)] accounts: &crate::users::Accounts,
+) -> std::io::Result<Vec<(String, String)>> {
+    let link_meta = std::fs::symlink_metadata(path)?;
+    // Follow links for everything but the link rows; a dangling link falls back to itself
+    let meta = std::fs::metadata(path).unwrap_or_else(|_| link_meta.clone());
+    let time = |t: std::io::Result<SystemTime>| {
+        t.map(format_timestamp)
+            .unwrap_or_else(|_| "unavailable".to_string())
+    };
+
+    let mut lines = vec![("Path".to_string(), path.display().to_string())];
+    if link_meta.file_type().is_symlink() {
+        let target = std::fs::read_link(path)
+            .map(|t| t.display().to_string())
+            .unwrap_or_else(|e| e.to_string());
+        let resolved = std::fs::canonicalize(path)
+            .map(|t| t.display().to_string())
+            .unwrap_or_else(|_| "(broken link)".to_string());
+        lines.push(("Link target".to_string(), target));
+        lines.push(("Resolved".to_string(), resolved));
+    }
+    lines.extend([
+        (
+            "Type".to_string(),
+            if meta.is_dir() { "Directory" } else { "File" }.to_string(),
+        ),
+        (
+            "Size".to_string(),
+            format!("{} ({} bytes)", format_size(meta.len()), meta.len()),
+        ),
+        (
+            "Permissions".to_string(),
+            format!("{} ({:04o})", permissions, ops::mode_of(&meta) & 0o7777),
+        ),
+    ]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let owner = format!("{} (uid {})", accounts.user_label(meta.uid()), meta.uid());
+        let group = format!("{} (gid {})", accounts.group_label(meta.gid()), meta.gid());
+        lines.push(("Owner".to_string(), owner));
+        lines.push(("Group".to_string(), group));
+    }
+    lines.extend([
+        ("Modified".to_string(), time(meta.modified())),
+        ("Accessed".to_string(), time(meta.accessed())),
+        ("Created".to_string(), time(meta.created())),
+    ]);
+    Ok(lines)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days_from_civil inverse
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The longest string every item starts with.
+fn common_prefix(items: &[String]) -> String {
+    let Some((first, rest)) = items.split_first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for item in rest {
+        len = first
+            .char_indices()
+            .zip(item.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(item.len()), |((i, _), _)| i.min(len));
+    }
+    first[..len].to_string()
+}
+
+/// `~` and `~/...` with the home directory put in; anything else (including
+/// `~user`) comes back as it is. None if it needs a home that isn't set.
+pub fn expand_tilde(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => {
+            Some(home_dir()?.join(rest.trim_start_matches(std::path::is_separator)))
+        }
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Expands `{a,b}` groups into one pattern per alternative, since the glob
+/// crate has no alternation: `*.{png,jpg}` gives `*.png` and `*.jpg`. Groups
+/// can't nest.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let Some(close) = pattern[open..].find('}').map(|len| open + len) else {
+        return vec![pattern.to_string()];
+    };
+    let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", head, alt, tail)))
+        .collect()
+}
+
+/// Where the cursor starts in a freshly opened directory: on the first real
+/// entry, with `..` one row up.
+fn first_entry(entries: &[FsEntry]) -> usize {
+    match entries {
+        [parent, _, ..] if parent.is_parent_link() => 1,
+        _ => 0,
+    }
+}
+
+/// The entries whose names contain `filter`, ignoring case. `..` is left out
+/// of filtered views, so Enter goes to the first match.
+fn filter_entries<'a>(entries: &'a [FsEntry], filter: &str) -> Vec<&'a FsEntry> {
+    let needle = filter.to_lowercase();
+    entries
+        .iter()
+        .filter(|e| {
+            needle.is_empty() || (!e.is_parent_link() && e.name.to_lowercase().contains(&needle))
+        })
+        .collect()
+}
+
+/// Lists `path`, `..` first. `pinned` entries come next, ahead of the
+/// dirs-first rule, and are sorted among themselves like everything else.
+pub fn read_entries(
+    path: &std::path::Path,
+    show_hidden: bool,
+    sort_mode: SortMode,
+    sort_reversed: bool,
+    dirs_first: bool,
+    pinned: &HashSet<PathBuf>,
+) -> std::io::Result<Vec<FsEntry>> {
+    let mut entries: Vec<FsEntry> = std::fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .filter(|entry| show_hidden || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| {
+            // `DirEntry::metadata` doesn't follow links, so it works for dangling ones too
+            let link_meta = entry.metadata().ok()?;
+            let symlink_target = link_meta
+                .file_type()
+                .is_symlink()
+                .then(|| std::fs::read_link(entry.path()).unwrap_or_default());
+            // Describe links by their target; a dangling link falls back to the link itself
+            let meta = if symlink_target.is_some() {
+                std::fs::metadata(entry.path()).unwrap_or(link_meta)
+            } else {
+                link_meta
+            };
+            #[cfg(unix)]
+            let perms_str = {
+                use std::os::unix::fs::PermissionsExt;
+                format_permissions(meta.permissions().mode(), meta.is_dir())
+            };
+            // No rwx bits on Windows; show the attribute flags instead
+            #[cfg(windows)]
+            let perms_str = {
+                use std::os::windows::fs::MetadataExt;
+                let attrs = meta.file_attributes();
+                let flag = |bit: u32, c: char| if attrs & bit != 0 { c } else { '-' };
+                let kind = if meta.is_dir() { 'd' } else { '-' };
+                // FILE_ATTRIBUTE_READONLY, _HIDDEN, _SYSTEM, _ARCHIVE
+                [kind, flag(0x1, 'r'), flag(0x2, 'h'), flag(0x4, 's'), flag(0x20, 'a')]
+                    .iter()
+                    .collect()
+            };
+
+            #[cfg(unix)]
+            let (uid, gid) = {
+                use std::os::unix::fs::MetadataExt;
+                (Some(meta.uid()), Some(meta.gid()))
+            };
+            #[cfg(not(unix))]
+            let (uid, gid) = (None, None);
+
+            Some(FsEntry {
+                path: entry.path().to_path_buf(),
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_dir: meta.is_dir(),
+                size: meta.len(),
+                modified: meta.modified().ok(),
+                permissions: perms_str,
+                symlink_target,
+                uid,
+                gid,
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        let (a_pinned, b_pinned) = (pinned.contains(&a.path), pinned.contains(&b.path));
+        if a_pinned != b_pinned {
+            b_pinned.cmp(&a_pinned) // Pinned first, even before folders
+        } else if dirs_first && a.is_dir != b.is_dir {
+            b.is_dir.cmp(&a.is_dir) // Dirs first, regardless of direction
+        } else if sort_reversed {
+            compare_entries(b, a, sort_mode)
+        } else {
+            compare_entries(a, b, sort_mode)
+        }
+    });
+
+    // `..` goes first whatever the sort, except at the root
+    if let Some(parent) = path.parent() {
+        entries.insert(
+            0,
+            FsEntry {
+                path: parent.to_path_buf(),
+                name: "..".to_string(),
+                is_dir: true,
+                size: 0,
+                modified: None,
+                permissions: String::new(),
+                symlink_target: None,
+                uid: None,
+                gid: None,
+            },
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Everything under `root` as one list: each folder followed by its
+/// contents, siblings in the usual sort order. Names are paths relative to
+/// `root`. Linked folders aren't followed, and the walk stops at
+/// `FLATTEN_MAX_DEPTH` levels and `FLATTEN_MAX_ENTRIES` entries.
+pub fn read_flat_entries(
+    root: &Path,
+    show_hidden: bool,
+    sort_mode: SortMode,
+    sort_reversed: bool,
+    dirs_first: bool,
+    pinned: &HashSet<PathBuf>,
+) -> std::io::Result<Vec<FsEntry>> {
+    // Depth-first; each listing goes on reversed so `pop` takes it in order
+    let mut stack =
+        read_entries(root, show_hidden, sort_mode, sort_reversed, dirs_first, pinned)?;
+    stack.reverse();
+    let mut flat = Vec::new();
+    let mut listed = 0;
+    while let Some(mut entry) = stack.pop() {
+        if entry.is_parent_link() {
+            flat.push(entry);
+            continue;
+        }
+        if listed == FLATTEN_MAX_ENTRIES {
+            break;
+        }
+        let rel = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+        let depth = rel.components().count();
+        entry.name = rel.to_string_lossy().into_owned();
+        if entry.is_dir
+            && entry.symlink_target.is_none()
+            && depth < FLATTEN_MAX_DEPTH
+            && let Ok(children) = read_entries(
+                &entry.path,
+                show_hidden,
+                sort_mode,
+                sort_reversed,
+                dirs_first,
+                pinned,
+            )
+        {
+            stack.extend(children.into_iter().filter(|e| !e.is_parent_link()).rev());
+        }
+        flat.push(entry);
+        listed += 1;
+    }
+    Ok(flat)
+}
+
+/// Compares names the way people read them: runs of digits by value, so
+/// `img2` < `img10`, and letters case-insensitively. Names that only differ
+/// in case or leading zeros (`1` < `01`) still get a stable order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    let mut tiebreak = Ordering::Equal;
+    loop {
+        let (x, y) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return tiebreak.then_with(|| a.cmp(b)),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(&x), Some(&y)) => (x, y),
+        };
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                let mut run = String::new();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    run.push(c);
                 }
-                _ => {}
+                run
+            };
+            let (run_a, run_b) = (digits(&mut a_chars), digits(&mut b_chars));
+            let (num_a, num_b) = (run_a.trim_start_matches('0'), run_b.trim_start_matches('0'));
+            // No parsing, so any number of digits works
+            let ord = num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+            if tiebreak == Ordering::Equal {
+                tiebreak = run_a.len().cmp(&run_b.len());
+            }
+        } else {
+            let ord = x.to_lowercase().cmp(y.to_lowercase());
+            if ord != Ordering::Equal {
+                return ord;
             }
+            a_chars.next();
+            b_chars.next();
         }
+    }
+}
 
-        fn as_ref(s: &str) -> &str {
-            s
+fn compare_entries(a: &FsEntry, b: &FsEntry, sort_mode: SortMode) -> Ordering {
+    let by_name = natural_cmp(&a.name, &b.name);
+    match sort_mode {
+        SortMode::Name => by_name,
+        SortMode::Size => a.size.cmp(&b.size).then(by_name),
+        // Entries without an mtime sort as oldest
+        SortMode::Modified => a.modified.cmp(&b.modified).then(by_name),
+        SortMode::Extension => {
+            let ext = |e: &FsEntry| {
+                e.path
+                    .extension()
+                    .map(|x| x.to_string_lossy().to_lowercase())
+                    .unwrap_or_default()
+            };
+            ext(a).cmp(&ext(b)).then(by_name)
         }
+    }
+}
 
-        match std::fs::read_to_string(&path) {
-            Ok(content) => {
-                // Return raw content regardless of extension for now.
-                // draw_preview handles highlighting.
-                // TODO: For very large files, read only first N KB.
-                Ok(PreviewContent::Text { title, content })
-            }
-            Err(_) => {
-                let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+/* =========================
+   RENDER (CLI DEMO)
+========================= */
 
-                Ok(PreviewContent::Binary {
-                    title,
-                    size: meta.len(),
-                })
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect, Margin, Alignment},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table, TableState, Tabs, Wrap, block::Title,
+    },
+};
+
+/// The named syntect theme, or the first one there is if it's missing (e.g. a
+/// typo in the config), so a bad name can't take the preview down.
+fn find_theme<'a>(themes: &'a ThemeSet, name: &str) -> Option<(&'a str, &'a Theme)> {
+    let found = themes.themes.get_key_value(name);
+    found.or_else(|| themes.themes.iter().next()).map(|(name, theme)| (name.as_str(), theme))
+}
+
+/* =========================
+   TUI RENDER
+========================= */
+
+pub fn ui(f: &mut Frame, state: &mut AppState) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(f.size());
+
+    // Only spend a row on the tab bar once there's more than one tab
+    let mut main_area = rows[0];
+    if state.tabs.len() > 1 {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
+            .split(main_area);
+        draw_tab_bar(f, state, split[0]);
+        main_area = split[1];
+    }
+
+    // Three columns: the parent on the left, then the usual two. Dual-pane
+    // mode has no room for it
+    if state.miller && state.other_pane.is_none() {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(PARENT_COLUMN_PERCENT), Constraint::Min(0)])
+            .split(main_area);
+        draw_parent_column(f, &state.parent_entries, &state.cwd, split[0]);
+        main_area = split[1];
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(state.split_ratio), Constraint::Min(0)].as_ref())
+        .split(main_area);
+
+    // In dual-pane mode the live listing sits on whichever side is active and
+    // the other pane takes the preview's place
+    let (live, other) = match (&state.other_pane, state.active_pane) {
+        (Some(_), 1) => (chunks[1], chunks[0]),
+        _ => (chunks[0], chunks[1]),
+    };
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(live);
+
+    draw_breadcrumb(f, &state.cwd, left[0]);
+    state.list_area = left[1];
+    state.preview_area = other;
+    #[cfg(unix)]
+    let owners = |entry: &FsEntry| owner_column(&state.accounts, entry);
+    #[cfg(not(unix))]
+    let owners = |_: &FsEntry| String::new();
+    let view = ListView {
+        entries: state.visible_entries(),
+        cursor: state.cursor,
+        selected: &state.selected,
+        pinned: &state.pinned,
+        title: files_title(
+            state,
+            &state.filter,
+            state.filter_mode,
+            state.glob_filter.as_deref(),
+            state.flatten,
+        ),
+        active: state.active_focus == ActiveFocus::FileList,
+        git: state.git_status.as_ref(),
+        owners: state.show_owner.then_some(&owners),
+        size_colors: state.size_colors,
+    };
+    state.list_offset = draw_file_list(f, &state.dir_sizes, view, left[1], state.list_offset);
+
+    if let Some(pane) = &state.other_pane {
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
+            .split(other);
+        draw_breadcrumb(f, &pane.cwd, right[0]);
+        let view = ListView {
+            entries: filter_entries(&pane.entries, &pane.filter),
+            cursor: pane.cursor,
+            selected: &pane.selected,
+            pinned: &state.pinned,
+            title: files_title(state, &pane.filter, false, None, false),
+            active: false,
+            git: None,
+            owners: state.show_owner.then_some(&owners),
+            size_colors: state.size_colors,
+        };
+        let offset = draw_file_list(f, &state.dir_sizes, view, right[1], state.other_list_offset);
+        state.other_list_offset = offset;
+        state.image_area = None;
+    } else {
+        state.image_area = image_area(state, chunks[1]);
+        draw_preview(f, state, chunks[1]);
+    }
+    let selection_size = state.selection_size();
+    draw_status_line(f, state, selection_size, rows[1]);
+    draw_toasts(f, state, rows[0]);
+
+    // Draw Popup if active
+    if let PopupState::Chmod {
+        paths,
+        mode,
+        cursor_idx,
+        input,
+        input_error,
+        ..
+    } = &state.popup
+    {
+        let title = if paths.len() == 1 {
+            " Permissions ".to_string()
+        } else {
+            format!(" Permissions ({} files) ", paths.len())
+        };
+        let block = Block::default().title(title).borders(Borders::ALL).style(Style::default().bg(Color::DarkGray));
+        let size = f.size();
+        let area = centered_rect_height(60, 13, size);
+        f.render_widget(Clear, area); // Clear background
+        f.render_widget(block, area);
+
+        let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
+        
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1), // Title/Path
+                Constraint::Length(1), // Spacer
+                Constraint::Length(1), // Owner
+                Constraint::Length(1), // Group
+                Constraint::Length(1), // Other
+                Constraint::Length(1), // Special
+                Constraint::Length(1), // Octal mode
+                Constraint::Length(1), // Typed mode
+                Constraint::Min(0),    // Spacer
+                Constraint::Length(1), // Instructions
+            ])
+            .split(inner);
+
+        let path_text = match paths.as_slice() {
+            [path] => format!("Path: {}", path.file_name().unwrap_or_default().to_string_lossy()),
+            _ => format!("{} files (starting from the first file's mode)", paths.len()),
+        };
+        f.render_widget(Paragraph::new(path_text).alignment(Alignment::Center), chunks[0]);
+
+        // Helper to draw row: three (letter, bit) checkboxes
+        let draw_row = |label: &str, cells: [(char, u32); 3], row_idx: usize| {
+            let mut spans = vec![Span::raw(format!("{:<10}", label))];
+            for (col, (letter, bit)) in cells.into_iter().enumerate() {
+                if col > 0 {
+                    spans.push(Span::raw("  "));
+                }
+                let check = if mode & bit != 0 { "[x]" } else { "[ ]" };
+                let style = if *cursor_idx == row_idx * 3 + col {
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!("{} {}", letter, check), style));
             }
+            Line::from(spans)
+        };
+
+        let rows = [
+            ("Owner", [('R', 0o400), ('W', 0o200), ('X', 0o100)]),
+            ("Group", [('R', 0o040), ('W', 0o020), ('X', 0o010)]),
+            ("Other", [('R', 0o004), ('W', 0o002), ('X', 0o001)]),
+            // setuid, setgid, sticky; lettered the way `ls -l` shows them
+            ("Special", [('s', 0o4000), ('s', 0o2000), ('t', 0o1000)]),
+        ];
+        for (row_idx, (label, cells)) in rows.into_iter().enumerate() {
+            let row = Paragraph::new(draw_row(label, cells, row_idx)).alignment(Alignment::Center);
+            f.render_widget(row, chunks[2 + row_idx]);
+        }
+
+        // Follows every toggle and keystroke
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let octal = Line::from(vec![
+            Span::raw("Octal: "),
+            Span::styled(format!("{:04o}", mode & 0o7777), bold),
+        ]);
+        f.render_widget(Paragraph::new(octal).alignment(Alignment::Center), chunks[6]);
+
+        let mut mode_line = vec![Span::raw(format!("Mode: {}_", input))];
+        if let Some(err) = input_error {
+            mode_line.push(Span::styled(format!("  {}", err), Style::default().fg(Color::Red)));
         }
+        f.render_widget(Paragraph::new(Line::from(mode_line)).alignment(Alignment::Center), chunks[7]);
+
+        let help = "space: toggle | type 755 or u+x | enter: save | esc: cancel";
+        f.render_widget(Paragraph::new(help).style(Style::default().fg(Color::Gray)).alignment(Alignment::Center), chunks[9]);
+    }
+
+    if let PopupState::FuzzyFind {
+        query,
+        candidates,
+        results,
+        cursor_idx,
+    } = &state.popup
+    {
+        draw_fuzzy_popup(f, query, candidates, results, *cursor_idx);
+    }
+
+    if let PopupState::Properties { path, lines, scroll } = &state.popup {
+        draw_properties_popup(f, path, lines, *scroll);
+    }
+
+    if let PopupState::Bookmarks { cursor_idx } = &state.popup {
+        let title = " Bookmarks (enter: go | d: remove | esc: close) ";
+        draw_dirs_popup(f, title, &state.bookmarks, *cursor_idx);
+    }
+
+    if let PopupState::RecentDirs { cursor_idx } = &state.popup {
+        let title = " Recent directories (enter: go | esc: close) ";
+        draw_dirs_popup(f, title, &state.recent_dirs, *cursor_idx);
+    }
+
+    if let PopupState::Chown {
+        paths,
+        user,
+        group,
+        field,
+        error,
+    } = &state.popup
+    {
+        let area = centered_rect_height(50, 7, f.size());
+        f.render_widget(Clear, area);
+        let row = |label: &str, value: &str, active: bool| {
+            let (cursor, style) = if active {
+                ("_", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            } else {
+                ("", Style::default())
+            };
+            Line::from(vec![
+                Span::raw(format!("{:<7}", label)),
+                Span::styled(format!("{}{}", value, cursor), style),
+            ])
+        };
+        let help = match error {
+            Some(e) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+            None => Line::styled(
+                "tab: switch field | enter: apply | esc: cancel",
+                Style::default().fg(Color::Gray),
+            ),
+        };
+        let text = vec![
+            row("User", user, *field == 0),
+            row("Group", group, *field == 1),
+            Line::from(""),
+            help,
+        ];
+        let title = match paths.as_slice() {
+            [path] => format!(
+                " chown {} ",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            _ => format!(" chown {} items ", paths.len()),
+        };
+        let p = Paragraph::new(text).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+        f.render_widget(p, area);
+    }
+
+    if let PopupState::Grep { .. } = &state.popup {
+        draw_grep_popup(f, &state.popup);
+    }
+
+    if let PopupState::GoToPath { input, error } = &state.popup {
+        draw_input_popup(f, " Go to path ", input, error, "enter: go | esc: cancel");
+    }
+
+    if let PopupState::BulkRename {
+        paths,
+        input,
+        renames,
+        error,
+    } = &state.popup
+    {
+        draw_bulk_rename(f, paths.len(), input, renames, error);
+    }
+
+    if let PopupState::MoveTo {
+        paths,
+        input,
+        error,
+    } = &state.popup
+    {
+        let title = format!(" Move {} item(s) to ", paths.len());
+        draw_input_popup(f, &title, input, error, "enter: move | esc: cancel");
+    }
+
+    if let PopupState::CreateSymlink {
+        target,
+        input,
+        error,
+    } = &state.popup
+    {
+        let title = format!(
+            " Link to {} ",
+            target.file_name().unwrap_or_default().to_string_lossy()
+        );
+        draw_input_popup(f, &title, input, error, "enter: create | esc: cancel");
+    }
+
+    if let PopupState::OpenWith {
+        path,
+        input,
+        cursor_idx,
+        ..
+    } = &state.popup
+    {
+        draw_open_with_popup(f, path, input, *cursor_idx, &state.recent_commands);
+    }
+
+    if let PopupState::Progress {
+        done,
+        total,
+        current,
+    } = &state.popup
+    {
+        let area = centered_rect_height(60, 6, f.size());
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .title(" Copying ")
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::DarkGray));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1); 4])
+            .split(inner);
+
+        let (label, ratio) = match total {
+            0 => ("Counting files...".to_string(), 0.0),
+            _ => (format!("{}/{}", done, total), (*done as f64 / *total as f64).min(1.0)),
+        };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+            .ratio(ratio)
+            .label(label);
+        f.render_widget(gauge, rows[0]);
+        f.render_widget(Paragraph::new(current.as_str()), rows[1]);
+        let help = Paragraph::new("esc: cancel")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center);
+        f.render_widget(help, rows[3]);
+    }
+
+    if let PopupState::ConfirmDelete { paths } = &state.popup {
+        let area = centered_rect_height(50, 6, f.size());
+        f.render_widget(Clear, area);
+        let what = match paths.as_slice() {
+            [path] => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            _ => format!("these {} items", paths.len()),
+        };
+        let why = match state.outside_listing(paths) {
+            0 => format!("That's over {} files and folders.", DELETE_CONFIRM_ENTRIES),
+            n if n == paths.len() => "None of them are in this folder.".to_string(),
+            n => format!("{} of them are in other folders.", n),
+        };
+        let text = vec![
+            Line::from(format!("Delete {}?", what)),
+            Line::from(why),
+            Line::from(""),
+            Line::styled("y: delete | n/esc: keep", Style::default().fg(Color::Gray)),
+        ];
+        let p = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .title(" Delete ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray))
+                .border_style(Style::default().fg(Color::Red)),
+        );
+        f.render_widget(p, area);
+    }
+
+    if let PopupState::ConfirmCopyText { bytes } = &state.popup {
+        let area = centered_rect_height(50, 5, f.size());
+        f.render_widget(Clear, area);
+        let text = vec![
+            Line::from(format!("Put all {} on the clipboard?", format_size(*bytes as u64))),
+            Line::from(""),
+            Line::styled("y/enter: copy | n/esc: cancel", Style::default().fg(Color::Gray)),
+        ];
+        let p = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .title(" Copy contents ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+        f.render_widget(p, area);
+    }
+
+    if let PopupState::Summary {
+        items,
+        cached,
+        scanned,
+        running,
+    } = &state.popup
+    {
+        let mut total = *cached;
+        total.add(*scanned);
+        let area = centered_rect_height(50, 8, f.size());
+        f.render_widget(Clear, area);
+        let row = |label: &str, value: String| Line::from(format!("{:<8}{}", label, value));
+        let text = vec![
+            row("Items", items.to_string()),
+            row("Size", format!("{} ({} bytes)", format_size(total.bytes), total.bytes)),
+            row("Files", total.files.to_string()),
+            row("Folders", total.dirs.to_string()),
+            Line::from(""),
+            if *running {
+                Line::styled("Counting...", Style::default().fg(Color::Yellow))
+            } else {
+                Line::styled("esc: close", Style::default().fg(Color::Gray))
+            },
+        ];
+        let p = Paragraph::new(text).block(
+            Block::default()
+                .title(" Selection ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+        f.render_widget(p, area);
+    }
+
+    if let PopupState::Conflict { name } = &state.popup {
+        let area = centered_rect_height(50, 5, f.size());
+        f.render_widget(Clear, area);
+        let text = vec![
+            Line::from(format!("{} already exists", name)),
+            Line::from(""),
+            Line::styled(
+                "o: overwrite | s: skip | r: rename | a: all | n: none",
+                Style::default().fg(Color::Gray),
+            ),
+        ];
+        let p = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .title(" Paste ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+        f.render_widget(p, area);
+    }
+
+    if let PopupState::ConfirmQuit { reason } = &state.popup {
+        let area = centered_rect_height(50, 5, f.size());
+        f.render_widget(Clear, area);
+        let text = vec![
+            Line::from(reason.as_str()),
+            Line::from(""),
+            Line::styled("y/q/enter: quit | n/esc: stay", Style::default().fg(Color::Gray)),
+        ];
+        let p = Paragraph::new(text).alignment(Alignment::Center).block(
+            Block::default()
+                .title(" Quit? ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        );
+        f.render_widget(p, area);
+    }
+}
+
+fn draw_fuzzy_popup(
+    f: &mut Frame,
+    query: &str,
+    candidates: &Option<Vec<PathBuf>>,
+    results: &[PathBuf],
+    cursor_idx: usize,
+) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
+
+    let title = match candidates {
+        None => " Find (indexing...) ".to_string(),
+        Some(c) => format!(" Find ({}/{}) ", results.len(), c.len()),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::DarkGray));
+    f.render_widget(block, area);
+
+    let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(inner);
+
+    f.render_widget(Paragraph::new(format!("> {}_", query)), chunks[0]);
+
+    let items: Vec<ListItem> = results
+        .iter()
+        .map(|p| ListItem::new(p.to_string_lossy().into_owned()))
+        .collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    let mut list_state = ListState::default();
+    if !results.is_empty() {
+        list_state.select(Some(cursor_idx));
+    }
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+}
+
+/// A one-line text prompt, with `error` (if any) in place of the help line.
+fn draw_input_popup(f: &mut Frame, title: &str, input: &str, error: &Option<String>, help: &str) {
+    let area = centered_rect_height(60, 5, f.size());
+    f.render_widget(Clear, area);
+    let help = match error {
+        Some(e) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+        None => Line::styled(help, Style::default().fg(Color::Gray)),
+    };
+    let text = vec![Line::from(format!("> {}_", input)), Line::from(""), help];
+    let p = Paragraph::new(text).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    f.render_widget(p, area);
+}
+
+/// The pattern being typed, then each old -> new name it gives.
+fn draw_bulk_rename(
+    f: &mut Frame,
+    count: usize,
+    input: &str,
+    renames: &[(String, String)],
+    error: &Option<String>,
+) {
+    let area = centered_rect(70, 70, f.size());
+    f.render_widget(Clear, area);
+    let help = match error {
+        Some(e) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+        None => Line::styled(
+            "{n:03} {name} {ext} or s/find/replace/ | enter: rename | esc: cancel",
+            Style::default().fg(Color::Gray),
+        ),
+    };
+    let mut text = vec![Line::from(format!("> {}_", input)), help, Line::from("")];
+    let room = (area.height as usize).saturating_sub(text.len() + 2);
+    let shown = if renames.len() > room { room.saturating_sub(1) } else { renames.len() };
+    for (old, new) in &renames[..shown] {
+        text.push(Line::from(vec![
+            Span::raw(old.clone()),
+            Span::styled(" → ", Style::default().fg(Color::Gray)),
+            Span::styled(new.clone(), Style::default().fg(Color::Green)),
+        ]));
+    }
+    if shown < renames.len() {
+        text.push(Line::styled(
+            format!("… and {} more", renames.len() - shown),
+            Style::default().fg(Color::Gray),
+        ));
+    }
+    let p = Paragraph::new(text).block(
+        Block::default()
+            .title(format!(" Rename {} item(s) ", count))
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    f.render_widget(p, area);
+}
+
+fn draw_grep_popup(f: &mut Frame, popup: &PopupState) {
+    let PopupState::Grep {
+        query,
+        regex,
+        results,
+        cursor_idx,
+        running,
+        truncated,
+        error,
+        ..
+    } = popup
+    else {
+        return;
+    };
+    let area = centered_rect(80, 70, f.size());
+    f.render_widget(Clear, area);
+
+    let mode = if *regex { "regex" } else { "text" };
+    let count = match results.len() {
+        _ if *running => format!("{} matches, searching...", results.len()),
+        n if *truncated => format!("first {} matches", n),
+        n => format!("{} matches", n),
+    };
+    let title = format!(" Grep [{}] ({}) | ctrl+r: regex ", mode, count);
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::DarkGray));
+    f.render_widget(block, area);
+
+    let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(inner);
+
+    let prompt = match error {
+        Some(e) => Line::from(vec![
+            Span::raw(format!("> {}_  ", query)),
+            Span::styled(e.lines().last().unwrap_or(""), Style::default().fg(Color::Red)),
+        ]),
+        None => Line::from(format!("> {}_", query)),
+    };
+    f.render_widget(Paragraph::new(prompt), chunks[0]);
+
+    let items: Vec<ListItem> = results
+        .iter()
+        .map(|m| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}:{}: ", m.path.display(), m.line),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(m.text.as_str()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    let mut list_state = ListState::default();
+    if !results.is_empty() {
+        list_state.select(Some(*cursor_idx));
     }
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
 }
 
-pub fn read_entries(path: &std::path::Path) -> std::io::Result<Vec<FsEntry>> {
-    use std::os::unix::fs::PermissionsExt;
-
-    let mut entries: Vec<FsEntry> = std::fs::read_dir(path)?
-        .filter_map(|e| e.ok())
-        .map(|entry| {
-            let meta = entry.metadata().unwrap();
-            let mode = meta.permissions().mode();
-            
-            // Format permissions logic
-            let mut perms = String::with_capacity(10);
-            perms.push(if meta.is_dir() { 'd' } else { '-' });
-            perms.push(if mode & 0o400 != 0 { 'r' } else { '-' });
-            perms.push(if mode & 0o200 != 0 { 'w' } else { '-' });
-            perms.push(if mode & 0o100 != 0 { 'x' } else { '-' });
-            perms.push(if mode & 0o040 != 0 { 'r' } else { '-' });
-            perms.push(if mode & 0o020 != 0 { 'w' } else { '-' });
-            let mut perms_str = String::with_capacity(10);
-            perms_str.push(if entry.path().is_dir() { 'd' } else { '-' });
-            perms_str.push(if mode & 0o400 != 0 { 'r' } else { '-' });
-            perms_str.push(if mode & 0o200 != 0 { 'w' } else { '-' });
-            perms_str.push(if mode & 0o100 != 0 { 'x' } else { '-' });
-            perms_str.push(if mode & 0o040 != 0 { 'r' } else { '-' });
-            perms_str.push(if mode & 0o020 != 0 { 'w' } else { '-' });
-            perms_str.push(if mode & 0o010 != 0 { 'x' } else { '-' });
-            perms_str.push(if mode & 0o004 != 0 { 'r' } else { '-' });
-            perms_str.push(if mode & 0o002 != 0 { 'w' } else { '-' });
-            perms_str.push(if mode & 0o001 != 0 { 'x' } else { '-' });
+fn draw_properties_popup(f: &mut Frame, path: &Path, lines: &[(String, String)], scroll: usize) {
+    let area = centered_rect(70, 60, f.size());
+    f.render_widget(Clear, area);
 
-            FsEntry {
-                path: entry.path().to_path_buf(),
-                name: entry.file_name().to_string_lossy().to_string(),
-                is_dir: entry.path().is_dir(),
-                _size: entry.metadata().map(|m| m.len()).unwrap_or(0),
-                permissions: perms_str,
-            }
+    let text: Vec<Line> = lines
+        .iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<12}", label),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(value.as_str()),
+            ])
         })
         .collect();
 
-    entries.sort_by(|a, b| {
-        if a.is_dir != b.is_dir {
-            b.is_dir.cmp(&a.is_dir) // Dirs first
+    let p = Paragraph::new(text)
+        .block(
+            Block::default()
+                .title(format!(
+                    " Properties: {} ",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ))
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        )
+        .wrap(Wrap { trim: false })
+        .scroll((scroll as u16, 0));
+    f.render_widget(p, area);
+}
+
+fn draw_open_with_popup(
+    f: &mut Frame,
+    path: &Path,
+    input: &str,
+    cursor_idx: Option<usize>,
+    recent: &[String],
+) {
+    let height = 4 + recent.len().min(RECENT_COMMANDS_LIMIT) as u16;
+    let area = centered_rect_height(60, height, f.size());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![Line::from(format!("> {}_", input)), Line::from("")];
+    for (idx, command) in recent.iter().enumerate() {
+        let style = if cursor_idx == Some(idx) {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
         } else {
-            a.name.cmp(&b.name) // Then alphabetical
-        }
-    });
+            Style::default().fg(Color::Gray)
+        };
+        lines.push(Line::styled(format!("  {}", command), style));
+    }
+    let title = format!(
+        " Open {} with (end with & to detach | esc: cancel) ",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let p = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::DarkGray)),
+    );
+    f.render_widget(p, area);
+}
 
-    Ok(entries)
+// A list of directories to jump to; ones that are gone are crossed out
+fn draw_dirs_popup(f: &mut Frame, title: &str, dirs: &[PathBuf], cursor_idx: usize) {
+    let area = centered_rect(60, 50, f.size());
+    f.render_widget(Clear, area);
+
+    let items: Vec<ListItem> = dirs
+        .iter()
+        .map(|path| {
+            let style = if path.is_dir() {
+                Style::default()
+            } else {
+                Style::default().fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT)
+            };
+            ListItem::new(path.display().to_string()).style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::DarkGray)),
+        )
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    let mut list_state = ListState::default();
+    list_state.select(Some(cursor_idx));
+    f.render_stateful_widget(list, area, &mut list_state);
 }
 
-/* =========================
-   RENDER (CLI DEMO)
-========================= */
+// Like `centered_rect`, but with a fixed height in rows so short terminals
+// still fit every line of the popup.
+fn centered_rect_height(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let height = height.min(r.height);
+    let area = centered_rect(percent_x, 100, r);
+    Rect {
+        y: r.y + (r.height - height) / 2,
+        height,
+        ..area
+    }
+}
 
-use ratatui::{
-    Frame,
-    layout::{Constraint, Direction, Layout, Rect, Margin, Alignment},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Clear},
-};
+/// Splits `spans` so every case-insensitive occurrence of `query` gets a
+/// `bg` background, keeping each piece's original foreground.
+fn mark_matches<'a>(spans: Vec<Span<'a>>, query: &str, bg: Color) -> Vec<Span<'a>> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let text: Vec<char> = spans.iter().flat_map(|s| s.content.chars()).map(lower).collect();
+    let query: Vec<char> = query.chars().map(lower).collect();
 
-/* =========================
-   TUI RENDER
-========================= */
+    // Per-character flag: inside a match?
+    let mut marked = vec![false; text.len()];
+    let mut i = 0;
+    while !query.is_empty() && i + query.len() <= text.len() {
+        if text[i..i + query.len()] == query[..] {
+            marked[i..i + query.len()].iter_mut().for_each(|m| *m = true);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
 
-pub fn ui(f: &mut Frame, state: &mut AppState) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(f.size());
+    let mut out = Vec::new();
+    let mut pos = 0;
+    for span in spans {
+        let chars: Vec<char> = span.content.chars().collect();
+        let mut start = 0;
+        while start < chars.len() {
+            let hit = marked[pos + start];
+            let mut end = start + 1;
+            while end < chars.len() && marked[pos + end] == hit {
+                end += 1;
+            }
+            let piece: String = chars[start..end].iter().collect();
+            let style = if hit { span.style.bg(bg) } else { span.style };
+            out.push(Span::styled(piece, style));
+            start = end;
+        }
+        pos += chars.len();
+    }
+    out
+}
 
-    draw_file_list(f, state, chunks[0]);
-    draw_preview(f, state, chunks[1]);
+/// Drops the first `n` characters of a line split into spans.
+fn skip_chars(spans: Vec<Span<'_>>, mut n: usize) -> Vec<Span<'_>> {
+    let mut out = Vec::new();
+    for span in spans {
+        let len = span.content.chars().count();
+        if n >= len {
+            n -= len;
+            continue;
+        }
+        let rest: String = span.content.chars().skip(n).collect();
+        out.push(Span::styled(rest, span.style));
+        n = 0;
+    }
+    out
+}
 
-    // Draw Popup if active
-    if let PopupState::Chmod { path, mode, cursor_idx } = &state.popup {
-        let block = Block::default().title(" Permissions ").borders(Borders::ALL).style(Style::default().bg(Color::DarkGray));
-        let size = f.size();
-        let area = centered_rect(60, 20, size);
-        f.render_widget(Clear, area); // Clear background
-        f.render_widget(block, area);
+/// Renders a parsed CSV with columns sized to their content, starting at the
+/// `preview_hscroll`th column so wide tables can be panned.
+fn draw_table_preview(
+    f: &mut Frame,
+    state: &AppState,
+    headers: &[String],
+    rows: &[Vec<String>],
+    block: Block,
+    area: Rect,
+) {
+    let columns = headers.len().max(rows.iter().map(Vec::len).max().unwrap_or(0));
+    let first = state.preview_hscroll.min(columns.saturating_sub(1));
+    // Rows are one line high, so quoted newlines are flattened
+    let cell = |row: &[String], col: usize| {
+        row.get(col).map(|s| s.replace('\n', " ")).unwrap_or_default()
+    };
 
-        let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
-        
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(1), // Title/Path
-                Constraint::Length(1), // Spacer
-                Constraint::Length(1), // Owner
-                Constraint::Length(1), // Group
-                Constraint::Length(1), // Other
-                Constraint::Min(1),    // Spacer
-                Constraint::Length(1), // Instructions
-            ])
-            .split(inner);
+    let widths: Vec<Constraint> = (first..columns)
+        .map(|col| {
+            let width = std::iter::once(headers)
+                .chain(rows.iter().map(Vec::as_slice))
+                .map(|row| row.get(col).map_or(0, |s| s.chars().count()))
+                .max()
+                .unwrap_or(0);
+            Constraint::Length(width.clamp(1, TABLE_MAX_COL_WIDTH) as u16)
+        })
+        .collect();
 
-        let path_text = format!("Path: {}", path.file_name().unwrap_or_default().to_string_lossy());
-        f.render_widget(Paragraph::new(path_text).alignment(Alignment::Center), chunks[0]);
+    let header = Row::new((first..columns).map(|col| cell(headers, col)))
+        .style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow));
+    let body = rows
+        .iter()
+        .skip(state.preview_scroll)
+        .map(|row| Row::new((first..columns).map(|col| cell(row, col))));
 
-        // Helper to draw row
-        let draw_row = |label: &str, start_bit: u32, row_idx: usize| {
-             let r_bit = start_bit;
-             let w_bit = start_bit >> 1;
-             let x_bit = start_bit >> 2;
-             
-             let r_check = if mode & r_bit != 0 { "[x]" } else { "[ ]" };
-             let w_check = if mode & w_bit != 0 { "[x]" } else { "[ ]" };
-             let x_check = if mode & x_bit != 0 { "[x]" } else { "[ ]" };
-             
-             // Check cursor
-             let base_idx = row_idx * 3;
-             let r_style = if *cursor_idx == base_idx { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
-             let w_style = if *cursor_idx == base_idx + 1 { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
-             let x_style = if *cursor_idx == base_idx + 2 { Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { Style::default() };
-
-             let line = Line::from(vec![
-                 Span::raw(format!("{:<10}", label)),
-                 Span::styled(format!("R {}", r_check), r_style),
-                 Span::raw("  "),
-                 Span::styled(format!("W {}", w_check), w_style),
-                 Span::raw("  "),
-                 Span::styled(format!("X {}", x_check), x_style),
-             ]);
-             
-             line
-        };
-
-        f.render_widget(Paragraph::new(draw_row("Owner", 0o400, 0)).alignment(Alignment::Center), chunks[2]);
-        f.render_widget(Paragraph::new(draw_row("Group", 0o040, 1)).alignment(Alignment::Center), chunks[3]);
-        f.render_widget(Paragraph::new(draw_row("Other", 0o004, 2)).alignment(Alignment::Center), chunks[4]);
-
-        let help = "arrows: navigate | space: toggle | enter: save | esc: cancel";
-        f.render_widget(Paragraph::new(help).style(Style::default().fg(Color::Gray)).alignment(Alignment::Center), chunks[6]);
-    }
+    let table = Table::new(body, widths).header(header).block(block).column_spacing(2);
+    f.render_widget(table, area);
 }
 
 // Helper for centering popup
@@ -621,27 +5676,348 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn draw_file_list(f: &mut Frame, state: &mut AppState, area: Rect) {
-    let items: Vec<ListItem> = state
-        .entries
+fn files_title(
+    state: &AppState,
+    filter: &str,
+    typing: bool,
+    glob: Option<&str>,
+    flat: bool,
+) -> String {
+    let mut title = format!(
+        "Files [{} {}]",
+        state.sort_mode.label(),
+        if state.sort_reversed { "↓" } else { "↑" }
+    );
+    if flat {
+        title.push_str(" [flat]");
+    }
+    if let Some(glob) = glob {
+        title.push_str(&format!(" [{}]", glob));
+    }
+    if typing {
+        title.push_str(&format!(" /{}_", filter));
+    } else if !filter.is_empty() {
+        title.push_str(&format!(" /{}", filter));
+    }
+    title
+}
+
+const BREADCRUMB_SEP: &str = " › ";
+
+/// Splits `cwd` into display components, replacing middle ones with `…` until
+/// the joined crumbs fit in `width` columns. The root and the current
+/// directory are always kept.
+fn breadcrumb_parts(cwd: &Path, width: usize) -> Vec<String> {
+    let parts: Vec<String> = cwd
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let len = |parts: &[String]| {
+        parts.iter().map(|p| p.chars().count()).sum::<usize>()
+            + parts.len().saturating_sub(1) * BREADCRUMB_SEP.chars().count()
+    };
+    if parts.len() <= 2 || len(&parts) <= width {
+        return parts;
+    }
+
+    // Drop components right after the root until the tail fits
+    for skip in 1..parts.len() - 1 {
+        let mut candidate = vec![parts[0].clone(), "…".to_string()];
+        candidate.extend_from_slice(&parts[skip + 1..]);
+        if len(&candidate) <= width {
+            return candidate;
+        }
+    }
+    vec![parts[0].clone(), "…".to_string(), parts[parts.len() - 1].clone()]
+}
+
+fn draw_breadcrumb(f: &mut Frame, cwd: &Path, area: Rect) {
+    let parts = breadcrumb_parts(cwd, area.width as usize);
+    let last = parts.len().saturating_sub(1);
+
+    let mut spans = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(BREADCRUMB_SEP, Style::default().fg(Color::DarkGray)));
+        }
+        let style = if i == last {
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Blue)
+        };
+        spans.push(Span::styled(part, style));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn draw_tab_bar(f: &mut Frame, state: &AppState, area: Rect) {
+    let titles: Vec<String> = (0..state.tabs.len())
+        .map(|i| {
+            let cwd = state.tab_cwd(i);
+            let name = cwd
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| cwd.display().to_string());
+            format!("{}:{}", i + 1, name)
+        })
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(state.active_tab)
+        .highlight_style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, area);
+}
+
+fn draw_status_line(f: &mut Frame, state: &AppState, selection_size: u64, area: Rect) {
+    // The command line takes the whole row while it's being typed
+    if state.command_mode {
+        let line = Paragraph::new(format!(":{}_", state.command_input));
+        f.render_widget(line, area);
+        return;
+    }
+    let items = state.visible_entries().iter().filter(|e| !e.is_parent_link()).count();
+    let dir_name = state
+        .cwd
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| state.cwd.display().to_string());
+    let mut spans = vec![
+        Span::styled(format!(" {}", dir_name), Style::default().fg(Color::Blue)),
+        Span::raw(format!(" | {} items", items)),
+    ];
+    if state.dry_run {
+        let badge = Style::default().fg(Color::Black).bg(Color::Yellow);
+        spans.insert(0, Span::styled(" DRY RUN ", badge));
+    }
+    if state.readonly {
+        let badge = Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD);
+        spans.insert(0, Span::styled(" READONLY ", badge));
+    }
+    if state.visual_anchor.is_some() {
+        spans.insert(
+            0,
+            Span::styled(
+                " VISUAL ",
+                Style::default().fg(Color::Black).bg(Color::Magenta),
+            ),
+        );
+    }
+    if let Some(status) = &state.status {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::raw(status.as_str()));
+    }
+    if !state.selected.is_empty() {
+        let selected: Vec<PathBuf> = state.selected.iter().cloned().collect();
+        let count = match state.outside_listing(&selected) {
+            0 => format!("{} selected", selected.len()),
+            n => format!("{} selected, {} elsewhere", selected.len(), n),
+        };
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(
+            format!("{} ({})", count, format_size(selection_size)),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some((op, paths)) = &state.clipboard {
+        let verb = match op {
+            ClipboardOp::Copy => "copied",
+        };
+        spans.push(Span::raw(format!(" | clipboard: {} {}", paths.len(), verb)));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Stacks the toasts up from the bottom-right corner of `area`, newest at the
+/// bottom. A toast dims over its last second before it goes.
+fn draw_toasts(f: &mut Frame, state: &AppState, area: Rect) {
+    // Inside the panes' borders, so those stay intact
+    let area = area.inner(&Margin { vertical: 1, horizontal: 1 });
+    let now = Instant::now();
+    let mut bottom = area.bottom();
+    for toast in state.toasts.iter().rev() {
+        let text = format!(" {} ", toast.message);
+        let width = (text.chars().count() as u16).min(area.width);
+        if bottom <= area.y || width == 0 {
+            break;
+        }
+        bottom -= 1;
+        let rect = Rect {
+            x: area.right() - width,
+            y: bottom,
+            width,
+            height: 1,
+        };
+        let bg = match toast.kind {
+            ToastKind::Info => Color::DarkGray,
+            ToastKind::Error => Color::Red,
+        };
+        let left = toast.lifetime().saturating_sub(now.duration_since(toast.created_at));
+        let fg = if left < Duration::from_secs(1) { Color::Gray } else { Color::White };
+        f.render_widget(Clear, rect);
+        f.render_widget(Paragraph::new(text).style(Style::default().fg(fg).bg(bg)), rect);
+    }
+}
+
+/// A braille spinner frame; the event loop redraws often enough to animate it.
+fn spinner_frame() -> char {
+    const FRAMES: [char; 8] = ['⣾', '⣽', '⣻', '⢿', '⡿', '⣟', '⣯', '⣷'];
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    FRAMES[(millis / 250) as usize % FRAMES.len()]
+}
+
+// Rust's own orange, as most editor icon themes use it
+const RUST_ORANGE: Color = Color::Rgb(0xde, 0xa5, 0x84);
+
+/// The Nerd Font icon (with its trailing space) and color for a list row.
+/// Links, directories and executables go by type; other files by extension.
+pub fn icon_and_color(entry: &FsEntry) -> (&'static str, Color) {
+    if entry.is_parent_link() {
+        return ("\u{f148} ", Color::Magenta); // level-up arrow
+    }
+    if entry.symlink_target.is_some() {
+        return ("\u{f0c1} ", Color::Cyan);
+    }
+    if entry.is_dir {
+        return ("\u{f115} ", Color::Blue);
+    }
+    // `-rwxr-xr-x`: any x (or s/t over an x) bit. Windows attribute strings
+    // are shorter and never match; .exe and friends are caught below.
+    let perms = entry.permissions.as_bytes();
+    if perms.len() == 10 && [3, 6, 9].iter().any(|&i| matches!(perms[i], b'x' | b's' | b't')) {
+        return ("\u{f489} ", Color::Green);
+    }
+
+    let ext = match entry.name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_ascii_lowercase(),
+        _ => String::new(),
+    };
+    match ext.as_str() {
+        "rs" => ("\u{e7a8} ", RUST_ORANGE),
+        "md" | "markdown" | "txt" | "rst" => ("\u{f48a} ", Color::White),
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" => {
+            ("\u{f1c5} ", Color::Magenta)
+        }
+        "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "7z" | "rar" => ("\u{f1c6} ", Color::Red),
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" => ("\u{f1c7} ", Color::LightMagenta),
+        "mp4" | "mkv" | "webm" | "mov" | "avi" => ("\u{f1c8} ", Color::LightMagenta),
+        "pdf" => ("\u{f1c1} ", Color::LightRed),
+        "toml" | "yaml" | "yml" | "json" | "ini" | "conf" | "lock" => {
+            ("\u{e615} ", Color::Yellow)
+        }
+        "exe" | "bat" | "cmd" | "sh" | "ps1" => ("\u{f489} ", Color::Green),
+        "py" | "js" | "ts" | "c" | "h" | "cpp" | "go" | "java" | "html" | "css" => {
+            ("\u{f1c9} ", Color::LightBlue)
+        }
+        _ => ("\u{f15c} ", Color::White),
+    }
+}
+
+/// The parent directory's entries, with the one we're in highlighted. Names
+/// only; the middle column has the details.
+fn draw_parent_column(f: &mut Frame, entries: &[FsEntry], cwd: &Path, area: Rect) {
+    // Level with the listing, which has the breadcrumb above it
+    let area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(area)[1];
+    let items: Vec<ListItem> = entries
         .iter()
         .map(|entry| {
-            // Distinct icons
-            let icon = if entry.is_dir { " " } else { " " };
+            let (icon, color) = icon_and_color(entry);
+            ListItem::new(Line::from(vec![
+                Span::styled(icon, Style::default().fg(color)),
+                Span::raw(entry.name.clone()),
+            ]))
+        })
+        .collect();
+    let title = match cwd.parent().and_then(|p| p.file_name()) {
+        Some(name) => format!(" {} ", name.to_string_lossy()),
+        None => " / ".to_string(),
+    };
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default();
+    list_state.select(entries.iter().position(|e| e.path == cwd));
+    f.render_stateful_widget(list, area, &mut list_state);
+}
 
-            // Color logic:
-            // Directories: Blue
-            // Executables: Green (maybe later)
-            // Symlinks: Cyan (maybe later)
-            // Regular: White
+/// What `draw_file_list` needs from one pane.
+struct ListView<'a> {
+    entries: Vec<&'a FsEntry>,
+    cursor: usize,
+    selected: &'a HashSet<PathBuf>,
+    pinned: &'a HashSet<PathBuf>,
+    title: String,
+    active: bool, // gets the green border
+    git: Option<&'a HashMap<String, GitState>>,
+    owners: Option<&'a dyn Fn(&FsEntry) -> String>, // the owner column, when shown
+    size_colors: bool,
+}
 
-            let color = if entry.is_dir {
-                Color::Blue
-            } else {
-                Color::White
-            };
+// Room for `user:group` with both clipped to 8 characters
+const OWNER_WIDTH: usize = 17;
+
+/// `user:group` for the owner column, each clipped so long names can't
+/// crowd out the file names. Unknown ids show as numbers.
+#[cfg(unix)]
+fn owner_column(accounts: &crate::users::Accounts, entry: &FsEntry) -> String {
+    let clip = |name: String| match name.char_indices().nth(7) {
+        Some((end, _)) if name.chars().count() > 8 => format!("{}…", &name[..end]),
+        _ => name,
+    };
+    match (entry.uid, entry.gid) {
+        (Some(uid), Some(gid)) => format!(
+            "{}:{}",
+            clip(accounts.user_label(uid)),
+            clip(accounts.group_label(gid))
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Green for the smallest files through yellow to red for `largest`. On a
+/// log scale, or one big file would leave everything else green.
+pub fn size_color(size: u64, largest: u64) -> Color {
+    let t = if largest == 0 {
+        0.0
+    } else {
+        (size as f64).ln_1p() / (largest as f64).ln_1p()
+    };
+    let red = (510.0 * t).min(255.0) as u8;
+    let green = (510.0 * (1.0 - t)).min(255.0) as u8;
+    Color::Rgb(red, green, 0)
+}
+
+fn git_color(state: GitState) -> Color {
+    match state {
+        GitState::Modified => Color::Yellow,
+        GitState::Added => Color::Green,
+        GitState::Deleted | GitState::Conflicted => Color::Red,
+        GitState::Renamed => Color::Blue,
+        GitState::Untracked => Color::LightRed,
+    }
+}
+
+/// Draws one directory listing scrolled from `offset`; returns the offset
+/// ratatui settled on, to carry over to the next frame.
+fn draw_file_list(
+    f: &mut Frame,
+    dir_sizes: &HashMap<PathBuf, Option<u64>>,
+    view: ListView,
+    area: Rect,
+    offset: usize,
+) -> usize {
+    let largest = view.entries.iter().filter(|e| !e.is_dir).map(|e| e.size).max();
+    let rows: Vec<Row> = view
+        .entries
+        .iter()
+        .map(|entry| {
+            // Distinct icons
+            let (icon, color) = icon_and_color(entry);
 
-            let style = if state.selected.contains(&entry.path) {
+            let style = if view.selected.contains(&entry.path) {
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD)
@@ -649,26 +6025,97 @@ fn draw_file_list(f: &mut Frame, state: &mut AppState, area: Rect) {
                 Style::default().fg(color)
             };
 
-            // Layout: Name ... Permissions
-            // Simple approach: Just append text. Ratatui list doesn't support columns easily without Table widget.
-            // Let's pad it? Or just put it in parens?
-            // "  FolderName (drwxr-xr-x)"
+            let size = match dir_sizes.get(&entry.path) {
+                _ if entry.is_parent_link() => String::new(),
+                _ if !entry.is_dir => format_size(entry.size),
+                Some(Some(size)) => format_size(*size),
+                Some(None) => spinner_frame().to_string(),
+                None => "-".to_string(),
+            };
+
+            let modified = match entry.modified {
+                Some(time) => format_relative_time(time),
+                None if entry.is_parent_link() => String::new(),
+                None => "-".to_string(),
+            };
+
+            // Only the flat view has nested names; indent them by depth
+            let indent = "  ".repeat(entry.name.matches(std::path::MAIN_SEPARATOR).count());
+            let name = match &entry.symlink_target {
+                Some(target) => {
+                    format!("{}{} {} -> {}", indent, icon, entry.name, target.display())
+                }
+                None => format!("{}{} {}", indent, icon, entry.name),
+            };
+            let name = if view.pinned.contains(&entry.path) {
+                format!("{} 📌", name)
+            } else {
+                name
+            };
+
+            // A marker column, only inside a repo so other listings keep their width
+            let name = match view.git {
+                Some(git) => {
+                    let state = git.get(&entry.name);
+                    let marker = format!("{:<3}", state.map_or("", |s| s.marker()));
+                    let color = state.map_or(Color::Reset, |s| git_color(*s));
+                    Line::from(vec![Span::styled(marker, Style::default().fg(color)), name.into()])
+                }
+                None => Line::from(name),
+            };
 
-            ListItem::new(format!("{} {}  ({})", icon, entry.name, entry.permissions)).style(style)
+            let mut size = Cell::from(Line::from(size).alignment(Alignment::Right));
+            if view.size_colors
+                && !entry.is_dir
+                && let Some(largest) = largest
+            {
+                size = size.style(Style::default().fg(size_color(entry.size, largest)));
+            }
+            let mut cells = vec![
+                Cell::from(name),
+                size,
+                Cell::from(entry.permissions.clone()),
+            ];
+            if let Some(owners) = view.owners {
+                cells.push(Cell::from(owners(entry)));
+            }
+            cells.push(Cell::from(modified));
+            Row::new(cells).style(style)
         })
         .collect();
 
-    let border_color = if state.active_focus == ActiveFocus::FileList {
+    let border_color = if view.active {
         Color::Green
     } else {
         Color::White
     };
 
-    let list = List::new(items)
+    let mut widths = vec![
+        Constraint::Min(12),    // name
+        Constraint::Length(7),  // size
+        Constraint::Length(10), // permissions
+    ];
+    let mut header = vec![
+        Cell::from("Name"),
+        Cell::from(Line::from("Size").alignment(Alignment::Right)),
+        Cell::from("Perms"),
+    ];
+    if view.owners.is_some() {
+        widths.push(Constraint::Length(OWNER_WIDTH as u16));
+        header.push(Cell::from("Owner"));
+    }
+    widths.push(Constraint::Length(8)); // modified
+    header.push(Cell::from("Modified"));
+
+    let header = Row::new(header)
+        .style(Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD));
+
+    let table = Table::new(rows, widths)
+        .header(header)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Files")
+                .title(view.title)
                 .border_style(Style::default().fg(border_color)),
         )
         .highlight_style(
@@ -678,13 +6125,44 @@ fn draw_file_list(f: &mut Frame, state: &mut AppState, area: Rect) {
         )
         .highlight_symbol(">> ");
 
-    let mut list_state = ListState::default();
-    list_state.select(Some(state.cursor));
+    // Carry the scroll offset over between frames; mouse clicks need it too
+    let mut table_state = TableState::default()
+        .with_offset(offset)
+        .with_selected(Some(view.cursor));
 
-    f.render_stateful_widget(list, area, &mut list_state);
+    f.render_stateful_widget(table, area, &mut table_state);
+
+    // Borders and the header row take three lines
+    let len = view.entries.len();
+    draw_scrollbar(f, area, len, view.cursor, area.height.saturating_sub(3));
+    table_state.offset()
 }
 
-fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
+// Lines of image metadata kept above an inline image
+const IMAGE_HEADER_LINES: u16 = 2;
+
+/// The part of the preview pane an inline image may cover. Only while no popup
+/// is open, since graphics are drawn on top of whatever ratatui renders.
+fn image_area(state: &AppState, area: Rect) -> Option<Rect> {
+    state.graphics?;
+    if !matches!(state.preview, PreviewState::Ready(PreviewContent::Image { .. }))
+        || !matches!(state.popup, PopupState::None)
+    {
+        return None;
+    }
+    let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
+    if inner.height <= IMAGE_HEADER_LINES + 1 || inner.width < 2 {
+        return None;
+    }
+    Some(Rect {
+        y: inner.y + IMAGE_HEADER_LINES,
+        height: inner.height - IMAGE_HEADER_LINES,
+        ..inner
+    })
+}
+
+fn draw_preview(f: &mut Frame, state: &mut AppState, area: Rect) {
+    state.preview_height = area.height.saturating_sub(2); // inside the borders
     let border_color = if state.active_focus == ActiveFocus::Preview {
         Color::Green
     } else {
@@ -704,46 +6182,57 @@ fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
             f.render_widget(Paragraph::new("Loading...").block(block), area);
         }
         PreviewState::Ready(content) => match content {
-            PreviewContent::Text { title, content } => {
+            PreviewContent::Text {
+                title,
+                content,
+                encoding,
+            } => {
                 let mut lines: Vec<Line> = Vec::new();
 
-                let syntax = state
-                    .syntax_set
-                    .find_syntax_by_token(title)
+                // Look the syntax up by extension ("rs"), then by whole name ("Makefile")
+                let syntax = Path::new(title)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .and_then(|e| state.syntax_set.find_syntax_by_token(e))
+                    .or_else(|| state.syntax_set.find_syntax_by_token(title))
                     .unwrap_or_else(|| state.syntax_set.find_syntax_plain_text());
 
-                let mut h =
-                    HighlightLines::new(syntax, &state.theme_set.themes["base16-ocean.dark"]);
-
-                // PERFORMANCE FIX: Only highlight visible lines
-                // Skip lines based on scroll
                 let scroll = state.preview_scroll;
                 let height = area.height as usize;
+                let fallback = Theme::default();
+                let (theme_name, theme) =
+                    find_theme(&state.theme_set, &state.theme_name).unwrap_or(("none", &fallback));
+                let visible = state.highlight_cache.highlight(
+                    content,
+                    syntax,
+                    &state.syntax_set,
+                    theme,
+                    scroll,
+                    height,
+                );
 
-                // We use LinesWithEndings to ensure correct highlighting context if we were keeping state,
-                // but since we create new HighlightLines each frame, we assume stateless highlighting (ok for most langs).
-                // Actually syntect is stateful. Ideally we should iterate from start but that's slow.
-                // For now, re-instantiating is the compromise for performance vs correctness.
-                // But `highlight_line` updates state. We need to feed it previous lines?
-                // For large files, that's slow.
-                // Let's just highlight the slice. It might be slightly wrong for multi-line constructs but fast.
-
-                for line in content.lines().skip(scroll).take(height) {
-                    // Sanitize line: Remove control chars (like \r) but keep tabs/spaces.
-                    // This prevents cursor jumping or terminal corruption.
-                    let clean_line: String = line
-                        .chars()
-                        .filter(|c| !c.is_control() || *c == '\t')
-                        .collect();
+                // Gutter wide enough for the last line number, plus a space
+                let gutter = content.lines().count().max(1).to_string().len();
 
-                    let ranges: Vec<(SyntectStyle, &str)> = h
-                        .highlight_line(&clean_line, &state.syntax_set)
-                        .unwrap_or_default();
-                    let spans: Vec<Span> = ranges
+                for (offset, ranges) in visible.into_iter().enumerate() {
+                    let idx = scroll + offset;
+                    let mut spans: Vec<Span> = Vec::new();
+                    let current_match = state
+                        .preview_search
+                        .as_ref()
+                        .and_then(|s| s.matches.get(s.current))
+                        .is_some_and(|&m| m == idx);
+                    if state.show_line_numbers {
+                        spans.push(Span::styled(
+                            format!("{:>width$} ", idx + 1, width = gutter),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    let highlighted: Vec<Span> = ranges
                         .into_iter()
                         .map(|(style, text)| {
                             Span::styled(
-                                text.to_string(),
+                                text,
                                 Style::default().fg(Color::Rgb(
                                     style.foreground.r,
                                     style.foreground.g,
@@ -752,11 +6241,125 @@ fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
                             )
                         })
                         .collect();
+                    let highlighted = match &state.preview_search {
+                        Some(search) if !search.query.is_empty() => mark_matches(
+                            highlighted,
+                            &search.query,
+                            if current_match { Color::Yellow } else { Color::DarkGray },
+                        ),
+                        _ => highlighted,
+                    };
+                    // Panned after marking, so a match cut by the edge still shows
+                    if state.preview_wrap {
+                        spans.extend(highlighted);
+                    } else {
+                        spans.extend(skip_chars(highlighted, state.preview_hscroll));
+                    }
                     lines.push(Line::from(spans));
                 }
 
+                let title = match &state.preview_search {
+                    Some(search) => {
+                        let cursor = if state.preview_search_mode { "_" } else { "" };
+                        let count = match search.matches.len() {
+                            0 if !search.query.is_empty() => " [no matches]".to_string(),
+                            0 => String::new(),
+                            n => format!(" [{}/{}]", search.current + 1, n),
+                        };
+                        format!("{} /{}{}{}", title, search.query, cursor, count)
+                    }
+                    None => title.clone(),
+                };
+                let title = match encoding {
+                    Some(encoding) => format!("{} [{}]", title, encoding),
+                    None => title,
+                };
+                let theme_title = Title::from(format!(" {} ", theme_name))
+                    .alignment(Alignment::Right);
+                let mut p = Paragraph::new(lines).block(block.title(title).title(theme_title));
+                // No .scroll(): the lines are already sliced from `preview_scroll`.
+                // Wrapped, they may need more rows than there are; the rest is cut off
+                if state.preview_wrap {
+                    p = p.wrap(Wrap { trim: false });
+                }
+                f.render_widget(p, area);
+            }
+            PreviewContent::Diff { title, lines } => {
+                let mut p = Paragraph::new(lines.clone())
+                    .block(block.title(title.as_str()))
+                    .scroll((state.preview_scroll.min(u16::MAX as usize) as u16, 0));
+                if state.preview_wrap {
+                    p = p.wrap(Wrap { trim: false });
+                }
+                f.render_widget(p, area);
+            }
+            PreviewContent::Markdown { title, lines } => {
+                // Rendered paragraphs are single long lines, so wrap them
+                let p = Paragraph::new(lines.clone())
+                    .block(block.title(title.as_str()))
+                    .wrap(Wrap { trim: false })
+                    .scroll((state.preview_scroll.min(u16::MAX as usize) as u16, 0));
+                f.render_widget(p, area);
+            }
+            PreviewContent::Table {
+                title,
+                headers,
+                rows,
+                truncated,
+            } => {
+                let title = if *truncated {
+                    format!("{} (first {} rows)", title, TABLE_MAX_ROWS)
+                } else {
+                    title.clone()
+                };
+                draw_table_preview(f, state, headers, rows, block.title(title), area);
+            }
+            PreviewContent::Archive {
+                title,
+                entries,
+                truncated,
+            } => {
+                let title = if *truncated {
+                    format!("{} (first {} entries)", title, ARCHIVE_MAX_ENTRIES)
+                } else {
+                    format!("{} ({} entries)", title, entries.len())
+                };
+                let lines: Vec<Line> = entries
+                    .iter()
+                    .skip(state.preview_scroll)
+                    .take(area.height as usize)
+                    .map(|entry| {
+                        if entry.is_dir {
+                            let style = Style::default().fg(Color::Blue);
+                            // Blank size column, lined up with the files
+                            Line::from(vec![
+                                Span::raw(" ".repeat(10)),
+                                Span::styled(entry.path.as_str(), style),
+                            ])
+                        } else {
+                            Line::from(format!("{:>8}  {}", format_size(entry.size), entry.path))
+                        }
+                    })
+                    .collect();
+                let p = Paragraph::new(lines).block(block.title(title));
+                f.render_widget(p, area);
+            }
+            PreviewContent::Media { title, fields } => {
+                let width = fields.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+                let lines: Vec<Line> = fields
+                    .iter()
+                    .skip(state.preview_scroll)
+                    .map(|(key, value)| {
+                        Line::from(vec![
+                            Span::styled(
+                                format!("{:<width$}  ", key, width = width),
+                                Style::default().fg(Color::Cyan),
+                            ),
+                            Span::raw(value.as_str()),
+                        ])
+                    })
+                    .collect();
                 let p = Paragraph::new(lines).block(block.title(title.as_str()));
-                // .scroll() removed because we manually sliced content
                 f.render_widget(p, area);
             }
             PreviewContent::Binary { title, size } => {
@@ -768,7 +6371,10 @@ fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
                 title,
                 width,
                 height,
+                format,
                 color_type,
+                thumbnail,
+                ..
             } => {
                 let dim_text = if *width == 0 && *height == 0 {
                     "Dimensions: Unavailable".to_string()
@@ -776,13 +6382,38 @@ fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
                     format!("Dimensions: {} x {} px", width, height)
                 };
 
+                // The image itself is drawn by `run_app`; just leave room for it
+                if state.image_area.is_some() {
+                    let info = format!("Format: {} | Color: {}", format, color_type);
+                    let text = vec![Line::from(dim_text), Line::from(info)];
+                    let p = Paragraph::new(text).block(block.title(title.as_str()));
+                    f.render_widget(p, area);
+                    return;
+                }
+
+                // No graphics: draw it with text instead, under the metadata
+                let inner = area.inner(&Margin { vertical: 1, horizontal: 1 });
+                if let Some(thumbnail) = thumbnail
+                    && state.image_art != ImageArt::Off
+                    && inner.height > IMAGE_HEADER_LINES + 1
+                {
+                    let info = format!("Format: {} | Color: {}", format, color_type);
+                    let mut text = vec![Line::from(dim_text), Line::from(info)];
+                    let art_height = inner.height - IMAGE_HEADER_LINES;
+                    text.extend(thumbnail.render(inner.width, art_height, state.image_art));
+                    let p = Paragraph::new(text).block(block.title(title.as_str()));
+                    f.render_widget(p, area);
+                    return;
+                }
+
                 let text = vec![
                     Line::from(vec![Span::styled(
                         "Image File",
                         Style::default().add_modifier(Modifier::BOLD),
                     )]),
                     Line::from(dim_text),
-                    Line::from(format!("Info: {}", color_type)),
+                    Line::from(format!("Format: {}", format)),
+                    Line::from(format!("Color: {}", color_type)),
                     Line::from(""),
                     Line::from(vec![Span::styled(
                         "Press 'o' to open externally.",
@@ -800,4 +6431,38 @@ fn draw_preview(f: &mut Frame, state: &AppState, area: Rect) {
             f.render_widget(p, area);
         }
     }
+
+    if let PreviewState::Ready(content) = &state.preview {
+        let total = match content {
+            PreviewContent::Text { content, .. } => content.lines().count(),
+            // Counts unwrapped lines, so it's approximate for long paragraphs
+            PreviewContent::Markdown { lines, .. } | PreviewContent::Diff { lines, .. } => {
+                lines.len()
+            }
+            PreviewContent::Table { rows, .. } => rows.len(),
+            PreviewContent::Archive { entries, .. } => entries.len(),
+            PreviewContent::Media { fields, .. } => fields.len(),
+            PreviewContent::Binary { .. } | PreviewContent::Image { .. } => 0,
+        };
+        draw_scrollbar(f, area, total, state.preview_scroll, area.height.saturating_sub(2));
+    }
+}
+
+/// Draws a scrollbar over the right border of `area` when `total` rows don't
+/// fit into the `visible` ones; `position` is the row the view is at.
+fn draw_scrollbar(f: &mut Frame, area: Rect, total: usize, position: usize, visible: u16) {
+    if total <= visible as usize {
+        return;
+    }
+    let mut scrollbar_state = ScrollbarState::new(total)
+        .position(position.min(total - 1))
+        .viewport_content_length(visible as usize);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    f.render_stateful_widget(
+        scrollbar,
+        area.inner(&Margin { vertical: 1, horizontal: 0 }),
+        &mut scrollbar_state,
+    );
 }
@@ -0,0 +1,87 @@
+use std::{fs::File, io, path::Path};
+
+use flate2::read::GzDecoder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Guesses the archive type from the file name.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// The file name of `path` without its archive extension (`a.tar.gz` -> `a`).
+pub fn stem(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    [".tar.gz", ".tgz", ".tar", ".zip"].iter().find_map(|ext| {
+        let cut = name.len().checked_sub(ext.len()).filter(|&cut| cut > 0)?;
+        let (stem, tail) = (name.get(..cut)?, name.get(cut..)?);
+        tail.eq_ignore_ascii_case(ext).then(|| stem.to_string())
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub size: u64, // uncompressed
+    pub is_dir: bool,
+}
+
+/// Lists up to `limit` entries of the archive at `path` without extracting
+/// anything. The bool is true when there were more entries than that.
+pub fn list(path: &Path, kind: ArchiveKind, limit: usize) -> io::Result<(Vec<ArchiveEntry>, bool)> {
+    let file = File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => {
+            // Only the central directory at the end of the file is read
+            let mut zip = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+            let mut entries = Vec::new();
+            for i in 0..zip.len().min(limit) {
+                let entry = zip.by_index_raw(i).map_err(io::Error::other)?;
+                entries.push(ArchiveEntry {
+                    path: entry.name().to_string(),
+                    size: entry.size(),
+                    is_dir: entry.is_dir(),
+                });
+            }
+            Ok((entries, zip.len() > limit))
+        }
+        // Plain tars can seek past the file data instead of reading it
+        ArchiveKind::Tar => list_tar(tar::Archive::new(file).entries_with_seek()?, limit),
+        ArchiveKind::TarGz => list_tar(tar::Archive::new(GzDecoder::new(file)).entries()?, limit),
+    }
+}
+
+fn list_tar<R: io::Read>(
+    entries: tar::Entries<'_, R>,
+    limit: usize,
+) -> io::Result<(Vec<ArchiveEntry>, bool)> {
+    let mut listed = Vec::new();
+    for entry in entries {
+        if listed.len() == limit {
+            return Ok((listed, true));
+        }
+        let entry = entry?;
+        listed.push(ArchiveEntry {
+            path: entry.path()?.to_string_lossy().into_owned(),
+            size: entry.size(),
+            is_dir: entry.header().entry_type().is_dir(),
+        });
+    }
+    Ok((listed, false))
+}
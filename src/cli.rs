@@ -0,0 +1,78 @@
+use std::{io, path::PathBuf};
+
+use clap::Parser;
+
+use crate::app::SortMode;
+
+/// A terminal file manager. Options given here win over the config file.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Directory to start in (the current one if left out), or a file to
+    /// start on
+    pub path: Option<PathBuf>,
+    /// Show hidden files
+    #[arg(long)]
+    pub show_hidden: bool,
+    /// Sort by name, size, modified or ext
+    #[arg(long, value_name = "MODE", value_parser = parse_sort)]
+    pub sort: Option<SortMode>,
+    /// Syntax highlighting theme for previews
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+    /// Report file operations instead of doing them
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Browse only: refuse to delete, paste, move, rename, ... anything
+    #[arg(long)]
+    pub readonly: bool,
+    /// Open the preview of the file given as PATH
+    #[arg(long)]
+    pub preview: bool,
+}
+
+/// Where to open: a directory, and maybe the name of a file in it for the
+/// cursor to start on.
+#[derive(Debug, PartialEq)]
+pub struct Start {
+    pub dir: PathBuf,
+    pub file: Option<String>,
+    pub warning: Option<String>, // set when `path` doesn't exist
+}
+
+impl Args {
+    /// Where `path` says to start. A file opens its folder with the cursor
+    /// on it; a missing path falls back to its folder, or the current one.
+    pub fn start(&self) -> io::Result<Start> {
+        let cwd = std::env::current_dir()?;
+        let Some(path) = &self.path else {
+            return Ok(Start { dir: cwd, file: None, warning: None });
+        };
+        if path.is_dir() {
+            return Ok(Start { dir: path.canonicalize()?, file: None, warning: None });
+        }
+        let path = cwd.join(path);
+        let parent = path.parent().and_then(|p| p.canonicalize().ok()).filter(|p| p.is_dir());
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+        // Dangling links are still entries to land on
+        let exists = std::fs::symlink_metadata(&path).is_ok();
+        Ok(match parent {
+            Some(dir) if exists => Start { dir, file: name, warning: None },
+            Some(dir) => Start {
+                dir,
+                file: None,
+                warning: Some(format!("{} doesn't exist", path.display())),
+            },
+            None => Start {
+                dir: cwd,
+                file: None,
+                warning: Some(format!("{} doesn't exist", path.display())),
+            },
+        })
+    }
+}
+
+fn parse_sort(label: &str) -> Result<SortMode, String> {
+    SortMode::from_label(label)
+        .ok_or_else(|| format!("expected name, size, modified or ext, not '{}'", label))
+}
@@ -0,0 +1,52 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Access to the OS clipboard, behind a trait so the reducer can be tested
+/// without a display server.
+pub trait SystemClipboard {
+    fn set_text(&self, text: &str) -> Result<(), String>;
+}
+
+/// Pipes text into the first clipboard tool that works: `wl-copy` on Wayland,
+/// then `xclip`/`xsel` on X11, then `pbcopy` on macOS.
+pub struct CommandClipboard;
+
+const BACKENDS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+];
+
+impl SystemClipboard for CommandClipboard {
+    fn set_text(&self, text: &str) -> Result<(), String> {
+        let mut last_error = "no clipboard tool found (install wl-copy, xclip or xsel)".to_string();
+        for (program, args) in BACKENDS {
+            let child = Command::new(program)
+                .args(*args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            // Not installed: try the next one
+            let Ok(mut child) = child else {
+                continue;
+            };
+            if let Some(mut stdin) = child.stdin.take()
+                && let Err(e) = stdin.write_all(text.as_bytes())
+            {
+                last_error = format!("{}: {}", program, e);
+                let _ = child.wait();
+                continue;
+            }
+            match child.wait() {
+                Ok(status) if status.success() => return Ok(()),
+                Ok(status) => last_error = format!("{} exited with {}", program, status),
+                Err(e) => last_error = format!("{}: {}", program, e),
+            }
+        }
+        Err(last_error)
+    }
+}
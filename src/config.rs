@@ -0,0 +1,526 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::PathBuf,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{
+    app::{self, SortMode},
+    thumbnail::ImageArt,
+};
+
+/// `$XDG_CONFIG_HOME/file_management`, falling back to `~/.config/file_management`.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|dir| dir.join("file_management"))
+}
+
+/// `$XDG_STATE_HOME/file_management`, falling back to `~/.local/state/file_management`.
+pub fn state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+        .map(|dir| dir.join("file_management"))
+}
+
+/// Where file operations are logged when `ops_log` is on.
+pub fn ops_log_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("ops.log"))
+}
+
+fn recent_dirs_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("recent_dirs.txt"))
+}
+
+fn bookmarks_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("bookmarks.txt"))
+}
+
+fn pinned_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("pinned.txt"))
+}
+
+fn recent_commands_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("open_with.txt"))
+}
+
+/// Reads a list saved one item per line. A missing file means an empty list.
+fn read_lines(file: Option<PathBuf>) -> Vec<String> {
+    file.and_then(|file| fs::read_to_string(file).ok())
+        .map(|text| {
+            text.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_lines<'a>(
+    file: Option<PathBuf>,
+    lines: impl Iterator<Item = Cow<'a, str>>,
+) -> io::Result<()> {
+    let file = file.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    fs::write(file, text)
+}
+
+/// Reads the recently visited directories, newest first.
+pub fn load_recent_dirs() -> Vec<PathBuf> {
+    read_lines(recent_dirs_file()).into_iter().map(PathBuf::from).collect()
+}
+
+pub fn save_recent_dirs(dirs: &[PathBuf]) -> io::Result<()> {
+    write_lines(recent_dirs_file(), dirs.iter().map(|path| path.to_string_lossy()))
+}
+
+/// Reads saved bookmarks, one path per line.
+pub fn load_bookmarks() -> Vec<PathBuf> {
+    read_lines(bookmarks_file()).into_iter().map(PathBuf::from).collect()
+}
+
+pub fn save_bookmarks(bookmarks: &[PathBuf]) -> io::Result<()> {
+    write_lines(bookmarks_file(), bookmarks.iter().map(|path| path.to_string_lossy()))
+}
+
+/// Reads the pinned paths, one per line.
+pub fn load_pinned() -> HashSet<PathBuf> {
+    read_lines(pinned_file()).into_iter().map(PathBuf::from).collect()
+}
+
+pub fn save_pinned(pinned: &HashSet<PathBuf>) -> io::Result<()> {
+    let mut paths: Vec<&PathBuf> = pinned.iter().collect();
+    paths.sort(); // keeps the file stable between saves
+    write_lines(pinned_file(), paths.into_iter().map(|path| path.to_string_lossy()))
+}
+
+/// Commands picked in the "open with" popup, most recent first.
+pub fn load_recent_commands() -> Vec<String> {
+    read_lines(recent_commands_file())
+}
+
+pub fn save_recent_commands(commands: &[String]) -> io::Result<()> {
+    write_lines(recent_commands_file(), commands.iter().map(|c| Cow::from(c.as_str())))
+}
+
+fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Largest file the preview reads by default; the rest is cut off.
+pub const DEFAULT_PREVIEW_MAX_BYTES: u64 = 4 * 1024 * 1024;
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+/// Startup options and keybindings from `config.toml`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub show_hidden: bool,
+    pub sort_mode: SortMode,
+    pub dirs_first: bool,
+    pub per_dir_view: bool,
+    pub size_colors: bool, // shade file sizes by how big they are
+    pub clear_selection_on_nav: bool,
+    pub split_ratio: u16, // the file list's share of the width, in percent
+    pub ops_log: bool,    // append every copy, move, delete, ... to `ops_log_file()`
+    pub trash: bool,      // deletes go to `trash::trash_dir()`, so undo can restore them
+    pub dry_run: bool,    // report file operations instead of doing them
+    pub confirm_overwrite: bool, // ask before a paste lands on an existing name
+    pub readonly: bool,          // no file operations at all
+    pub preview_max_bytes: u64,
+    pub preview_page_step: usize, // lines per page in the preview; 0 fits the pane
+    pub image_art: ImageArt, // images without a graphics protocol
+    pub previewers: HashMap<String, String>, // extension or MIME type -> shell command
+    pub theme: String,
+    pub keymap: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            show_hidden: false,
+            sort_mode: SortMode::Name,
+            dirs_first: true,
+            per_dir_view: false,
+            size_colors: false,
+            clear_selection_on_nav: false,
+            split_ratio: 50,
+            ops_log: false,
+            trash: true,
+            dry_run: false,
+            confirm_overwrite: false,
+            readonly: false,
+            preview_max_bytes: DEFAULT_PREVIEW_MAX_BYTES,
+            preview_page_step: 0,
+            image_art: ImageArt::Color,
+            previewers: HashMap::new(),
+            theme: DEFAULT_THEME.to_string(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+// The file as written; every field may be left out.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    show_hidden: Option<bool>,
+    sort: Option<SortMode>,
+    dirs_first: Option<bool>,
+    per_dir_view: Option<bool>,
+    size_colors: Option<bool>,
+    clear_selection_on_nav: Option<bool>,
+    split_ratio: Option<u16>,
+    ops_log: Option<bool>,
+    trash: Option<bool>,
+    dry_run: Option<bool>,
+    confirm_overwrite: Option<bool>,
+    readonly: Option<bool>,
+    preview_max_bytes: Option<u64>,
+    preview_page_step: Option<usize>,
+    image_art: Option<ImageArt>,
+    theme: Option<String>,
+    keys: HashMap<Command, KeyList>,
+    previewers: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Reads `config.toml`. A missing file gives the defaults; a broken one is an
+/// error for the caller to report, so a typo never stops the app starting.
+pub fn load_config() -> Result<Config, String> {
+    let Some(file) = config_file() else {
+        return Ok(Config::default());
+    };
+    match fs::read_to_string(&file) {
+        Ok(text) => parse_config(&text).map_err(|e| format!("{}: {}", file.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", file.display(), e)),
+    }
+}
+
+pub fn parse_config(text: &str) -> Result<Config, String> {
+    let file: ConfigFile = toml::from_str(text).map_err(|e| e.message().to_string())?;
+    let defaults = Config::default();
+
+    let mut keymap = defaults.keymap;
+    for (command, keys) in file.keys {
+        let keys = match keys {
+            KeyList::One(key) => vec![key],
+            KeyList::Many(keys) => keys,
+        };
+        let keys = keys.iter().map(|k| parse_key(k)).collect::<Result<Vec<_>, _>>()?;
+        keymap.rebind(command, keys);
+    }
+
+    Ok(Config {
+        show_hidden: file.show_hidden.unwrap_or(defaults.show_hidden),
+        sort_mode: file.sort.unwrap_or(defaults.sort_mode),
+        dirs_first: file.dirs_first.unwrap_or(defaults.dirs_first),
+        per_dir_view: file.per_dir_view.unwrap_or(defaults.per_dir_view),
+        size_colors: file.size_colors.unwrap_or(defaults.size_colors),
+        clear_selection_on_nav: file
+            .clear_selection_on_nav
+            .unwrap_or(defaults.clear_selection_on_nav),
+        split_ratio: file
+            .split_ratio
+            .unwrap_or(defaults.split_ratio)
+            .clamp(app::SPLIT_MIN, app::SPLIT_MAX),
+        ops_log: file.ops_log.unwrap_or(defaults.ops_log),
+        trash: file.trash.unwrap_or(defaults.trash),
+        dry_run: file.dry_run.unwrap_or(defaults.dry_run),
+        confirm_overwrite: file.confirm_overwrite.unwrap_or(defaults.confirm_overwrite),
+        readonly: file.readonly.unwrap_or(defaults.readonly),
+        preview_max_bytes: file.preview_max_bytes.unwrap_or(defaults.preview_max_bytes),
+        preview_page_step: file.preview_page_step.unwrap_or(defaults.preview_page_step),
+        image_art: file.image_art.unwrap_or(defaults.image_art),
+        previewers: file.previewers.into_iter().map(|(k, v)| (k.to_lowercase(), v)).collect(),
+        theme: file.theme.unwrap_or(defaults.theme),
+        keymap,
+    })
+}
+
+/// Everything a key can be bound to in the file list. Named in `config.toml`
+/// in snake_case, e.g. `toggle_hidden = "."`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Quit,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    Enter, // enter a directory, or pan right in the preview
+    Back,  // parent directory, or pan left in the preview
+    HistoryBack,
+    HistoryForward,
+    SwitchFocus,
+    NewTab,
+    CloseTab,
+    NextTab, // or switch panes in dual-pane mode
+    DualPane,
+    Columns,
+    CopyToPane,
+    MoveToPane,
+    MoveTo,
+    ToggleSelect,
+    SelectAll,
+    InvertSelection,
+    VisualMode,
+    Yank,
+    CopyPath,
+    CopyContents, // of the text preview; needs the preview focused
+    Paste,
+    PasteInto,
+    Undo,
+    Delete,
+    Duplicate,
+    Diff,
+    BulkRename,
+    Symlink,
+    Chmod,
+    Chown,
+    Extract,
+    Compress,
+    Open,
+    OpenWith,
+    Edit,
+    Properties,
+    DirSize,
+    SummarizeSelection,
+    Bookmark,
+    OpenBookmarks,
+    TogglePin,
+    RecentDirs,
+    ToggleHidden,
+    ShrinkList,
+    GrowList,
+    Flatten,
+    ToggleLineNumbers,
+    PreviewWrap,
+    CycleTheme,
+    CycleSort,
+    ReverseSort,
+    Search, // filter the list, or search the preview when it has focus
+    SearchNext,
+    SearchPrev,
+    Cancel,
+    FuzzyFind,
+    Grep,
+    GoToPath,
+    CommandLine,
+    Preview,
+}
+
+// Keys that mean one thing whatever has focus; the rest are in `SHARED_KEYS`
+const DEFAULT_KEYS: &[(Command, &[&str])] = &[
+    (Command::Quit, &["q"]),
+    (Command::Down, &["j", "down"]),
+    (Command::Up, &["k", "up"]),
+    // Single `g` rather than vim's `gg`, so the jump needs no pending-key state
+    (Command::Top, &["g"]),
+    (Command::Bottom, &["G"]),
+    (Command::PageUp, &["ctrl+b", "pageup"]),
+    (Command::PageDown, &["pagedown"]),
+    (Command::HalfPageUp, &["ctrl+u"]),
+    (Command::HalfPageDown, &["ctrl+d"]),
+    (Command::Enter, &["enter", "l", "right"]),
+    (Command::Back, &["backspace", "h", "left"]),
+    (Command::HistoryBack, &["alt+left"]),
+    (Command::HistoryForward, &["alt+right"]),
+    (Command::SwitchFocus, &["ctrl+tab", "ctrl+h"]),
+    (Command::NewTab, &["ctrl+t"]),
+    (Command::CloseTab, &["ctrl+w"]),
+    (Command::NextTab, &["tab"]),
+    (Command::DualPane, &["|"]),
+    (Command::Columns, &["M"]),
+    // Midnight Commander's keys
+    (Command::CopyToPane, &["f5"]),
+    (Command::MoveToPane, &["f6"]),
+    (Command::MoveTo, &["ctrl+x"]),
+    (Command::ToggleSelect, &["space"]),
+    (Command::SelectAll, &["ctrl+a"]),
+    (Command::InvertSelection, &["*"]),
+    (Command::VisualMode, &["v"]),
+    (Command::Yank, &["y"]),
+    (Command::CopyPath, &["Y"]),
+    (Command::CopyContents, &["ctrl+y"]),
+    (Command::Paste, &["P"]),
+    (Command::PasteInto, &["alt+p"]),
+    (Command::Undo, &["u"]),
+    (Command::Delete, &["d"]),
+    (Command::Duplicate, &["c"]),
+    (Command::Diff, &["D"]),
+    (Command::BulkRename, &["R"]),
+    (Command::Symlink, &["L"]),
+    (Command::Chmod, &["x"]),
+    (Command::Chown, &["C"]),
+    (Command::Extract, &["E"]),
+    (Command::Compress, &["Z"]),
+    (Command::Open, &["o"]),
+    (Command::OpenWith, &["O"]),
+    (Command::Edit, &["e"]),
+    (Command::Properties, &["i"]),
+    (Command::DirSize, &["ctrl+s"]),
+    (Command::SummarizeSelection, &["I"]),
+    (Command::Bookmark, &["m"]),
+    (Command::OpenBookmarks, &["'"]),
+    (Command::TogglePin, &["ctrl+p"]),
+    (Command::RecentDirs, &["ctrl+o"]),
+    (Command::ToggleHidden, &["."]),
+    (Command::ShrinkList, &["<"]),
+    (Command::GrowList, &[">"]),
+    (Command::Flatten, &["f"]),
+    (Command::ToggleLineNumbers, &["#"]),
+    (Command::PreviewWrap, &["w"]),
+    (Command::CycleTheme, &["t"]),
+    (Command::CycleSort, &["s"]),
+    (Command::ReverseSort, &["S"]),
+    (Command::Search, &["/"]),
+    (Command::SearchNext, &["n"]),
+    (Command::SearchPrev, &["N"]),
+    (Command::Cancel, &["esc"]),
+    (Command::Grep, &["F"]),
+    (Command::GoToPath, &["ctrl+g"]),
+    (Command::CommandLine, &[":"]),
+    (Command::Preview, &["p"]),
+];
+
+/// Default keys bound to two commands: (key, in the file list, with the
+/// preview focused). ctrl+f is fuzzy find in the list and Vim's page down
+/// in the preview.
+const SHARED_KEYS: &[(&str, Command, Command)] =
+    &[("ctrl+f", Command::FuzzyFind, Command::PageDown)];
+
+/// Key -> command lookup for the file list, plus the few keys that do
+/// something else while the preview has focus.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<(KeyCode, KeyModifiers), Command>,
+    preview: HashMap<(KeyCode, KeyModifiers), Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        for (command, keys) in DEFAULT_KEYS {
+            for key in *keys {
+                let key = parse_key(key).expect("default keys parse");
+                bindings.insert(key, *command);
+            }
+        }
+        let mut preview = HashMap::new();
+        for (key, list, in_preview) in SHARED_KEYS {
+            let key = parse_key(key).expect("default keys parse");
+            bindings.insert(key, *list);
+            preview.insert(key, *in_preview);
+        }
+        Keymap { bindings, preview }
+    }
+}
+
+impl Keymap {
+    /// Replaces every key bound to `command`. The new keys win over any
+    /// other command they were bound to.
+    pub fn rebind(&mut self, command: Command, keys: Vec<(KeyCode, KeyModifiers)>) {
+        self.bindings.retain(|_, bound| *bound != command);
+        self.preview.retain(|_, bound| *bound != command);
+        for key in keys {
+            self.preview.remove(&key);
+            self.bindings.insert(key, command);
+        }
+    }
+
+    pub fn lookup(&self, key: &KeyEvent) -> Option<Command> {
+        self.bindings.get(&normalize(key.code, key.modifiers)).copied()
+    }
+
+    /// Like `lookup`, for when the preview has focus.
+    pub fn lookup_in_preview(&self, key: &KeyEvent) -> Option<Command> {
+        let key = normalize(key.code, key.modifiers);
+        self.preview.get(&key).or_else(|| self.bindings.get(&key)).copied()
+    }
+}
+
+// Shift is already part of an uppercase or symbol character, and terminals
+// disagree on whether to report it as well.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(_) => (code, modifiers - KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    }
+}
+
+/// Parses a key like `q`, `G`, `ctrl+t`, `alt+left` or `space`.
+pub fn parse_key(spec: &str) -> Result<(KeyCode, KeyModifiers), String> {
+    // Split off the last `+` only, so `+` and `ctrl++` work too
+    let (mods, key) = match spec.rsplit_once('+') {
+        Some((mods, "")) => (mods.strip_suffix('+').unwrap_or(mods), "+"),
+        Some((mods, key)) => (mods, key),
+        None => ("", spec),
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for m in mods.split('+').filter(|m| !m.is_empty()) {
+        modifiers |= match m.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier `{}` in key `{}`", m, spec)),
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key `{}`", spec)),
+            },
+        },
+    };
+    Ok(normalize(code, modifiers))
+}
@@ -0,0 +1,93 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+    time::Duration,
+};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Line,
+};
+use similar::{ChangeTag, TextDiff};
+
+// Bytes of each file compared; the rest is left out
+pub const DIFF_MAX_BYTES: u64 = 1024 * 1024;
+// Lines of diff shown, headers included
+pub const DIFF_MAX_LINES: usize = 5000;
+// Past this the diff is approximated rather than exact
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+// Lines of unchanged context around each change
+const CONTEXT_LINES: usize = 3;
+
+/// A unified diff of `old` against `new`, colored for the preview pane.
+/// Binary files are only compared byte for byte.
+pub fn diff_files(old: &Path, new: &Path) -> io::Result<Vec<Line<'static>>> {
+    let (old_bytes, old_cut) = read_capped(old)?;
+    let (new_bytes, new_cut) = read_capped(new)?;
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let (old_name, new_name) = (name(old), name(new));
+
+    if old_bytes == new_bytes && !old_cut && !new_cut {
+        return Ok(vec![Line::from("Files are identical")]);
+    }
+    if is_binary(&old_bytes) || is_binary(&new_bytes) {
+        let text = format!("Binary files {} and {} differ", old_name, new_name);
+        return Ok(vec![Line::from(text)]);
+    }
+
+    let old_text = String::from_utf8_lossy(&old_bytes);
+    let new_text = String::from_utf8_lossy(&new_bytes);
+    let diff = TextDiff::configure().timeout(DIFF_TIMEOUT).diff_lines(&old_text, &new_text);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::styled(format!("--- {}", old_name), bold),
+        Line::styled(format!("+++ {}", new_name), bold),
+    ];
+    if old_cut || new_cut {
+        let note = format!("(only the first {} KiB of each file compared)", DIFF_MAX_BYTES / 1024);
+        lines.push(Line::styled(note, Style::default().fg(Color::DarkGray)));
+    }
+    let mut more = 0;
+    for hunk in diff.unified_diff().context_radius(CONTEXT_LINES).iter_hunks() {
+        let header = hunk.header().to_string();
+        let changes = hunk.iter_changes().map(|change| {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ('-', Style::default().fg(Color::Red)),
+                ChangeTag::Insert => ('+', Style::default().fg(Color::Green)),
+                ChangeTag::Equal => (' ', Style::default()),
+            };
+            let text = change.value().trim_end_matches(['\n', '\r']);
+            Line::styled(format!("{}{}", sign, text), style)
+        });
+        let hunk_lines = std::iter::once(Line::styled(header, Style::default().fg(Color::Cyan)))
+            .chain(changes);
+        for line in hunk_lines {
+            if lines.len() < DIFF_MAX_LINES {
+                lines.push(line);
+            } else {
+                more += 1;
+            }
+        }
+    }
+    if more > 0 {
+        lines.push(Line::styled(
+            format!("…and {} more lines", more),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    Ok(lines)
+}
+
+// The start of `path`, and whether there was more
+fn read_capped(path: &Path) -> io::Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?.take(DIFF_MAX_BYTES + 1).read_to_end(&mut bytes)?;
+    let cut = bytes.len() as u64 > DIFF_MAX_BYTES;
+    bytes.truncate(DIFF_MAX_BYTES as usize);
+    Ok((bytes, cut))
+}
+
+// Text files don't have NULs; checking the start is enough
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|&b| b == 0)
+}
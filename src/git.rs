@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// What `git status` says about a file, or about something inside a directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitState {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    Conflicted,
+}
+
+impl GitState {
+    /// The marker drawn in the file list, as `git status --short` writes it.
+    pub fn marker(self) -> &'static str {
+        match self {
+            GitState::Modified => "M",
+            GitState::Added => "A",
+            GitState::Deleted => "D",
+            GitState::Renamed => "R",
+            GitState::Untracked => "??",
+            GitState::Conflicted => "U",
+        }
+    }
+
+    // From the XY status code of a porcelain line
+    fn from_code(code: &str) -> Option<Self> {
+        let (x, y) = (code.chars().next()?, code.chars().nth(1)?);
+        Some(match (x, y) {
+            ('?', '?') => GitState::Untracked,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => GitState::Conflicted,
+            ('A', _) => GitState::Added,
+            ('R' | 'C', _) => GitState::Renamed,
+            ('D', _) | (_, 'D') => GitState::Deleted,
+            ('M' | 'T', _) | (_, 'M' | 'T') => GitState::Modified,
+            _ => return None,
+        })
+    }
+}
+
+/// The status of `dir`'s entries, keyed by entry name. A directory gets the
+/// state of its changes, or `Modified` if they differ. None when `dir` isn't
+/// inside a work tree or `git` isn't installed.
+pub fn status(dir: &Path) -> Option<HashMap<String, GitState>> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // `dir` relative to the top of the work tree, e.g. "src/"
+    let prefix = git(&["rev-parse", "--show-prefix"])?;
+    let output = git(&["status", "--porcelain", "-z"])?;
+    Some(parse_porcelain(&output, prefix.trim_end_matches('\n')))
+}
+
+/// Parses `git status --porcelain -z` output. Its paths are relative to the
+/// top of the work tree, so only those under `prefix` are kept.
+pub fn parse_porcelain(output: &str, prefix: &str) -> HashMap<String, GitState> {
+    let mut states = HashMap::new();
+    let mut records = output.split('\0');
+    while let Some(record) = records.next() {
+        let (Some(code), Some(path)) = (record.get(..2), record.get(3..)) else {
+            continue;
+        };
+        // Renames and copies are followed by the old path
+        if code.starts_with(['R', 'C']) {
+            records.next();
+        }
+        let Some(state) = GitState::from_code(code) else {
+            continue;
+        };
+        let Some(rest) = path.strip_prefix(prefix) else {
+            continue;
+        };
+        let Some(name) = rest.split('/').find(|part| !part.is_empty()) else {
+            continue;
+        };
+        states
+            .entry(name.to_string())
+            .and_modify(|old| {
+                if *old != state {
+                    *old = GitState::Modified;
+                }
+            })
+            .or_insert(state);
+    }
+    states
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use image::RgbaImage;
+use ratatui::layout::Rect;
+
+/// Terminal image protocols we know how to speak.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Sixel,
+}
+
+/// Guesses graphics support from the environment. There's no reliable way to
+/// query the terminal without racing the input reader, so this goes by
+/// `TERM`/`TERM_PROGRAM`; `FILE_MANAGEMENT_GRAPHICS=kitty|sixel|none` overrides it.
+pub fn detect() -> Option<GraphicsProtocol> {
+    let var = |key: &str| std::env::var(key).unwrap_or_default();
+    match var("FILE_MANAGEMENT_GRAPHICS").as_str() {
+        "kitty" => return Some(GraphicsProtocol::Kitty),
+        "sixel" => return Some(GraphicsProtocol::Sixel),
+        "none" | "off" => return None,
+        _ => {}
+    }
+    // tmux swallows both protocols unless passthrough is set up
+    if std::env::var_os("TMUX").is_some() {
+        return None;
+    }
+
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term.contains("kitty")
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+    {
+        Some(GraphicsProtocol::Kitty)
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        Some(GraphicsProtocol::Sixel)
+    } else {
+        None
+    }
+}
+
+/// Pixel size of one terminal cell, falling back to a typical 8x16 when the
+/// terminal doesn't report its pixel dimensions.
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns).max(1) as u32,
+            (size.height / size.rows).max(1) as u32,
+        ),
+        _ => (8, 16),
+    }
+}
+
+/// Decodes `path` and scales it to fit inside `area` (keeping the aspect
+/// ratio), returning the escape sequence that draws it at the cursor.
+pub fn encode(protocol: GraphicsProtocol, path: &Path, area: Rect) -> Option<String> {
+    let (cell_w, cell_h) = cell_size();
+    let img = image::open(path).ok()?;
+    let img = img
+        .thumbnail(area.width as u32 * cell_w, area.height as u32 * cell_h)
+        .to_rgba8();
+    if img.width() == 0 || img.height() == 0 {
+        return None;
+    }
+    Some(match protocol {
+        GraphicsProtocol::Kitty => encode_kitty(&img),
+        GraphicsProtocol::Sixel => encode_sixel(&img),
+    })
+}
+
+// Raw RGBA, base64'd and split into the 4096-byte chunks the protocol requires.
+// C=1 keeps the cursor where it is; q=2 silences the terminal's replies.
+fn encode_kitty(img: &RgbaImage) -> String {
+    let data = STANDARD.encode(img.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 16);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=32,s={},v={},C=1,q=2,m={};{}\x1b\\",
+                img.width(),
+                img.height(),
+                more,
+                chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out
+}
+
+// Quantizes to a fixed 6x6x6 color cube, which every sixel terminal can hold
+// in its palette. Transparent pixels are left as background.
+fn encode_sixel(img: &RgbaImage) -> String {
+    let (width, height) = img.dimensions();
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for i in 0..216u32 {
+        let (r, g, b) = (i / 36, i / 6 % 6, i % 6);
+        out.push_str(&format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20));
+    }
+
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    for band in (0..height).step_by(6) {
+        // Palette index -> one sixel (6 vertical bits) per column
+        let mut layers: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                let px = img.get_pixel(x, y);
+                if px[3] < 128 {
+                    continue;
+                }
+                let color = level(px[0]) * 36 + level(px[1]) * 6 + level(px[2]);
+                layers.entry(color).or_insert_with(|| vec![0; width as usize])[x as usize] |=
+                    1 << (y - band);
+            }
+        }
+        for (n, (color, bits)) in layers.iter().enumerate() {
+            if n > 0 {
+                out.push('$'); // back to the start of the band for the next color
+            }
+            out.push_str(&format!("#{}", color));
+            push_sixel_run(&mut out, bits);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+// Run-length encodes a row of sixels (`!<count><char>`).
+fn push_sixel_run(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let mut run = 1;
+        while i + run < bits.len() && bits[i + run] == bits[i] {
+            run += 1;
+        }
+        let ch = (63 + bits[i]) as char;
+        if run > 3 {
+            out.push_str(&format!("!{}{}", run, ch));
+        } else {
+            (0..run).for_each(|_| out.push(ch));
+        }
+        i += run;
+    }
+}
+
+/// Tracks the image currently on screen. Graphics live outside ratatui's
+/// buffer, so they have to be removed by hand whenever the preview changes.
+pub struct ImageOverlay {
+    protocol: GraphicsProtocol,
+    shown: Option<(PathBuf, Rect)>,
+}
+
+impl ImageOverlay {
+    pub fn new(protocol: GraphicsProtocol) -> Self {
+        Self { protocol, shown: None }
+    }
+
+    pub fn is_current(&self, target: &Option<(PathBuf, Rect)>) -> bool {
+        &self.shown == target
+    }
+
+    /// Removes the shown image. Sixel pixels can't be deleted, only painted
+    /// over, so this returns true when the caller has to repaint the screen.
+    pub fn erase(&mut self, w: &mut impl Write) -> io::Result<bool> {
+        if self.shown.take().is_none() {
+            return Ok(false);
+        }
+        match self.protocol {
+            GraphicsProtocol::Kitty => {
+                w.write_all(b"\x1b_Ga=d,q=2\x1b\\")?;
+                w.flush()?;
+                Ok(false)
+            }
+            GraphicsProtocol::Sixel => Ok(true),
+        }
+    }
+
+    pub fn show(&mut self, w: &mut impl Write, path: PathBuf, area: Rect) -> io::Result<()> {
+        // Remember failures too, so a broken image isn't decoded every frame
+        if let Some(seq) = encode(self.protocol, &path, area) {
+            crossterm::queue!(w, crossterm::cursor::MoveTo(area.x, area.y))?;
+            w.write_all(seq.as_bytes())?;
+            w.flush()?;
+        }
+        self.shown = Some((path, area));
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use regex::Regex;
+use walkdir::WalkDir;
+
+use crate::app::{BINARY_SNIFF_BYTES, looks_binary};
+
+// Bounds on a search, so a huge tree can't run away with it
+pub const MAX_DEPTH: usize = 8;
+pub const MAX_MATCHES: usize = 1000;
+// Bigger files are skipped; they're rarely what a text search is after
+const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+// Long lines (minified files) are cut to this in the results
+const MAX_LINE_CHARS: usize = 200;
+
+/// What a line has to contain: a plain substring (case-insensitive, like the
+/// preview search) or a regex, taken as written.
+#[derive(Clone, Debug)]
+pub enum Matcher {
+    Plain(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str, regex: bool) -> Result<Self, String> {
+        if regex {
+            Regex::new(query).map(Matcher::Regex).map_err(|e| e.to_string())
+        } else {
+            Ok(Matcher::Plain(query.to_lowercase()))
+        }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            Matcher::Plain(query) => line.to_lowercase().contains(query),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrepMatch {
+    pub path: PathBuf, // relative to the search root
+    pub line: usize,   // 1-based
+    pub text: String,
+}
+
+/// Searches the files under `root` line by line, handing each file's matches
+/// to `on_matches` as soon as it's done. Binary, huge and `.git` files are
+/// skipped. Stops early when `cancel` is set; true if `MAX_MATCHES` cut the
+/// search short.
+pub fn search(
+    root: &Path,
+    matcher: &Matcher,
+    cancel: &AtomicBool,
+    mut on_matches: impl FnMut(Vec<GrepMatch>),
+) -> bool {
+    let mut found = 0;
+    let walker = WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(MAX_DEPTH)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in walker.filter_map(|e| e.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+        let too_big = entry.metadata().map_or(true, |m| m.len() > MAX_FILE_BYTES);
+        if !entry.file_type().is_file() || too_big {
+            continue;
+        }
+        let Some(text) = read_text(entry.path()) else {
+            continue;
+        };
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let mut matches = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            if !matcher.is_match(line) {
+                continue;
+            }
+            matches.push(GrepMatch {
+                path: rel.to_path_buf(),
+                line: idx + 1,
+                text: line.trim().chars().take(MAX_LINE_CHARS).collect(),
+            });
+            found += 1;
+            if found == MAX_MATCHES {
+                on_matches(matches);
+                return true;
+            }
+        }
+        if !matches.is_empty() {
+            on_matches(matches);
+        }
+    }
+    false
+}
+
+// The file as text, or None if it looks binary. Not-quite-UTF-8 is patched up
+fn read_text(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    let head = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES as usize)];
+    if looks_binary(head) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,86 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use syntect::{
+    highlighting::{HighlightIterator, HighlightState, Highlighter, Style, Theme},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+// Lines between saved parser states; scrolling re-parses at most this many
+// lines above the window
+const CHECKPOINT_EVERY: usize = 64;
+
+/// Syntax highlighting that stays correct when scrolled.
+///
+/// syntect is stateful (a block comment opened on line 10 colours line 200),
+/// so every line has to be parsed from the top. The parser state is saved every
+/// `CHECKPOINT_EVERY` lines, so that's paid once per file rather than per frame.
+#[derive(Default)]
+pub struct HighlightCache {
+    key: u64, // content + syntax + theme the checkpoints belong to
+    checkpoints: Vec<(ParseState, HighlightState)>, // state at line i * CHECKPOINT_EVERY
+}
+
+impl HighlightCache {
+    /// Highlights `take` lines of `content` starting at line `skip`. Control
+    /// characters other than tabs are dropped so they can't garble the screen.
+    pub fn highlight(
+        &mut self,
+        content: &str,
+        syntax: &SyntaxReference,
+        syntax_set: &SyntaxSet,
+        theme: &Theme,
+        skip: usize,
+        take: usize,
+    ) -> Vec<Vec<(Style, String)>> {
+        let highlighter = Highlighter::new(theme);
+
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        syntax.name.hash(&mut hasher);
+        theme.name.hash(&mut hasher);
+        let key = hasher.finish();
+        if key != self.key || self.checkpoints.is_empty() {
+            self.key = key;
+            self.checkpoints = vec![(
+                ParseState::new(syntax),
+                HighlightState::new(&highlighter, ScopeStack::new()),
+            )];
+        }
+
+        let start = (skip / CHECKPOINT_EVERY).min(self.checkpoints.len() - 1);
+        let (mut parse, mut state) = self.checkpoints[start].clone();
+        let mut out = Vec::with_capacity(take);
+
+        for (idx, line) in content
+            .lines()
+            .enumerate()
+            .skip(start * CHECKPOINT_EVERY)
+            .take_while(|(idx, _)| *idx < skip + take)
+        {
+            if idx % CHECKPOINT_EVERY == 0 && idx / CHECKPOINT_EVERY == self.checkpoints.len() {
+                self.checkpoints.push((parse.clone(), state.clone()));
+            }
+
+            // The default syntaxes expect the newline, e.g. to end `//` comments
+            let mut clean: String = line
+                .chars()
+                .filter(|c| !c.is_control() || *c == '\t')
+                .collect();
+            clean.push('\n');
+
+            let ops = parse.parse_line(&clean, syntax_set).unwrap_or_default();
+            let ranges = HighlightIterator::new(&mut state, &ops, &clean, &highlighter);
+            if idx < skip {
+                ranges.for_each(drop); // only advancing the state
+                continue;
+            }
+            out.push(
+                ranges
+                    .map(|(style, text)| (style, text.trim_end_matches('\n').to_string()))
+                    .filter(|(_, text)| !text.is_empty())
+                    .collect(),
+            );
+        }
+        out
+    }
+}
@@ -1,3 +1,22 @@
 pub mod app;
+pub mod archive;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod diff;
+pub mod git;
+pub mod grep;
+pub mod graphics;
+pub mod highlight;
+pub mod markdown;
+pub mod media;
 pub mod ops;
-
+pub mod oplog;
+pub mod preview_cache;
+pub mod preview_command;
+pub mod rename;
+pub mod thumbnail;
+pub mod trash;
+#[cfg(unix)]
+pub mod users;
+pub mod watcher;
@@ -1,22 +1,77 @@
-mod app;
-mod ops;
-
-use std::{collections::HashSet, io, time::Duration};
+use std::{
+    io,
+    process::Stdio,
+    time::{Duration, Instant},
+};
 
-use app::{
-    Action, ActiveFocus, AppState, DefaultPreviewLoader, PreviewLoader, PreviewState, Reducer,
-    read_entries, ui,
+use file_management::{
+    app::{
+        self, Action, ActiveFocus, AppState, ConflictChoice, DefaultPreviewLoader, ExternalCommand,
+        PreviewLoader, PreviewState, Reducer, ui,
+    },
+    cli::Args,
+    config::{self, Command, Config, Keymap},
+    graphics::{self, ImageOverlay},
+    preview_command::{self, CommandPreviewLoader},
+    thumbnail::ImageArt,
+    trash,
+    watcher::DirWatcher,
 };
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use clap::Parser;
 use ratatui::{Terminal, backend::CrosstermBackend};
-use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Create App State (before touching the terminal, so a failure leaves it intact)
+    let args = Args::parse();
+    let start = args.start()?;
+    // A broken config file is reported in the status line, not fatal
+    let (config, config_error) = match config::load_config() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(format!("Config error: {}", e))),
+    };
+    let mut state = AppState::new(start.dir)?;
+    state.show_hidden = config.show_hidden || args.show_hidden;
+    state.sort_mode = args.sort.unwrap_or(config.sort_mode);
+    state.dirs_first = config.dirs_first;
+    state.per_dir_view = config.per_dir_view;
+    state.size_colors = config.size_colors;
+    state.clear_selection_on_nav = config.clear_selection_on_nav;
+    state.split_ratio = config.split_ratio;
+    state.ops_log = config.ops_log.then(config::ops_log_file).flatten();
+    state.trash = config.trash.then(trash::trash_dir).flatten();
+    state.dry_run = config.dry_run || args.dry_run;
+    state.readonly = config.readonly || args.readonly;
+    state.confirm_overwrite = config.confirm_overwrite;
+    state.image_art = config.image_art;
+    state.preview_page_step = config.preview_page_step;
+    state.theme_name = args.theme.unwrap_or_else(|| config.theme.clone());
+    state.pinned = config::load_pinned();
+    state.reload_entries();
+    state.refresh_git_status();
+    // Start on the file given on the command line, even if it's hidden
+    if let Some(name) = &start.file
+        && !state.select_entry_by_name(name)
+    {
+        state.show_hidden = true;
+        state.reload_entries();
+        state.select_entry_by_name(name);
+    }
+    state.status = config_error.or(start.warning);
+    state.bookmarks = config::load_bookmarks();
+    state.recent_commands = config::load_recent_commands();
+    state.recent_dirs = config::load_recent_dirs();
+    state.remember_dir();
+    state.graphics = graphics::detect();
+
     // Setup Terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -24,29 +79,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create App State
-    let cwd = std::env::current_dir()?;
-    let entries = read_entries(&cwd)?; // Used from app module
-
-    let syntax_set = SyntaxSet::load_defaults_newlines();
-    let theme_set = ThemeSet::load_defaults();
-
-    let mut state = AppState {
-        cwd,
-        entries,
-        cursor: 0,
-        selected: HashSet::new(),
-        preview: PreviewState::None,
-        syntax_set,
-        theme_set,
-        clipboard: None,
-        active_focus: ActiveFocus::FileList,
-        preview_scroll: 0,
-        popup: app::PopupState::None,
+    let loader = CommandPreviewLoader {
+        commands: config.previewers.clone(),
+        timeout: preview_command::DEFAULT_TIMEOUT,
+        fallback: DefaultPreviewLoader {
+            max_bytes: config.preview_max_bytes,
+            thumbnails: state.graphics.is_none() && config.image_art != ImageArt::Off,
+        },
     };
-
-    let loader = DefaultPreviewLoader;
-    let res = run_app(&mut terminal, &mut state, &loader);
+    if args.preview && start.file.is_some() {
+        load_preview(&mut state, &loader);
+    }
+    let res = run_app(&mut terminal, &mut state, &loader, &config.keymap);
 
     // Restore Terminal
     disable_raw_mode()?;
@@ -57,6 +101,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )?;
     terminal.show_cursor()?;
 
+    if let Err(e) = config::save_recent_dirs(&state.recent_dirs) {
+        eprintln!("Couldn't save recent directories: {}", e);
+    }
     if let Err(err) = res {
         println!("{:?}", err);
     }
@@ -64,118 +111,478 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
+fn run_app<B: ratatui::backend::Backend + io::Write>(
     terminal: &mut Terminal<B>,
     state: &mut AppState,
     loader: &impl PreviewLoader,
+    keymap: &Keymap,
 ) -> io::Result<()> {
+    let mut overlay = state.graphics.map(ImageOverlay::new);
+    // Without a watcher the listing only refreshes on navigation
+    let mut watcher = DirWatcher::new(state.action_tx.clone()).ok();
+    let res = event_loop(terminal, state, loader, keymap, &mut overlay, &mut watcher);
+    if let Some(overlay) = overlay.as_mut() {
+        overlay.erase(terminal.backend_mut())?;
+    }
+    res
+}
+
+fn event_loop<B: ratatui::backend::Backend + io::Write>(
+    terminal: &mut Terminal<B>,
+    state: &mut AppState,
+    loader: &impl PreviewLoader,
+    keymap: &Keymap,
+    overlay: &mut Option<ImageOverlay>,
+    watcher: &mut Option<DirWatcher>,
+) -> io::Result<()> {
+    let mut last_click: Option<(Instant, u16, u16)> = None;
     loop {
+        // Apply results from background work before drawing
+        while let Ok(action) = state.action_rx.try_recv() {
+            state.reduce(action);
+        }
+        state.expire_toasts(Instant::now());
+        // Follow the user to whatever directory the last action left them in
+        if let Some(watcher) = watcher.as_mut() {
+            watcher.sync(&state.cwd);
+        }
+
+        if state.should_quit {
+            return Ok(());
+        }
+
+        if let Some(request) = state.run_request.take() {
+            if let Some(overlay) = overlay.as_mut() {
+                overlay.erase(terminal.backend_mut())?;
+            }
+            let result = run_external(terminal, &request)?;
+            state.reduce(Action::ExternalCommandDone {
+                program: request.program,
+                result,
+            });
+            // Show the edits, if the file was being previewed
+            if !request.background && !matches!(state.preview, PreviewState::None) {
+                run_command(state, loader, Command::Preview);
+            }
+            continue;
+        }
+
         terminal.draw(|f| ui(f, state))?;
 
-        if crossterm::event::poll(Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    // Check for Popup State first
-                    match state.popup {
-                        app::PopupState::None => {
-                            match key.code {
-                                KeyCode::Char('q') => return Ok(()),
-
-                                // Focus Switching
-                                KeyCode::Tab | KeyCode::Char('h')
-                                    if key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    state.reduce(Action::SwitchFocus);
-                                }
-
-                                // Navigation / Scrolling (Context Aware)
-                                KeyCode::Char('j') | KeyCode::Down => {
-                                    if state.active_focus == ActiveFocus::Preview {
-                                        state.reduce(Action::ScrollPreviewDown);
-                                    } else {
-                                        state.reduce(Action::CursorMoveDown);
-                                    }
-                                }
-                                KeyCode::Char('k') | KeyCode::Up => {
-                                    if state.active_focus == ActiveFocus::Preview {
-                                        state.reduce(Action::ScrollPreviewUp);
-                                    } else {
-                                        state.reduce(Action::CursorMoveUp);
-                                    }
-                                }
-
-                                // Page Scrolling
-                                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                    state.reduce(Action::ScrollPreviewPageUp);
-                                }
-                                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                    state.reduce(Action::ScrollPreviewPageDown);
-                                }
-                                // Page Up/Down keys
-                                KeyCode::PageUp => state.reduce(Action::ScrollPreviewPageUp),
-                                KeyCode::PageDown => state.reduce(Action::ScrollPreviewPageDown),
-
-                                KeyCode::Char(' ') => state.reduce(Action::ToggleSelect),
-                                KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => {
-                                    if state.active_focus == ActiveFocus::FileList {
-                                        state.reduce(Action::EnterDir);
-                                    }
-                                }
-                                KeyCode::Backspace | KeyCode::Char('h') | KeyCode::Left
-                                    if !key.modifiers.contains(KeyModifiers::CONTROL) =>
-                                {
-                                    if state.active_focus == ActiveFocus::FileList {
-                                        state.reduce(Action::GoBack);
-                                    }
-                                }
-                                KeyCode::Char('y') => state.reduce(Action::Yank),
-                                KeyCode::Char('P') => state.reduce(Action::Paste),
-                                KeyCode::Char('d') => state.reduce(Action::Delete),
-                                KeyCode::Char('x') => state.reduce(Action::Chmod),
-                                KeyCode::Char('o') => state.reduce(Action::Open),
-                                KeyCode::Char('p') => {
-                                    if let Some(entry) = state.entries.get(state.cursor) {
-                                        let path = entry.path.clone();
-                                        state.reduce(Action::RequestPreview(path.clone()));
-
-                                        match loader.load(path.clone()) {
-                                            Ok(content) => {
-                                                state.reduce(Action::PreviewReady(content));
-                                            }
-                                            Err(e) => {
-                                                // Actually logic uses path in PreviewError variant, but we renamed field in enum definition to _path?
-                                                // Wait, I renamed field in `PreviewState::Error { _path, message }`.
-                                                // But `Action::PreviewError` is a separate enum variant!
-                                                // Let's check `Action` definition in `app.rs`.
-                                                state.reduce(Action::PreviewError { path, error: e });
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
+        // Inline images sit outside ratatui's buffer: redo them only when the
+        // image or its area changes, wiping the old one first
+        if let Some(overlay) = overlay.as_mut() {
+            let target = state.image_target();
+            if !overlay.is_current(&target) {
+                if overlay.erase(terminal.backend_mut())? {
+                    terminal.clear()?;
+                    terminal.draw(|f| ui(f, state))?;
+                }
+                if let Some((path, area)) = target {
+                    overlay.show(terminal.backend_mut(), path, area)?;
+                }
+            }
+        }
+
+        let event = if crossterm::event::poll(Duration::from_millis(250))? {
+            event::read()?
+        } else {
+            continue;
+        };
+        if let Event::Mouse(mouse) = event {
+            handle_mouse(state, loader, mouse, &mut last_click);
+        }
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+        {
+            // Filter mode swallows keys until confirmed or cancelled
+            if state.filter_mode {
+                match key.code {
+                    KeyCode::Esc => state.reduce(Action::FilterCancel),
+                    KeyCode::Enter => state.reduce(Action::FilterConfirm),
+                    KeyCode::Backspace => state.reduce(Action::FilterBackspace),
+                    KeyCode::Char(c) => state.reduce(Action::FilterInput(c)),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if state.command_mode {
+                match key.code {
+                    KeyCode::Esc => state.reduce(Action::CommandCancel),
+                    KeyCode::Enter => state.reduce(Action::CommandSubmit),
+                    KeyCode::Backspace => state.reduce(Action::CommandBackspace),
+                    KeyCode::Up => state.reduce(Action::CommandHistoryPrev),
+                    KeyCode::Down => state.reduce(Action::CommandHistoryNext),
+                    KeyCode::Tab => state.reduce(Action::CommandComplete),
+                    KeyCode::Char(c) => state.reduce(Action::CommandInput(c)),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if state.preview_search_mode {
+                match key.code {
+                    KeyCode::Esc => state.reduce(Action::PreviewSearchCancel),
+                    KeyCode::Enter => state.reduce(Action::PreviewSearchConfirm),
+                    KeyCode::Backspace => state.reduce(Action::PreviewSearchBackspace),
+                    KeyCode::Char(c) => state.reduce(Action::PreviewSearchInput(c)),
+                    _ => {}
+                }
+                continue;
+            }
+
+            let command = match state.active_focus {
+                ActiveFocus::FileList => keymap.lookup(&key),
+                ActiveFocus::Preview => keymap.lookup_in_preview(&key),
+            };
+            // Check for Popup State first
+            match &state.popup {
+                app::PopupState::None => match command {
+                    Some(command) => run_command(state, loader, command),
+                    // Tab numbers stay on the digits
+                    None => {
+                        if let KeyCode::Char(c @ '1'..='9') = key.code {
+                            state.reduce(Action::SwitchTab(c as usize - '1' as usize));
                         }
-                        _ => {
-                           // ... popup keys handling (kept same)
-                           // Wait, I need to replicate the popup block or else it's outside this match
-                           // Actually the user loop provided in replacement covers the 'None' arm.
-                           // I should include the `_` arm in this replacement to be safe and clean.
-                           
-                            // Popup is active, handle popup keys
-                            match key.code {
-                                KeyCode::Esc | KeyCode::Char('q') => state.reduce(Action::PopupCancel),
-                                KeyCode::Enter => state.reduce(Action::PopupSubmit),
-                                KeyCode::Up | KeyCode::Char('k') => state.reduce(Action::PopupUp),
-                                KeyCode::Down | KeyCode::Char('j') => state.reduce(Action::PopupDown),
-                                KeyCode::Left | KeyCode::Char('h') => state.reduce(Action::PopupLeft),
-                                KeyCode::Right | KeyCode::Char('l') => state.reduce(Action::PopupRight),
-                                KeyCode::Char(' ') | KeyCode::Char('x') => state.reduce(Action::PopupToggle),
-                                _ => {}
-                            }
+                    }
+                },
+                app::PopupState::FuzzyFind { .. } => match key.code {
+                    KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Up => state.reduce(Action::PopupUp),
+                    KeyCode::Down => state.reduce(Action::PopupDown),
+                    KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                    KeyCode::Char(c) => state.reduce(Action::PopupInput(c)),
+                    _ => {}
+                },
+                app::PopupState::RecentDirs { .. } => match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Up | KeyCode::Char('k') => state.reduce(Action::PopupUp),
+                    KeyCode::Down | KeyCode::Char('j') => state.reduce(Action::PopupDown),
+                    _ => {}
+                },
+                app::PopupState::Bookmarks { .. } => match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Up | KeyCode::Char('k') => state.reduce(Action::PopupUp),
+                    KeyCode::Down | KeyCode::Char('j') => state.reduce(Action::PopupDown),
+                    KeyCode::Char('d') => state.reduce(Action::RemoveBookmark),
+                    _ => {}
+                },
+                app::PopupState::OpenWith { .. } => match key.code {
+                    KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Up => state.reduce(Action::PopupUp),
+                    KeyCode::Down => state.reduce(Action::PopupDown),
+                    KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                    KeyCode::Char(c) => state.reduce(Action::PopupInput(c)),
+                    _ => {}
+                },
+                app::PopupState::Chown { .. } => match key.code {
+                    KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Up | KeyCode::BackTab => state.reduce(Action::PopupUp),
+                    KeyCode::Down => state.reduce(Action::PopupDown),
+                    KeyCode::Tab => {
+                        let on_user =
+                            matches!(state.popup, app::PopupState::Chown { field: 0, .. });
+                        state.reduce(if on_user { Action::PopupDown } else { Action::PopupUp });
+                    }
+                    KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                    KeyCode::Char(c) => state.reduce(Action::PopupInput(c)),
+                    _ => {}
+                },
+                app::PopupState::Grep { .. } => match key.code {
+                    KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    KeyCode::Enter => {
+                        state.reduce(Action::PopupSubmit);
+                        // A picked result: preview it, so it can open at the line
+                        if state.preview_jump.is_some() {
+                            load_preview(state, loader);
                         }
                     }
+                    KeyCode::Up => state.reduce(Action::PopupUp),
+                    KeyCode::Down => state.reduce(Action::PopupDown),
+                    KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        state.reduce(Action::PopupToggle);
+                    }
+                    KeyCode::Char(c) => state.reduce(Action::PopupInput(c)),
+                    _ => {}
+                },
+                app::PopupState::GoToPath { .. }
+                | app::PopupState::MoveTo { .. }
+                | app::PopupState::CreateSymlink { .. }
+                | app::PopupState::BulkRename { .. } => {
+                    match key.code {
+                        KeyCode::Esc => state.reduce(Action::PopupCancel),
+                        KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                        KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                        KeyCode::Char(c) => state.reduce(Action::PopupInput(c)),
+                        _ => {}
+                    }
+                }
+                app::PopupState::Progress { .. } => {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('c')) {
+                        state.reduce(Action::CancelCopy);
+                    }
+                }
+                // Only an explicit `y`: Enter is too easy to hit by accident here
+                app::PopupState::ConfirmDelete { .. } => match key.code {
+                    KeyCode::Char('y') => state.reduce(Action::PopupSubmit),
+                    KeyCode::Char('n') | KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    _ => {}
+                },
+                app::PopupState::Conflict { .. } => {
+                    let resolve = Action::ResolveConflict;
+                    match key.code {
+                        KeyCode::Char('o') => state.reduce(resolve(ConflictChoice::Overwrite)),
+                        KeyCode::Char('s') => state.reduce(resolve(ConflictChoice::Skip)),
+                        KeyCode::Char('r') => state.reduce(resolve(ConflictChoice::Rename)),
+                        KeyCode::Char('a') => state.reduce(resolve(ConflictChoice::OverwriteAll)),
+                        KeyCode::Char('n') => state.reduce(resolve(ConflictChoice::SkipAll)),
+                        KeyCode::Esc => state.reduce(Action::PopupCancel),
+                        _ => {}
+                    }
+                }
+                app::PopupState::ConfirmCopyText { .. } => match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Char('n') | KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    _ => {}
+                },
+                app::PopupState::ConfirmQuit { .. } => match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                    KeyCode::Char('q') => state.reduce(Action::RequestQuit),
+                    KeyCode::Char('n') | KeyCode::Esc => state.reduce(Action::PopupCancel),
+                    _ => {}
+                },
+                app::PopupState::Summary { .. } => {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                        state.reduce(Action::PopupCancel);
+                    }
+                }
+                app::PopupState::Properties { .. } => match key.code {
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('i') => {
+                        state.reduce(Action::PopupCancel);
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => state.reduce(Action::PopupUp),
+                    KeyCode::Down | KeyCode::Char('j') => state.reduce(Action::PopupDown),
+                    _ => {}
+                },
+                app::PopupState::Chmod { .. } => {
+                    // Popup is active, handle popup keys
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Char('q') => state.reduce(Action::PopupCancel),
+                        KeyCode::Enter => state.reduce(Action::PopupSubmit),
+                        KeyCode::Up | KeyCode::Char('k') => state.reduce(Action::PopupUp),
+                        KeyCode::Down | KeyCode::Char('j') => state.reduce(Action::PopupDown),
+                        KeyCode::Left | KeyCode::Char('h') => state.reduce(Action::PopupLeft),
+                        KeyCode::Right | KeyCode::Char('l') => state.reduce(Action::PopupRight),
+                        KeyCode::Char(' ') => state.reduce(Action::PopupToggle),
+                        KeyCode::Backspace => state.reduce(Action::PopupBackspace),
+                        // Characters of an octal or symbolic mode go to the text field
+                        KeyCode::Char(c @ ('0'..='7' | 'u' | 'g' | 'o' | 'a' | '+' | '-' | '='
+                            | 'r' | 'w' | 'x' | 'X' | 's' | 't' | ',')) => {
+                            state.reduce(Action::PopupInput(c));
+                        }
+                        _ => {}
+                    }
                 }
             }
         }
     }
 }
+
+/// Runs `request`. A foreground program gets the real terminal: the TUI is
+/// suspended (raw mode and alternate screen off) until it exits. The outer
+/// error is for failing to restore the terminal; the program's own failure to
+/// start or succeed comes back as the inner one.
+fn run_external<B: ratatui::backend::Backend + io::Write>(
+    terminal: &mut Terminal<B>,
+    request: &ExternalCommand,
+) -> io::Result<Result<(), String>> {
+    let mut command = std::process::Command::new(&request.program);
+    command.args(&request.args);
+    if request.background {
+        let spawned = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        return Ok(spawned.map(drop).map_err(|e| e.to_string()));
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let status = command.status();
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    Ok(match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(match status.code() {
+            Some(code) => format!("exited with code {}", code),
+            None => "killed by a signal".to_string(),
+        }),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+// Two clicks on the same cell within this count as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Clicks pick a row or focus the preview, a double-click opens the row (enter
+/// a directory, preview a file) and the wheel scrolls the pane under it.
+/// Mouse input is ignored while a popup or text prompt is open.
+fn handle_mouse(
+    state: &mut AppState,
+    loader: &impl PreviewLoader,
+    mouse: MouseEvent,
+    last_click: &mut Option<(Instant, u16, u16)>,
+) {
+    let prompt_open = state.filter_mode || state.preview_search_mode || state.command_mode;
+    if prompt_open || !matches!(state.popup, app::PopupState::None) {
+        return;
+    }
+    let (column, row) = (mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            let double = last_click.is_some_and(|(at, c, r)| {
+                at.elapsed() < DOUBLE_CLICK && (c, r) == (column, row)
+            });
+            // A third click starts a new pair
+            *last_click = if double { None } else { Some((Instant::now(), column, row)) };
+
+            state.reduce(Action::MouseClick { column, row });
+            // Only on an entry; not the header, borders or empty rows below
+            if double && state.clicked_entry.is_some() {
+                match state.current_entry() {
+                    Some(entry) if entry.is_dir => state.reduce(Action::EnterDir),
+                    Some(_) => run_command(state, loader, Command::Preview),
+                    None => {}
+                }
+            }
+        }
+        MouseEventKind::ScrollDown => state.reduce(Action::MouseScroll { column, row, down: true }),
+        MouseEventKind::ScrollUp => state.reduce(Action::MouseScroll { column, row, down: false }),
+        _ => {}
+    }
+}
+
+/// Carries out a keymap command in the file list (no popup open). Several
+/// commands depend on which pane has focus.
+fn run_command(state: &mut AppState, loader: &impl PreviewLoader, command: Command) {
+    let preview_focused = state.active_focus == ActiveFocus::Preview;
+    let dual_pane = state.other_pane.is_some();
+    let action = match command {
+        Command::Quit => Action::RequestQuit,
+        Command::Down if preview_focused => Action::ScrollPreviewDown,
+        Command::Down => Action::CursorMoveDown,
+        Command::Up if preview_focused => Action::ScrollPreviewUp,
+        Command::Up => Action::CursorMoveUp,
+        Command::Top => Action::CursorTop,
+        Command::Bottom => Action::CursorBottom,
+        Command::PageUp => Action::ScrollPreviewPageUp,
+        Command::PageDown => Action::ScrollPreviewPageDown,
+        Command::HalfPageUp => Action::ScrollPreviewHalfPageUp,
+        Command::HalfPageDown => Action::ScrollPreviewHalfPageDown,
+        // With the preview focused, sideways keys pan wide tables and long lines
+        Command::Enter if preview_focused => Action::ScrollPreviewRight,
+        Command::Enter => Action::EnterDir,
+        Command::Back if preview_focused => Action::ScrollPreviewLeft,
+        Command::Back => Action::GoBack,
+        Command::HistoryBack => Action::HistoryBack,
+        Command::HistoryForward => Action::HistoryForward,
+        // The second list replaces the preview, so there's no preview to focus
+        Command::SwitchFocus | Command::NextTab if dual_pane => Action::SwitchPane,
+        Command::SwitchFocus => Action::SwitchFocus,
+        Command::NewTab => Action::NewTab,
+        Command::CloseTab => Action::CloseTab,
+        Command::NextTab => Action::NextTab,
+        Command::DualPane => Action::ToggleDualPane,
+        Command::Columns => Action::ToggleMiller,
+        Command::CopyToPane => Action::CopyToPane,
+        Command::MoveToPane => Action::MoveToPane,
+        Command::MoveTo => Action::MoveTo,
+        Command::ToggleSelect => Action::ToggleSelect,
+        Command::SelectAll => Action::SelectAll,
+        Command::InvertSelection => Action::InvertSelection,
+        Command::VisualMode => Action::ToggleVisualMode,
+        Command::Yank => Action::Yank,
+        Command::CopyPath => Action::CopyPath,
+        Command::CopyContents if preview_focused => Action::CopyPreviewText,
+        Command::CopyContents => return,
+        Command::Paste => Action::Paste,
+        Command::PasteInto => Action::PasteInto,
+        Command::Undo => Action::Undo,
+        Command::Delete => Action::Delete,
+        Command::Duplicate => Action::Duplicate,
+        Command::Diff => Action::Diff,
+        Command::BulkRename => Action::BulkRename,
+        Command::Symlink => Action::CreateSymlink,
+        Command::Chmod => Action::Chmod,
+        Command::Chown => Action::Chown,
+        Command::GoToPath => Action::GoToPath,
+        Command::Grep => Action::Grep,
+        Command::Extract => Action::ExtractArchive,
+        Command::Compress => Action::CompressSelection,
+        Command::Open => Action::Open,
+        Command::OpenWith => Action::OpenWith,
+        Command::Edit => Action::EditFile,
+        Command::Properties => Action::Properties,
+        Command::DirSize => Action::ComputeDirSize,
+        Command::SummarizeSelection => Action::SummarizeSelection,
+        Command::Bookmark => Action::Bookmark,
+        Command::TogglePin => Action::TogglePin,
+        Command::OpenBookmarks => Action::OpenBookmarks,
+        Command::RecentDirs => Action::OpenRecentDirs,
+        Command::ToggleHidden => Action::ToggleHidden,
+        Command::ShrinkList => Action::ResizeSplit { grow: false },
+        Command::GrowList => Action::ResizeSplit { grow: true },
+        Command::Flatten => Action::ToggleFlatten,
+        Command::ToggleLineNumbers => Action::ToggleLineNumbers,
+        Command::PreviewWrap => Action::TogglePreviewWrap,
+        Command::CycleTheme => Action::CycleTheme,
+        Command::CycleSort => Action::CycleSort,
+        Command::ReverseSort => Action::ToggleSortReverse,
+        Command::Search if preview_focused => Action::StartPreviewSearch,
+        Command::Search => Action::StartFilter,
+        Command::SearchNext => Action::PreviewSearchNext,
+        Command::SearchPrev => Action::PreviewSearchPrev,
+        // Esc backs out of the innermost mode first
+        Command::Cancel if state.visual_anchor.is_some() => Action::ToggleVisualMode,
+        Command::Cancel if state.preview_search.is_some() => Action::PreviewSearchCancel,
+        Command::Cancel => Action::FilterCancel,
+        Command::FuzzyFind => Action::FuzzyFind,
+        Command::CommandLine => Action::StartCommand,
+        Command::Preview if dual_pane => return,
+        Command::Preview => {
+            load_preview(state, loader);
+            return;
+        }
+    };
+    state.reduce(action);
+}
+
+/// Loads the preview of the entry under the cursor.
+fn load_preview(state: &mut AppState, loader: &impl PreviewLoader) {
+    if let Some(entry) = state.current_entry() {
+        let path = entry.path.clone();
+        state.reduce(Action::RequestPreview(path.clone()));
+        if let Some(content) = state.preview_cache.get(&path) {
+            state.reduce(Action::PreviewReady(content));
+            return;
+        }
+
+        match loader.load(path.clone()) {
+            Ok(content) => {
+                state.preview_cache.insert(&path, content.clone());
+                state.reduce(Action::PreviewReady(content))
+            }
+            Err(e) => state.reduce(Action::PreviewError { path, error: e }),
+        }
+    }
+}
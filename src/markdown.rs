@@ -0,0 +1,180 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Turns a markdown document into styled lines for the preview pane.
+/// Only the common subset is styled; anything else comes through as plain text.
+pub fn render(src: &str) -> Vec<Line<'static>> {
+    let mut r = Renderer::default();
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(src, options) {
+        r.event(event);
+    }
+    r.flush();
+    // Drop the blank line left after the last block
+    while r.lines.last().is_some_and(|l| l.spans.is_empty()) {
+        r.lines.pop();
+    }
+    r.lines
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    styles: Vec<Style>, // inline styles, innermost last
+    lists: Vec<Option<u64>>, // next number for ordered lists
+    quote_depth: usize,
+    in_code_block: bool,
+}
+
+impl Renderer {
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn push_style(&mut self, f: impl FnOnce(Style) -> Style) {
+        let style = f(self.style());
+        self.styles.push(style);
+    }
+
+    fn prefix(&self) -> String {
+        "│ ".repeat(self.quote_depth)
+    }
+
+    fn flush(&mut self) {
+        if !self.spans.is_empty() {
+            self.lines.push(Line::from(std::mem::take(&mut self.spans)));
+        }
+    }
+
+    fn blank(&mut self) {
+        self.flush();
+        if self.lists.is_empty() && self.lines.last().is_some_and(|l| !l.spans.is_empty()) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    fn start_line(&mut self) {
+        if self.spans.is_empty() && self.quote_depth > 0 {
+            let prefix = self.prefix();
+            self.spans.push(Span::styled(prefix, Style::default().fg(Color::DarkGray)));
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_code_block {
+            let style = Style::default().fg(Color::Gray).add_modifier(Modifier::DIM);
+            for line in text.lines() {
+                self.start_line();
+                self.spans.push(Span::styled(format!("    {}", line), style));
+                self.flush();
+            }
+            return;
+        }
+        self.start_line();
+        self.spans.push(Span::styled(text.to_string(), self.style()));
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                self.start_line();
+                self.spans.push(Span::styled(code.to_string(), Style::default().fg(Color::Yellow)));
+            }
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.lines.push(Line::styled("─".repeat(40), Style::default().fg(Color::DarkGray)));
+                self.blank();
+            }
+            Event::TaskListMarker(done) => self.text(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                let style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+                let style = if level == HeadingLevel::H1 {
+                    style.add_modifier(Modifier::UNDERLINED)
+                } else {
+                    style
+                };
+                self.styles.push(style);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.in_code_block = true;
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}{}. ", indent, *n - 1)
+                    }
+                    _ => format!("{}• ", indent),
+                };
+                self.start_line();
+                self.spans.push(Span::raw(bullet));
+            }
+            Tag::Emphasis => self.push_style(|s| s.add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(|s| s.add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.push_style(|s| s.add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { .. } => {
+                self.push_style(|s| s.fg(Color::Blue).add_modifier(Modifier::UNDERLINED))
+            }
+            Tag::TableRow | Tag::TableHead => self.flush(),
+            Tag::TableCell if !self.spans.is_empty() => self.text(" │ "),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.blank();
+            }
+            TagEnd::Paragraph => self.blank(),
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.blank();
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.blank();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                self.blank();
+            }
+            TagEnd::Item | TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Table => self.blank(),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link => {
+                self.styles.pop();
+            }
+            _ => {}
+        }
+    }
+}
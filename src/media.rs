@@ -0,0 +1,110 @@
+use std::{path::Path, process::Command};
+
+use serde_json::Value;
+
+use crate::preview_command;
+
+// Far more JSON than any real file's tags make; past it the parse just fails
+const FFPROBE_MAX_BYTES: u64 = 1024 * 1024;
+
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "mp4", "mkv", "webm", "mov", "avi",
+];
+
+/// Whether the file name looks like audio or video.
+pub fn is_media(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| MEDIA_EXTENSIONS.iter().any(|m| m.eq_ignore_ascii_case(ext)))
+}
+
+/// Duration, bitrate, codecs and tags of a media file, as label/value pairs.
+/// None when `ffprobe` isn't installed, can't make sense of the file, or
+/// takes longer than `preview_command::DEFAULT_TIMEOUT` (say on a slow mount).
+pub fn probe(path: &Path) -> Option<Vec<(String, String)>> {
+    let mut ffprobe = Command::new("ffprobe");
+    ffprobe
+        .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(path);
+    let output =
+        preview_command::output(&mut ffprobe, preview_command::DEFAULT_TIMEOUT, FFPROBE_MAX_BYTES)
+            .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_ffprobe(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Turns `ffprobe -print_format json -show_format -show_streams` output into
+/// label/value pairs. None if it has no format section.
+pub fn parse_ffprobe(json: &str) -> Option<Vec<(String, String)>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let format = value.get("format")?;
+    let text = |v: &Value, key: &str| v.get(key).and_then(Value::as_str).map(str::to_string);
+    let mut fields = Vec::new();
+
+    if let Some(name) = text(format, "format_long_name").or_else(|| text(format, "format_name")) {
+        fields.push(("Container".to_string(), name));
+    }
+    if let Some(secs) = text(format, "duration").and_then(|d| d.parse::<f64>().ok()) {
+        fields.push(("Duration".to_string(), format_duration(secs)));
+    }
+    if let Some(bps) = text(format, "bit_rate").and_then(|b| b.parse::<u64>().ok()) {
+        fields.push(("Bitrate".to_string(), format!("{} kb/s", bps / 1000)));
+    }
+
+    for stream in value.get("streams").and_then(Value::as_array).into_iter().flatten() {
+        let kind = text(stream, "codec_type").unwrap_or_default();
+        let codec = text(stream, "codec_name").unwrap_or_else(|| "unknown".to_string());
+        match kind.as_str() {
+            "video" => {
+                let size = match (stream.get("width"), stream.get("height")) {
+                    (Some(w), Some(h)) => format!(", {}x{}", w, h),
+                    _ => String::new(),
+                };
+                fields.push(("Video".to_string(), format!("{}{}", codec, size)));
+            }
+            "audio" => {
+                let mut desc = codec;
+                if let Some(rate) = text(stream, "sample_rate") {
+                    desc.push_str(&format!(", {} Hz", rate));
+                }
+                if let Some(channels) = stream.get("channels").and_then(Value::as_u64) {
+                    desc.push_str(&format!(", {} ch", channels));
+                }
+                fields.push(("Audio".to_string(), desc));
+            }
+            _ => {}
+        }
+    }
+
+    // Tags are lower case in some containers and upper case in others
+    if let Some(tags) = format.get("tags").and_then(Value::as_object) {
+        for (key, value) in tags {
+            if let Some(value) = value.as_str() {
+                fields.push((capitalize(key), value.to_string()));
+            }
+        }
+    }
+    Some(fields)
+}
+
+// 3725.4 -> "1:02:05"; under an hour, "2:05"
+fn format_duration(secs: f64) -> String {
+    let total = secs.round() as u64;
+    let (h, m, s) = (total / 3600, total / 60 % 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+fn capitalize(key: &str) -> String {
+    let lower = key.to_lowercase().replace('_', " ");
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => lower,
+    }
+}
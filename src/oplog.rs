@@ -0,0 +1,55 @@
+use std::{
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::app::format_timestamp;
+
+/// Appends a line about one file operation to the log at `file`. Does
+/// nothing when there's no log, and drops write errors: the log is a record
+/// of what happened, never a reason for an operation to fail.
+pub fn log_op<T, E: Display>(
+    file: Option<&Path>,
+    action: &str,
+    src: &Path,
+    dst: Option<&Path>,
+    result: &Result<T, E>,
+) {
+    if let Some(file) = file {
+        let _ = append(file, &format_line(SystemTime::now(), action, src, dst, result));
+    }
+}
+
+/// One tab-separated log line: time, action, source, destination (`-` when
+/// there isn't one) and `ok` or the error.
+pub fn format_line<T, E: Display>(
+    time: SystemTime,
+    action: &str,
+    src: &Path,
+    dst: Option<&Path>,
+    result: &Result<T, E>,
+) -> String {
+    let outcome = match result {
+        Ok(_) => "ok".to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        format_timestamp(time),
+        action,
+        src.display(),
+        dst.map_or("-".into(), |d| d.display().to_string()),
+        outcome
+    )
+}
+
+fn append(file: &Path, line: &str) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut log = OpenOptions::new().create(true).append(true).open(file)?;
+    writeln!(log, "{}", line)
+}
@@ -1,13 +1,68 @@
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+#[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use filetime::FileTime;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+
+use crate::archive::ArchiveKind;
 
 pub fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
-    if !src.exists() {
+    copy_with_progress(src, dst, &AtomicBool::new(false), &mut |_| {})
+}
+
+/// A free path next to `path` for a copy of it: `name (copy).ext`, then
+/// `name (copy 2).ext` and so on. Folders keep their whole name.
+pub fn unique_copy_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (stem, ext) = match (path.is_dir(), name.rsplit_once('.')) {
+        // A leading dot (`.bashrc`) starts the name, not an extension
+        (false, Some((stem, ext))) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name.as_ref(), String::new()),
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{} (copy){}", stem, ext)),
+            n => dir.join(format!("{} (copy {}){}", stem, n, ext)),
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .expect("some copy name is free")
+}
+
+/// `copy_recursive` for long copies: `on_file` is called after each file is
+/// copied, and setting `cancel` stops the copy with `ErrorKind::Interrupted`
+/// before the next file. Whatever was copied so far is left in place.
+///
+/// Links are copied as links, like `cp -R`: a link to a folder doesn't pull
+/// in the whole folder, and a dangling one still comes along.
+pub fn copy_with_progress(
+    src: &Path,
+    dst: &Path,
+    cancel: &AtomicBool,
+    on_file: &mut dyn FnMut(&Path),
+) -> std::io::Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "cancelled"));
+    }
+    let Ok(meta) = fs::symlink_metadata(src) else {
         return Ok(());
+    };
+    // `fs::copy` onto its own source truncates it to nothing
+    if same_entry(src, dst) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "source and destination are the same",
+        ));
     }
 
-    if src.is_dir() {
+    if meta.is_dir() {
         if !dst.exists() {
             fs::create_dir_all(dst)?;
         }
@@ -15,30 +70,374 @@ pub fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
             let entry = entry?;
             let entry_path = entry.path();
             let dest_path = dst.join(entry.file_name());
-            copy_recursive(&entry_path, &dest_path)?;
+            copy_with_progress(&entry_path, &dest_path, cancel, on_file)?;
         }
+        // Only now, so a read-only source dir doesn't stop the copy filling it
+        fs::set_permissions(dst, meta.permissions())?;
     } else {
         if let Some(parent) = dst.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::copy(src, dst)?;
+        // Replace a link in the way rather than write through it
+        if fs::symlink_metadata(dst).is_ok_and(|m| m.is_symlink() || meta.is_symlink()) {
+            fs::remove_file(dst)?;
+        }
+        if meta.is_symlink() {
+            copy_link(src, dst)?;
+            on_file(src);
+            let (atime, mtime) = (
+                FileTime::from_last_access_time(&meta),
+                FileTime::from_last_modification_time(&meta),
+            );
+            return filetime::set_symlink_file_times(dst, atime, mtime);
+        }
+        fs::copy(src, dst)?; // also copies the permission bits
+        on_file(src);
     }
+    // Last, since writing into a directory bumps its mtime
+    let mtime = FileTime::from_last_modification_time(&meta);
+    filetime::set_file_times(dst, FileTime::from_last_access_time(&meta), mtime)?;
     Ok(())
 }
 
+// Whether `a` and `b` are one entry, however their folders are spelled.
+// Only the folders are resolved, so a link and what it points to differ.
+fn same_entry(a: &Path, b: &Path) -> bool {
+    let resolve = |path: &Path| {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+        let parent = parent.unwrap_or(Path::new("."));
+        Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+    };
+    fs::symlink_metadata(b).is_ok() && resolve(a).is_some_and(|a| Some(a) == resolve(b))
+}
+
+#[cfg(unix)]
+fn copy_link(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dst)
+}
+
+#[cfg(not(unix))]
+fn copy_link(_src: &Path, _dst: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "copying links needs a Unix system",
+    ))
+}
+
+/// Number of files `copy_recursive` would copy from `path`, for progress
+/// totals. A link counts as one file, as it's copied as one.
+pub fn count_files(path: &Path) -> usize {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_type().is_dir())
+        .count()
+}
+
+/// Counts what's inside `path` (files and folders, at any depth), giving up
+/// once past `cap` so a huge tree costs no more than a small one. Links
+/// aren't followed; a file counts as 0.
+pub fn count_entries_capped(path: &Path, cap: usize) -> usize {
+    WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .take(cap + 1)
+        .count()
+}
+
+pub fn rename(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to)
+}
+
+/// Moves `src` to `dst`, falling back to copy + delete across filesystems.
+pub fn move_path(src: &Path, dst: &Path) -> std::io::Result<()> {
+    match fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_recursive(src, dst)?;
+            delete_path(src)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Entries that could complete `partial`: everything in its directory whose
+/// name starts with its last component, or the whole directory when it ends
+/// in a separator. Dotfiles only show up once the prefix starts with a dot.
+pub fn complete_path(partial: &Path) -> Vec<PathBuf> {
+    // Split the text rather than use `Path::file_name`, which skips a
+    // trailing `.` and would treat `dir/.` as `dir`
+    let text = partial.to_string_lossy();
+    let (dir, prefix) = match text.rfind(std::path::is_separator) {
+        Some(i) => (&text[..=i], &text[i + 1..]),
+        None => ("", &*text),
+    };
+    let dir = Path::new(if dir.is_empty() { "." } else { dir });
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<PathBuf> = read_dir
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    matches.sort();
+    matches
+}
+
 pub fn delete_path(path: &Path) -> std::io::Result<()> {
-    if path.is_dir() {
+    let is_link = fs::symlink_metadata(path)?.file_type().is_symlink();
+    if path.is_dir() && !is_link && fs::read_dir(path)?.next().is_none() {
+        // Empty: remove_dir can't take anything else with it if that changes
+        fs::remove_dir(path)
+    } else if path.is_dir() {
         fs::remove_dir_all(path)
     } else {
         fs::remove_file(path)
     }
 }
 
+/// Opens `path` in the desktop's default application without waiting for it.
+/// Fails if the opener (`open`, `xdg-open`, `start`) can't be started.
+pub fn open_external(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        // The empty argument is the window title `start` would otherwise eat
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let mut child = command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap it in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
 
+/// The Unix mode bits of `meta`. Windows has no such thing, so there it's
+/// made up from the read-only flag: 0o444 or 0o666, plus 0o111 for folders.
+pub fn mode_of(meta: &fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        meta.permissions().mode()
+    }
+    #[cfg(windows)]
+    {
+        let rw = if meta.permissions().readonly() { 0o444 } else { 0o666 };
+        if meta.is_dir() { rw | 0o111 } else { rw }
+    }
+}
+
+/// Sets `path`'s mode. On Windows only the owner's write bit means anything:
+/// clearing it makes the file read-only.
 pub fn set_permissions(path: &Path, mode: u32) -> std::io::Result<()> {
     let metadata = std::fs::metadata(path)?;
     let mut perms = metadata.permissions();
+    #[cfg(unix)]
     perms.set_mode(mode);
+    #[cfg(windows)]
+    perms.set_readonly(mode & 0o200 == 0);
     std::fs::set_permissions(path, perms)?;
     Ok(())
 }
+
+/// Changes `path`'s owner and/or group; None leaves that one as it is.
+/// Symlinks are followed. Usually needs root, which shows up as
+/// `PermissionDenied` (EPERM).
+#[cfg(unix)]
+pub fn set_owner(path: &Path, uid: Option<u32>, gid: Option<u32>) -> std::io::Result<()> {
+    std::os::unix::fs::chown(path, uid, gid)
+}
+
+/// Creates `link` pointing at `target`. A relative `target` is taken from
+/// the link's folder, as the OS reads it.
+#[cfg(unix)]
+pub fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Applies a chmod-style mode expression to `current`.
+///
+/// Accepts octal (`644`, `0755`, `4755`) or comma-separated symbolic clauses
+/// (`u+x`, `go-w`, `a=rX`, `u+s`). Bits outside `0o7777` (the file type) are
+/// left untouched. `X` adds execute only if some execute bit is already set.
+pub fn parse_symbolic(current: u32, expr: &str) -> Result<u32, String> {
+    let expr = expr.trim();
+    if expr.is_empty() {
+        return Err("empty mode".to_string());
+    }
+
+    if expr.chars().all(|c| c.is_ascii_digit()) {
+        if expr.len() > 4 || expr.chars().any(|c| c > '7') {
+            return Err(format!("invalid octal mode '{}'", expr));
+        }
+        let bits = u32::from_str_radix(expr, 8).map_err(|e| e.to_string())?;
+        return Ok((current & !0o7777) | bits);
+    }
+
+    let mut mode = current;
+    for clause in expr.split(',') {
+        let who_end = clause
+            .find(|c| !matches!(c, 'u' | 'g' | 'o' | 'a'))
+            .ok_or_else(|| format!("missing operator in '{}'", clause))?;
+        let (who, rest) = clause.split_at(who_end);
+
+        // An empty who list means everyone, like `a`
+        let (mut rwx_mask, mut special_mask) = if who.is_empty() { (0o777, 0o7000) } else { (0, 0) };
+        for c in who.chars() {
+            let (rwx, special) = match c {
+                'u' => (0o700, 0o4000),
+                'g' => (0o070, 0o2000),
+                'o' => (0o007, 0o1000),
+                _ => (0o777, 0o7000),
+            };
+            rwx_mask |= rwx;
+            special_mask |= special;
+        }
+
+        let mut chars = rest.chars().peekable();
+        while let Some(op) = chars.next() {
+            if !matches!(op, '+' | '-' | '=') {
+                return Err(format!("expected +, - or = in '{}', found '{}'", clause, op));
+            }
+            let mut bits = 0;
+            while let Some(&c) = chars.peek() {
+                if matches!(c, '+' | '-' | '=') {
+                    break;
+                }
+                bits |= match c {
+                    'r' => 0o444 & rwx_mask,
+                    'w' => 0o222 & rwx_mask,
+                    'x' => 0o111 & rwx_mask,
+                    'X' if mode & 0o111 != 0 => 0o111 & rwx_mask,
+                    'X' => 0,
+                    's' => 0o6000 & special_mask,
+                    't' => 0o1000,
+                    _ => return Err(format!("unknown permission '{}' in '{}'", c, clause)),
+                };
+                chars.next();
+            }
+            match op {
+                '+' => mode |= bits,
+                '-' => mode &= !bits,
+                _ => mode = (mode & !(rwx_mask | special_mask)) | bits,
+            }
+        }
+    }
+    Ok(mode)
+}
+
+/// Unpacks the archive at `path` into the directory `dest`. Entries that would
+/// land outside `dest` (absolute paths, `..`) are refused with an error.
+pub fn extract_archive(path: &Path, dest: &Path) -> std::io::Result<()> {
+    let kind = ArchiveKind::from_path(path).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a zip or tar archive")
+    })?;
+    let file = fs::File::open(path)?;
+    fs::create_dir_all(dest)?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(std::io::Error::other)?;
+            // Check every name up front so a bad entry doesn't leave half an extraction
+            for i in 0..zip.len() {
+                let entry = zip.by_index_raw(i).map_err(std::io::Error::other)?;
+                if entry.enclosed_name().is_none() {
+                    return Err(unsafe_entry(entry.name()));
+                }
+            }
+            zip.extract(dest).map_err(std::io::Error::other)
+        }
+        ArchiveKind::Tar => unpack_tar(tar::Archive::new(file), dest),
+        ArchiveKind::TarGz => unpack_tar(tar::Archive::new(GzDecoder::new(file)), dest),
+    }
+}
+
+fn unpack_tar<R: Read>(mut archive: tar::Archive<R>, dest: &Path) -> std::io::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // `unpack_in` skips (returns false) anything that escapes `dest`
+        if !entry.unpack_in(dest)? {
+            return Err(unsafe_entry(&entry.path()?.to_string_lossy()));
+        }
+    }
+    Ok(())
+}
+
+fn unsafe_entry(name: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("refusing entry outside the target folder: {}", name),
+    )
+}
+
+/// Packs `paths` (files or whole directories) into a new archive at `dest`.
+/// Each path is stored under its own file name.
+pub fn create_archive(paths: &[PathBuf], dest: &Path, kind: ArchiveKind) -> std::io::Result<()> {
+    let file = fs::File::options().write(true).create_new(true).open(dest)?;
+    let result = match kind {
+        ArchiveKind::Zip => create_zip(paths, file),
+        ArchiveKind::Tar => create_tar(paths, file).map(drop),
+        ArchiveKind::TarGz => {
+            create_tar(paths, GzEncoder::new(file, Compression::default()))?.finish().map(drop)
+        }
+    };
+    // Don't leave a truncated archive behind
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result
+}
+
+fn create_zip(paths: &[PathBuf], file: fs::File) -> std::io::Result<()> {
+    let mut zip = zip::ZipWriter::new(file);
+    for path in paths {
+        let base = path.parent().unwrap_or(Path::new(""));
+        for entry in WalkDir::new(path) {
+            let entry = entry.map_err(std::io::Error::other)?;
+            let name = entry
+                .path()
+                .strip_prefix(base)
+                .map_err(std::io::Error::other)?
+                .to_string_lossy()
+                .into_owned();
+            let mode = mode_of(&entry.metadata().map_err(std::io::Error::other)?);
+            let options = SimpleFileOptions::default().unix_permissions(mode & 0o7777);
+            if entry.file_type().is_dir() {
+                zip.add_directory(name, options).map_err(std::io::Error::other)?;
+            } else {
+                zip.start_file(name, options).map_err(std::io::Error::other)?;
+                std::io::copy(&mut fs::File::open(entry.path())?, &mut zip)?;
+            }
+        }
+    }
+    zip.finish().map_err(std::io::Error::other)?;
+    Ok(())
+}
+
+fn create_tar<W: Write>(paths: &[PathBuf], out: W) -> std::io::Result<W> {
+    let mut tar = tar::Builder::new(out);
+    tar.follow_symlinks(false);
+    for path in paths {
+        let name = path.file_name().unwrap_or_default();
+        if path.is_dir() {
+            tar.append_dir_all(name, path)?;
+        } else {
+            tar.append_path_with_name(path, name)?;
+        }
+    }
+    tar.into_inner()
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::app::PreviewContent;
+
+// Bounds on the cache; the oldest previews go first
+pub const MAX_ENTRIES: usize = 32;
+pub const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+/// Recently loaded previews, so flicking back to a file doesn't read and
+/// parse it again. Entries are checked against the file's mtime and size on
+/// the way out, so an edited file is loaded afresh.
+#[derive(Default)]
+pub struct PreviewCache {
+    entries: VecDeque<Cached>, // least recently used first
+    bytes: usize,
+}
+
+struct Cached {
+    path: PathBuf,
+    stamp: (SystemTime, u64),
+    content: PreviewContent,
+    bytes: usize,
+}
+
+impl PreviewCache {
+    /// The cached preview of `path`, if the file hasn't changed since.
+    pub fn get(&mut self, path: &Path) -> Option<PreviewContent> {
+        let idx = self.entries.iter().position(|e| e.path == path)?;
+        let cached = self.entries.remove(idx)?;
+        if stamp(path) != Some(cached.stamp) {
+            self.bytes -= cached.bytes;
+            return None;
+        }
+        let content = cached.content.clone();
+        self.entries.push_back(cached);
+        Some(content)
+    }
+
+    /// Remembers `content` as the preview of `path`. Only regular files are
+    /// kept; a folder's mtime doesn't tell when something deeper changed.
+    pub fn insert(&mut self, path: &Path, content: PreviewContent) {
+        let Some(stamp) = stamp(path) else { return };
+        if let Some(idx) = self.entries.iter().position(|e| e.path == path)
+            && let Some(old) = self.entries.remove(idx)
+        {
+            self.bytes -= old.bytes;
+        }
+        let bytes = content_bytes(&content);
+        if bytes > MAX_BYTES {
+            return;
+        }
+        while self.entries.len() >= MAX_ENTRIES || self.bytes + bytes > MAX_BYTES {
+            let Some(oldest) = self.entries.pop_front() else { break };
+            self.bytes -= oldest.bytes;
+        }
+        self.bytes += bytes;
+        self.entries.push_back(Cached {
+            path: path.to_path_buf(),
+            stamp,
+            content,
+            bytes,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// mtime alone can miss a quick rewrite on coarse-grained filesystems
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    if !meta.is_file() {
+        return None;
+    }
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+// The text (or thumbnail) a preview holds, which is most of its size
+fn content_bytes(content: &PreviewContent) -> usize {
+    match content {
+        PreviewContent::Text { content, .. } => content.len(),
+        PreviewContent::Markdown { lines, .. } | PreviewContent::Diff { lines, .. } => lines
+            .iter()
+            .flat_map(|l| &l.spans)
+            .map(|s| s.content.len())
+            .sum(),
+        PreviewContent::Table { headers, rows, .. } => {
+            headers.iter().chain(rows.iter().flatten()).map(String::len).sum()
+        }
+        PreviewContent::Archive { entries, .. } => entries.iter().map(|e| e.path.len()).sum(),
+        PreviewContent::Media { fields, .. } => fields.iter().map(|(k, v)| k.len() + v.len()).sum(),
+        PreviewContent::Binary { .. } => 0,
+        PreviewContent::Image { thumbnail, .. } => thumbnail.as_ref().map_or(0, |t| t.bytes()),
+    }
+}
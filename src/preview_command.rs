@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+
+use crate::app::{DefaultPreviewLoader, PreviewContent, PreviewLoader};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Most of an error that fits in the preview pane
+const STDERR_MAX_BYTES: u64 = 4096;
+
+/// Previews files with the shell commands in `config.toml`'s `[previewers]`,
+/// keyed by extension (`pdf`), MIME type (`application/pdf`) or MIME family
+/// (`image/*`). The command's output becomes a text preview. `$1` is the
+/// file; a command that doesn't mention it gets the file as its last
+/// argument. Files without a command, and commands that fail or run past
+/// `timeout`, get the built-in preview instead.
+pub struct CommandPreviewLoader {
+    pub commands: HashMap<String, String>,
+    pub timeout: Duration,
+    pub fallback: DefaultPreviewLoader, // its `max_bytes` caps the output too
+}
+
+impl CommandPreviewLoader {
+    /// The command for `path`, if any. An extension beats a MIME type, and
+    /// an exact type beats a family.
+    pub fn command_for(&self, path: &Path) -> Option<&str> {
+        if path.is_dir() {
+            return None;
+        }
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        let mime = mime_guess::from_path(path).first();
+        let keys = [
+            ext,
+            mime.as_ref().map(|m| m.essence_str().to_string()),
+            mime.as_ref().map(|m| format!("{}/*", m.type_())),
+        ];
+        keys.into_iter()
+            .flatten()
+            .find_map(|key| self.commands.get(&key))
+            .map(String::as_str)
+    }
+}
+
+impl PreviewLoader for CommandPreviewLoader {
+    fn load(&self, path: PathBuf) -> Result<PreviewContent, String> {
+        let Some(command) = self.command_for(&path) else {
+            return self.fallback.load(path);
+        };
+        match run(command, &path, self.timeout, self.fallback.max_bytes) {
+            Ok(content) => Ok(PreviewContent::Text {
+                title: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                content,
+                encoding: None,
+            }),
+            // The command's complaint is only shown if there's nothing better
+            Err(error) => self.fallback.load(path).map_err(|_| error),
+        }
+    }
+}
+
+/// Runs `command` on `path` through `sh`, returning its stdout (cut off after
+/// `max_bytes`, with color codes removed) or its stderr if it failed.
+pub fn run(
+    command: &str,
+    path: &Path,
+    timeout: Duration,
+    max_bytes: u64,
+) -> Result<String, String> {
+    let script = if command.contains("$1") {
+        command.to_string()
+    } else {
+        format!("{} \"$1\"", command)
+    };
+    let mut sh = Command::new("sh");
+    sh.args(["-c", &script, "sh"]).arg(path);
+    let Output {
+        status,
+        mut stdout,
+        stderr,
+    } = output(&mut sh, timeout, max_bytes).map_err(|e| match e.kind() {
+        io::ErrorKind::TimedOut => format!("{} timed out after {}s", command, timeout.as_secs()),
+        _ => format!("sh: {}", e),
+    })?;
+    // Cut off commands usually die of the closed pipe; that's not a failure
+    let truncated = stdout.len() as u64 > max_bytes;
+    stdout.truncate(max_bytes as usize);
+    if !status.success() && !truncated {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let stderr = stderr.trim();
+        return Err(if stderr.is_empty() {
+            format!("{} exited with {}", command, status)
+        } else {
+            stderr.to_string()
+        });
+    }
+    Ok(strip_ansi(&String::from_utf8_lossy(&stdout)))
+}
+
+/// What `output` collected from a command that finished in time.
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>, // up to one byte past `max_bytes`
+    pub stderr: Vec<u8>,
+}
+
+/// Like `Command::output`, but bounded: a command still running after
+/// `timeout` is killed (`ErrorKind::TimedOut`), and so are reads from a pipe
+/// it left open, e.g. to a background process; what arrived by then is kept.
+pub fn output(command: &mut Command, timeout: Duration, max_bytes: u64) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read both pipes as it runs, so a chatty command can't fill one and stall
+    let stdout = child.stdout.take().map(|out| read_capped(out, max_bytes));
+    let stderr = child.stderr.take().map(|err| read_capped(err, STDERR_MAX_BYTES));
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait()? {
+            Some(status) => break status,
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+            }
+        }
+    };
+    // The reader threads are left behind if they're still blocked by then
+    let collect = |reader: Option<PipeReader>| {
+        let Some((bytes, handle)) = reader else {
+            return Vec::new();
+        };
+        while !handle.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        std::mem::take(&mut *bytes.lock().unwrap_or_else(PoisonError::into_inner))
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+// A pipe's bytes so far, and the thread still adding to them
+type PipeReader = (Arc<Mutex<Vec<u8>>>, thread::JoinHandle<()>);
+
+// Reads up to one byte past `limit`, then hangs up, so a command with
+// endless output stops on SIGPIPE like it would under `head`. The bytes are
+// shared as they arrive, for a caller that stops waiting to take them.
+fn read_capped(
+    reader: impl Read + Send + 'static,
+    limit: u64,
+) -> PipeReader {
+    let bytes = Arc::new(Mutex::new(Vec::new()));
+    let shared = bytes.clone();
+    let handle = thread::spawn(move || {
+        let mut reader = reader.take(limit + 1);
+        let mut buf = [0; 8192];
+        while let Ok(n @ 1..) = reader.read(&mut buf) {
+            shared.lock().unwrap_or_else(PoisonError::into_inner).extend_from_slice(&buf[..n]);
+        }
+    });
+    (bytes, handle)
+}
+
+/// `text` without terminal escape sequences, for tools that color their
+/// output even into a pipe.
+pub fn strip_ansi(text: &str) -> String {
+    let escapes = Regex::new(r"\x1b(\[[0-9;?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\)|[@-_])")
+        .expect("valid regex");
+    escapes.replace_all(text, "").into_owned()
+}
@@ -0,0 +1,189 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use regex::Regex;
+
+/// How bulk rename turns each old name into a new one.
+///
+/// `s/regex/replacement/` (with an optional trailing `g` for every match)
+/// rewrites the old name; `$1` and friends refer to capture groups. Anything
+/// else is a template for the whole name, where `{n}` is a counter from 1
+/// (`{n:03}` pads it to 3 digits), `{name}` the old name without its
+/// extension and `{ext}` the extension. `{{` and `}}` are literal braces.
+#[derive(Clone, Debug)]
+pub enum RenamePattern {
+    Template(Vec<Piece>),
+    Replace {
+        regex: Regex,
+        replacement: String,
+        all: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Counter { width: usize },
+    Stem,
+    Ext,
+}
+
+impl RenamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        if let Some(rest) = pattern.strip_prefix("s/") {
+            return parse_replace(rest);
+        }
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("unmatched '}' (write '}}' for a brace)".to_string()),
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err("unclosed '{'".to_string());
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(parse_placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(RenamePattern::Template(pieces))
+    }
+
+    /// The new name for `name`, the `n`th (from 1) file being renamed.
+    pub fn apply(&self, name: &str, n: usize) -> String {
+        match self {
+            RenamePattern::Template(pieces) => {
+                let (stem, ext) = split_ext(name);
+                pieces
+                    .iter()
+                    .map(|piece| match piece {
+                        Piece::Text(text) => text.clone(),
+                        Piece::Counter { width } => format!("{:0width$}", n, width = width),
+                        Piece::Stem => stem.to_string(),
+                        Piece::Ext => ext.to_string(),
+                    })
+                    .collect()
+            }
+            RenamePattern::Replace {
+                regex,
+                replacement,
+                all,
+            } => {
+                let limit = if *all { 0 } else { 1 };
+                regex.replacen(name, limit, replacement.as_str()).into_owned()
+            }
+        }
+    }
+}
+
+// `find/replacement/` or `find/replacement/g`; `\/` is a literal slash
+fn parse_replace(rest: &str) -> Result<RenamePattern, String> {
+    let mut parts = vec![String::new()];
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.as_str().starts_with('/') => {
+                chars.next();
+                parts.last_mut().unwrap().push('/');
+            }
+            '/' => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+    let (find, replacement, flags) = match parts.as_slice() {
+        [find, replacement] => (find, replacement, ""),
+        [find, replacement, flags] => (find, replacement, flags.as_str()),
+        _ => return Err("expected s/find/replace/".to_string()),
+    };
+    if find.is_empty() {
+        return Err("nothing to find".to_string());
+    }
+    let all = match flags {
+        "" => false,
+        "g" => true,
+        _ => return Err(format!("unknown flag '{}' (only g)", flags)),
+    };
+    Ok(RenamePattern::Replace {
+        regex: Regex::new(find).map_err(|e| e.to_string())?,
+        replacement: replacement.clone(),
+        all,
+    })
+}
+
+fn parse_placeholder(inner: &str) -> Result<Piece, String> {
+    match inner.split_once(':') {
+        None if inner == "n" => Ok(Piece::Counter { width: 0 }),
+        None if inner == "name" => Ok(Piece::Stem),
+        None if inner == "ext" => Ok(Piece::Ext),
+        Some(("n", width)) => width
+            .parse()
+            .map(|width| Piece::Counter { width })
+            .map_err(|_| format!("bad counter width '{}'", width)),
+        _ => Err(format!("unknown placeholder {{{}}}", inner)),
+    }
+}
+
+// "photo.tar.gz" -> ("photo.tar", "gz"); dotfiles like ".env" have no extension
+fn split_ext(name: &str) -> (&str, &str) {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+        _ => (name, ""),
+    }
+}
+
+/// Each of `paths` with its new name. Unchanged names are left out.
+pub fn plan(paths: &[PathBuf], pattern: &RenamePattern) -> Vec<(PathBuf, String)> {
+    paths
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, path)| {
+            let name = path.file_name()?.to_string_lossy();
+            let new_name = pattern.apply(&name, idx + 1);
+            (new_name != name).then_some((path.clone(), new_name))
+        })
+        .collect()
+}
+
+/// The (from, to) moves for `renames`, or why they can't all be done: a bad
+/// new name, two files ending up with the same one, or a name that's already
+/// taken. Checked up front, so a bulk rename happens entirely or not at all.
+pub fn check(renames: &[(PathBuf, String)]) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut taken = HashSet::new();
+    let mut moves = Vec::new();
+    for (from, name) in renames {
+        let old = from.file_name().unwrap_or_default().to_string_lossy();
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(format!("{} would get an empty name", old));
+        }
+        if name.contains(std::path::is_separator) {
+            return Err(format!("{} -> {}: names can't contain '/'", old, name));
+        }
+        let to = from.with_file_name(name);
+        if !taken.insert(to.clone()) {
+            return Err(format!("more than one file would be named {}", name));
+        }
+        // Dangling links count as taken too
+        if std::fs::symlink_metadata(&to).is_ok() {
+            return Err(format!("{} already exists", name));
+        }
+        moves.push((from.clone(), to));
+    }
+    Ok(moves)
+}
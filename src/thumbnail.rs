@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use image::{RgbImage, imageops::FilterType};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use serde::Deserialize;
+
+// Longest side of the decoded copy; panes are rarely wider than this
+pub const MAX_SIDE: u32 = 256;
+
+// Dark to light, for monochrome art
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+/// How images are drawn when the terminal has no graphics protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageArt {
+    Off,
+    Mono,  // luminance as ASCII
+    Color, // two colored pixels per cell, with '▀'
+}
+
+/// A small copy of an image, decoded once when the preview loads and scaled
+/// to the pane each time it's drawn.
+#[derive(Clone, Debug)]
+pub struct Thumbnail {
+    image: RgbImage,
+}
+
+impl Thumbnail {
+    /// Decodes `path` (the first frame, for animations) and shrinks it.
+    /// Transparent parts come out black.
+    pub fn load(path: &Path) -> Option<Self> {
+        let rgba = image::open(path).ok()?.thumbnail(MAX_SIDE, MAX_SIDE).to_rgba8();
+        let image = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            let over_black = |c: u8| (c as u16 * a as u16 / 255) as u8;
+            image::Rgb([over_black(r), over_black(g), over_black(b)])
+        });
+        Some(Thumbnail { image })
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.image.as_raw().len()
+    }
+
+    /// The image as `height` lines of `width` cells, letterboxed to keep its
+    /// shape. A cell is taken to be twice as tall as it is wide.
+    pub fn render(&self, width: u16, height: u16, art: ImageArt) -> Vec<Line<'static>> {
+        let (w, h) = (self.image.width().max(1), self.image.height().max(1));
+        if width == 0 || height == 0 || art == ImageArt::Off {
+            return Vec::new();
+        }
+        // Fit in width x (2 * height) square pixels
+        let scale = f64::min(width as f64 / w as f64, 2.0 * height as f64 / h as f64);
+        let cols = ((w as f64 * scale).round() as u32).clamp(1, width as u32);
+        let px_rows = ((h as f64 * scale).round() as u32).clamp(1, 2 * height as u32);
+        let rows = px_rows.div_ceil(2);
+        let pad_left = " ".repeat((width as u32 - cols) as usize / 2);
+        let pad_top = (height as u32 - rows) / 2;
+
+        let resize = |w, h| image::imageops::resize(&self.image, w, h, FilterType::Triangle);
+
+        let mut lines = vec![Line::default(); pad_top as usize];
+        match art {
+            ImageArt::Color => {
+                let small = resize(cols, px_rows);
+                let rgb = |x, y| {
+                    let [r, g, b] = small.get_pixel(x, y).0;
+                    Color::Rgb(r, g, b)
+                };
+                for row in 0..rows {
+                    let mut spans = vec![Span::raw(pad_left.clone())];
+                    spans.extend((0..cols).map(|x| {
+                        let mut style = Style::default().fg(rgb(x, 2 * row));
+                        if 2 * row + 1 < px_rows {
+                            style = style.bg(rgb(x, 2 * row + 1));
+                        }
+                        Span::styled("▀", style)
+                    }));
+                    lines.push(Line::from(spans));
+                }
+            }
+            _ => {
+                let small = resize(cols, rows);
+                for row in 0..rows {
+                    let text: String = (0..cols)
+                        .map(|x| {
+                            let [r, g, b] = small.get_pixel(x, row).0;
+                            let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+                            RAMP[luma as usize * (RAMP.len() - 1) / 255] as char
+                        })
+                        .collect();
+                    lines.push(Line::from(format!("{}{}", pad_left, text)));
+                }
+            }
+        }
+        lines
+    }
+}
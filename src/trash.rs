@@ -0,0 +1,122 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::ops;
+
+/// The desktop trash, `$XDG_DATA_HOME/Trash` (`~/.local/share/Trash`), laid
+/// out the freedesktop way so file managers can restore from it too.
+pub fn trash_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .map(|dir| dir.join("Trash"))
+}
+
+/// Moves `path` into `trash`'s `files/`, with a `.trashinfo` saying where it
+/// came from. Returns where it ended up, for `restore`.
+///
+/// Items on another filesystem go to that filesystem's own trash,
+/// `$topdir/.Trash-$uid`, as nothing is ever copied into a trash. Where that
+/// can't be had the error is `ErrorKind::CrossesDevices`.
+pub fn trash(trash: &Path, path: &Path) -> io::Result<PathBuf> {
+    match trash_into(trash, path, None) {
+        #[cfg(unix)]
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let (topdir, volume_trash) = volume_trash(trash, path).ok_or(e)?;
+            trash_into(&volume_trash, path, Some(&topdir))
+        }
+        result => result,
+    }
+}
+
+// `trash` for one trash directory. In a volume's trash `Path=` is relative
+// to `topdir`, so the drive can be mounted somewhere else later.
+fn trash_into(trash: &Path, path: &Path, topdir: Option<&Path>) -> io::Result<PathBuf> {
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+    let original = std::path::absolute(path)?;
+    let recorded = match topdir {
+        Some(topdir) => original.strip_prefix(topdir).unwrap_or(&original),
+        None => &original,
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    // `name`, `name.2`, ... whichever has neither a file nor an info yet
+    let (trashed, info_file) = (1..)
+        .map(|n| match n {
+            1 => name.clone(),
+            n => format!("{}.{}", name, n),
+        })
+        .map(|name| (files.join(&name), info.join(format!("{}.trashinfo", name))))
+        .find(|(file, info)| fs::symlink_metadata(file).is_err() && !info.exists())
+        .expect("some trash name is free");
+    let date = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S");
+    fs::write(
+        &info_file,
+        format!("[Trash Info]\nPath={}\nDeletionDate={}\n", escape(recorded), date),
+    )?;
+    if let Err(e) = fs::rename(path, &trashed) {
+        let _ = fs::remove_file(&info_file);
+        return Err(e);
+    }
+    Ok(trashed)
+}
+
+// The top of the filesystem `path` is on and the trash there, made if need
+// be. The home trash is ours, so its owner is the uid to name it by.
+#[cfg(unix)]
+fn volume_trash(home_trash: &Path, path: &Path) -> Option<(PathBuf, PathBuf)> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    let uid = fs::metadata(home_trash).ok()?.uid();
+    let mut topdir = std::path::absolute(path).ok()?.parent()?.to_path_buf();
+    let dev = fs::metadata(&topdir).ok()?.dev();
+    while let Some(up) = topdir.parent()
+        && fs::metadata(up).is_ok_and(|m| m.dev() == dev)
+    {
+        topdir = up.to_path_buf();
+    }
+    let volume_trash = topdir.join(format!(".Trash-{}", uid));
+    // Private, as the spec asks; someone else's link in its place won't do
+    let made = fs::DirBuilder::new().mode(0o700).create(&volume_trash);
+    let meta = fs::symlink_metadata(&volume_trash).ok()?;
+    if made.is_err() && !(meta.is_dir() && meta.uid() == uid) {
+        return None;
+    }
+    Some((topdir, volume_trash))
+}
+
+/// Puts a `trash`ed item back at `original`, unless something has taken
+/// its place since.
+pub fn restore(trashed: &Path, original: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(original).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", original.display()),
+        ));
+    }
+    ops::move_path(trashed, original)?;
+    if let (Some(trash), Some(name)) = (trashed.parent().and_then(Path::parent), trashed.file_name())
+    {
+        let info = trash.join("info").join(format!("{}.trashinfo", name.to_string_lossy()));
+        let _ = fs::remove_file(info);
+    }
+    Ok(())
+}
+
+// `Path=` is URL-escaped, slashes aside
+fn escape(path: &Path) -> String {
+    let mut escaped = String::new();
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    escaped
+}
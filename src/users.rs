@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+/// User and group names by id, read from `/etc/passwd` and `/etc/group`.
+/// Accounts only known to NSS (LDAP and the like) aren't in there and show
+/// up as plain numbers.
+#[derive(Clone, Debug, Default)]
+pub struct Accounts {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Accounts {
+    /// Reads the local account files; a missing one just means no names.
+    pub fn load() -> Self {
+        let read = |path| std::fs::read_to_string(path).unwrap_or_default();
+        Accounts::parse(&read("/etc/passwd"), &read("/etc/group"))
+    }
+
+    /// Both files are `name:password:id:...` lines.
+    pub fn parse(passwd: &str, group: &str) -> Self {
+        fn ids(text: &str) -> HashMap<u32, String> {
+            let mut ids = HashMap::new();
+            for line in text.lines().filter(|l| !l.starts_with('#')) {
+                let mut fields = line.split(':');
+                let (Some(name), Some(id)) = (fields.next(), fields.nth(1)) else {
+                    continue;
+                };
+                if let Ok(id) = id.parse() {
+                    // The first line for an id wins, as with getpwuid
+                    ids.entry(id).or_insert_with(|| name.to_string());
+                }
+            }
+            ids
+        }
+        Accounts {
+            users: ids(passwd),
+            groups: ids(group),
+        }
+    }
+
+    pub fn user_name(&self, uid: u32) -> Option<&str> {
+        self.users.get(&uid).map(String::as_str)
+    }
+
+    pub fn group_name(&self, gid: u32) -> Option<&str> {
+        self.groups.get(&gid).map(String::as_str)
+    }
+
+    /// The user's name, or the bare number for an unknown uid.
+    pub fn user_label(&self, uid: u32) -> String {
+        self.user_name(uid).map_or_else(|| uid.to_string(), str::to_string)
+    }
+
+    /// The group's name, or the bare number for an unknown gid.
+    pub fn group_label(&self, gid: u32) -> String {
+        self.group_name(gid).map_or_else(|| gid.to_string(), str::to_string)
+    }
+
+    /// The uid for a user name or a number. Blank means "leave it alone".
+    pub fn uid(&self, name: &str) -> Result<Option<u32>, String> {
+        resolve(&self.users, name).map_err(|_| format!("no such user '{}'", name.trim()))
+    }
+
+    /// The gid for a group name or a number. Blank means "leave it alone".
+    pub fn gid(&self, name: &str) -> Result<Option<u32>, String> {
+        resolve(&self.groups, name).map_err(|_| format!("no such group '{}'", name.trim()))
+    }
+}
+
+fn resolve(ids: &HashMap<u32, String>, name: &str) -> Result<Option<u32>, ()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(id) = name.parse() {
+        return Ok(Some(id));
+    }
+    ids.iter().find(|(_, n)| *n == name).map(|(&id, _)| Some(id)).ok_or(())
+}
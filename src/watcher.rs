@@ -0,0 +1,69 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    time::{Duration, Instant},
+};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::app::Action;
+
+// Quiet time after the last change before the listing is reloaded
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// Longest a burst can hold a reload back, so a download still shows up
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+/// Watches one directory (non-recursively) and sends `Action::DirChanged` once
+/// a burst of changes in it settles down, or at least every `MAX_WAIT`
+/// while one goes on.
+pub struct DirWatcher {
+    watcher: RecommendedWatcher,
+    watched: Option<PathBuf>,
+}
+
+impl DirWatcher {
+    pub fn new(tx: Sender<Action>) -> notify::Result<Self> {
+        let (raw_tx, raw_rx) = mpsc::channel::<PathBuf>();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            // Reads don't change the listing, and our own previews cause plenty
+            if let Ok(event) = res
+                && !matches!(event.kind, EventKind::Access(_))
+                && let Some(dir) = event.paths.first().and_then(|p| p.parent())
+            {
+                let _ = raw_tx.send(dir.to_path_buf());
+            }
+        })?;
+
+        std::thread::spawn(move || {
+            while let Ok(mut dir) = raw_rx.recv() {
+                // Swallow the rest of the burst, or as much as `MAX_WAIT` allows
+                let deadline = Instant::now() + MAX_WAIT;
+                while let Some(quiet) = deadline.checked_duration_since(Instant::now())
+                    && let Ok(next) = raw_rx.recv_timeout(quiet.min(DEBOUNCE))
+                {
+                    dir = next;
+                }
+                if tx.send(Action::DirChanged(dir)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(DirWatcher {
+            watcher,
+            watched: None,
+        })
+    }
+
+    /// Moves the watch to `dir` if it isn't there already. A directory that
+    /// can't be watched just doesn't auto-refresh.
+    pub fn sync(&mut self, dir: &Path) {
+        if self.watched.as_deref() == Some(dir) {
+            return;
+        }
+        if let Some(old) = self.watched.replace(dir.to_path_buf()) {
+            let _ = self.watcher.unwatch(&old);
+        }
+        let _ = self.watcher.watch(dir, RecursiveMode::NonRecursive);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use std::{fs, io::Write, path::Path};
+
+use common::TempDir;
+use file_management::{
+    archive::{self, ArchiveKind},
+    ops,
+};
+
+#[test]
+fn stem_strips_archive_extensions() {
+    assert_eq!(archive::stem(Path::new("a/photos.tar.gz")).as_deref(), Some("photos"));
+    assert_eq!(archive::stem(Path::new("Backup.ZIP")).as_deref(), Some("Backup"));
+    assert_eq!(archive::stem(Path::new("x.tgz")).as_deref(), Some("x"));
+    assert_eq!(archive::stem(Path::new(".zip")), None);
+    assert_eq!(archive::stem(Path::new("notes.txt")), None);
+}
+
+#[test]
+fn created_archives_extract_to_the_same_tree() {
+    let dir = TempDir::new("archive-roundtrip");
+    let src = dir.path().join("src");
+    fs::create_dir_all(src.join("sub")).unwrap();
+    fs::write(src.join("sub/a.txt"), "alpha").unwrap();
+    fs::write(dir.path().join("b.txt"), "beta").unwrap();
+    let paths = [src, dir.path().join("b.txt")];
+
+    for (kind, name) in [(ArchiveKind::Zip, "out.zip"), (ArchiveKind::TarGz, "out.tar.gz")] {
+        let packed = dir.path().join(name);
+        ops::create_archive(&paths, &packed, kind).unwrap();
+
+        let dest = dir.path().join(format!("unpacked-{}", name));
+        ops::extract_archive(&packed, &dest).unwrap();
+        assert_eq!(fs::read_to_string(dest.join("src/sub/a.txt")).unwrap(), "alpha");
+        assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "beta");
+    }
+}
+
+#[test]
+fn create_refuses_to_overwrite() {
+    let dir = TempDir::new("archive-exists");
+    dir.touch(&["a.txt", "archive.zip"]);
+    let dest = dir.path().join("archive.zip");
+
+    assert!(ops::create_archive(&[dir.path().join("a.txt")], &dest, ArchiveKind::Zip).is_err());
+    assert_eq!(fs::metadata(&dest).unwrap().len(), 0);
+}
+
+#[test]
+fn zip_entries_escaping_the_target_are_refused() {
+    let dir = TempDir::new("archive-traversal");
+    let path = dir.path().join("evil.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("ok.txt", options).unwrap();
+    zip.write_all(b"fine").unwrap();
+    zip.start_file("../escaped.txt", options).unwrap();
+    zip.write_all(b"gotcha").unwrap();
+    zip.finish().unwrap();
+
+    let dest = dir.path().join("out");
+    assert!(ops::extract_archive(&path, &dest).is_err());
+    assert!(!dir.path().join("escaped.txt").exists());
+    // Nothing at all is written when a bad entry is found up front
+    assert!(!dest.join("ok.txt").exists());
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, PopupState, Reducer},
+    ops::parse_symbolic,
+};
+
+#[test]
+fn octal_modes_replace_permission_bits() {
+    assert_eq!(parse_symbolic(0o100644, "755"), Ok(0o100755));
+    assert_eq!(parse_symbolic(0o100777, "0644"), Ok(0o100644));
+    assert_eq!(parse_symbolic(0o100644, "4755"), Ok(0o104755));
+    assert_eq!(parse_symbolic(0o644, "0"), Ok(0));
+}
+
+#[test]
+fn invalid_octal_modes_are_rejected() {
+    assert!(parse_symbolic(0o644, "8").is_err());
+    assert!(parse_symbolic(0o644, "77777").is_err());
+    assert!(parse_symbolic(0o644, "").is_err());
+}
+
+#[test]
+fn symbolic_add_and_remove() {
+    assert_eq!(parse_symbolic(0o644, "u+x"), Ok(0o744));
+    assert_eq!(parse_symbolic(0o666, "go-w"), Ok(0o644));
+    assert_eq!(parse_symbolic(0o644, "+x"), Ok(0o755));
+    assert_eq!(parse_symbolic(0o644, "a-r"), Ok(0o200));
+}
+
+#[test]
+fn symbolic_assign_and_multiple_clauses() {
+    assert_eq!(parse_symbolic(0o777, "go=r"), Ok(0o744));
+    assert_eq!(parse_symbolic(0o600, "u=rwx,g=rx,o="), Ok(0o750));
+    assert_eq!(parse_symbolic(0o644, "u+x-w"), Ok(0o544));
+}
+
+#[test]
+fn symbolic_special_bits() {
+    assert_eq!(parse_symbolic(0o755, "u+s"), Ok(0o4755));
+    assert_eq!(parse_symbolic(0o755, "g+s"), Ok(0o2755));
+    assert_eq!(parse_symbolic(0o777, "+t"), Ok(0o1777));
+    assert_eq!(parse_symbolic(0o644, "a+X"), Ok(0o644));
+    assert_eq!(parse_symbolic(0o744, "a+X"), Ok(0o755));
+}
+
+#[test]
+fn invalid_symbolic_modes_are_rejected() {
+    assert!(parse_symbolic(0o644, "u").is_err());
+    assert!(parse_symbolic(0o644, "u+q").is_err());
+    assert!(parse_symbolic(0o644, "z+x").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn chmod_popup_toggles_and_applies_a_bit() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let dir = TempDir::new("chmod-popup");
+    dir.touch(&["script.sh"]);
+    let path = dir.path().join("script.sh");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::Chmod);
+    state.reduce(Action::PopupRight);
+    state.reduce(Action::PopupRight); // owner execute
+    state.reduce(Action::PopupToggle);
+    match &state.popup {
+        PopupState::Chmod {
+            mode, cursor_idx, ..
+        } => {
+            assert_eq!(mode & 0o7777, 0o744);
+            assert_eq!(*cursor_idx, 2);
+        }
+        other => panic!("expected the chmod popup, got {:?}", other),
+    }
+    assert!(format!("{:?}", state).contains("popup: Chmod"));
+
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o744);
+    assert_eq!(state.entries[1].permissions, "-rwxr--r--");
+}
+
+#[cfg(unix)]
+#[test]
+fn chmod_popup_sets_special_bits() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let dir = TempDir::new("chmod-special");
+    dir.touch(&["tool"]);
+    let path = dir.path().join("tool");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::Chmod);
+    for _ in 0..3 {
+        state.reduce(Action::PopupDown);
+    }
+    state.reduce(Action::PopupDown); // already on the last row
+    state.reduce(Action::PopupToggle); // setuid
+    state.reduce(Action::PopupSubmit);
+
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o4755);
+    assert_eq!(state.entries[1].permissions, "-rwsr-xr-x");
+}
@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::os::unix::fs::MetadataExt;
+
+use common::{TempDir, type_popup};
+use file_management::{
+    app::{Action, AppState, PopupState, Reducer},
+    users::Accounts,
+};
+
+const PASSWD: &str = "# comment
+root:x:0:0:root:/root:/bin/sh
+khai:x:1000:1000::/home/khai:/bin/sh
+";
+const GROUP: &str = "root:x:0:\nstaff:x:50:khai\nkhai:x:1000:\n";
+
+#[test]
+fn names_resolve_both_ways() {
+    let accounts = Accounts::parse(PASSWD, GROUP);
+    assert_eq!(accounts.user_name(1000), Some("khai"));
+    assert_eq!(accounts.group_name(50), Some("staff"));
+    assert_eq!(accounts.user_name(4242), None);
+
+    assert_eq!(accounts.uid("khai"), Ok(Some(1000)));
+    assert_eq!(accounts.uid(" 4242 "), Ok(Some(4242)));
+    assert_eq!(accounts.gid(""), Ok(None));
+    assert_eq!(accounts.uid("nobody"), Err("no such user 'nobody'".to_string()));
+    assert_eq!(accounts.gid("wheel"), Err("no such group 'wheel'".to_string()));
+}
+
+#[test]
+fn unknown_names_keep_the_popup_open() {
+    let dir = TempDir::new("chown-unknown");
+    dir.touch(&["a.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::Chown);
+    assert!(matches!(state.popup, PopupState::Chown { field: 0, .. }));
+    type_popup(&mut state, "-no-such-user");
+    state.reduce(Action::PopupSubmit);
+    match &state.popup {
+        PopupState::Chown { error: Some(e), .. } => assert!(e.starts_with("no such user")),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn chown_to_the_current_owner_by_id() {
+    let dir = TempDir::new("chown-self");
+    dir.touch(&["a.txt"]);
+    let meta = dir.path().join("a.txt").metadata().unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    // Giving a file to its own owner is always allowed
+    state.reduce(Action::Chown);
+    if let PopupState::Chown { user, group, .. } = &mut state.popup {
+        *user = meta.uid().to_string();
+        group.clear();
+    }
+    state.reduce(Action::PopupDown);
+    type_popup(&mut state, &meta.gid().to_string());
+    state.reduce(Action::PopupSubmit);
+
+    assert!(matches!(state.popup, PopupState::None));
+    let message = &state.toasts.last().unwrap().message;
+    assert_eq!(*message, format!("chown {}:{}: 1 updated", meta.uid(), meta.gid()));
+}
+
+#[test]
+fn entries_and_properties_carry_the_owner() {
+    let dir = TempDir::new("owner-names");
+    dir.touch(&["a.txt"]);
+    let meta = dir.path().join("a.txt").metadata().unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    let entry = state.current_entry().unwrap();
+    assert_eq!((entry.uid, entry.gid), (Some(meta.uid()), Some(meta.gid())));
+    assert_eq!(state.entries[0].uid, None); // `..`
+
+    state.reduce(Action::Properties);
+    let PopupState::Properties { lines, .. } = &state.popup else {
+        panic!("{:?}", state.popup);
+    };
+    let owner = &lines.iter().find(|(label, _)| label == "Owner").unwrap().1;
+    let name = state.accounts.user_label(meta.uid());
+    assert_eq!(*owner, format!("{} (uid {})", name, meta.uid()));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use clap::Parser;
+use common::TempDir;
+use file_management::{app::SortMode, cli::Args};
+
+#[test]
+fn flags_and_path_parse() {
+    let dir = TempDir::new("cli");
+    let path = dir.path().to_str().unwrap();
+    let args = Args::try_parse_from([
+        "file_management",
+        "--show-hidden",
+        "--sort",
+        "size",
+        "--theme",
+        "InspiredGitHub",
+        "--readonly",
+        path,
+    ])
+    .unwrap();
+    assert!(args.show_hidden);
+    assert_eq!(args.sort, Some(SortMode::Size));
+    assert_eq!(args.theme.as_deref(), Some("InspiredGitHub"));
+    assert!(!args.dry_run);
+    assert!(args.readonly);
+    let start = args.start().unwrap();
+    assert_eq!(start.dir, dir.path().canonicalize().unwrap());
+    assert_eq!(start.file, None);
+
+    assert!(Args::try_parse_from(["file_management", "--sort", "colour"]).is_err());
+}
+
+#[test]
+fn file_path_starts_on_the_file() {
+    let dir = TempDir::new("cli-file");
+    dir.touch(&["file.txt"]);
+    let root = dir.path().canonicalize().unwrap();
+
+    let file = dir.path().join("file.txt");
+    let args = Args::try_parse_from(["file_management".as_ref(), file.as_os_str()]).unwrap();
+    let start = args.start().unwrap();
+    assert_eq!(start.dir, root);
+    assert_eq!(start.file.as_deref(), Some("file.txt"));
+    assert_eq!(start.warning, None);
+
+    // A missing file falls back to its folder, a missing folder to cwd
+    let missing = dir.path().join("missing.txt");
+    let args = Args::try_parse_from(["file_management".as_ref(), missing.as_os_str()]).unwrap();
+    let start = args.start().unwrap();
+    assert_eq!(start.dir, root);
+    assert_eq!(start.file, None);
+    assert!(start.warning.unwrap().ends_with("missing.txt doesn't exist"));
+
+    let missing = dir.path().join("gone/file.txt");
+    let args = Args::try_parse_from(["file_management".as_ref(), missing.as_os_str()]).unwrap();
+    let start = args.start().unwrap();
+    assert_eq!(start.dir, std::env::current_dir().unwrap());
+    assert!(start.warning.is_some());
+}
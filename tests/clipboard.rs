@@ -0,0 +1,113 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, PopupState, PreviewContent, Reducer},
+    clipboard::SystemClipboard,
+};
+
+/// Records the last text written, or fails every write.
+struct FakeClipboard {
+    contents: Rc<RefCell<Option<String>>>,
+    fail: bool,
+}
+
+impl SystemClipboard for FakeClipboard {
+    fn set_text(&self, text: &str) -> Result<(), String> {
+        if self.fail {
+            return Err("no backend".to_string());
+        }
+        *self.contents.borrow_mut() = Some(text.to_string());
+        Ok(())
+    }
+}
+
+fn state_with_fake(dir: &TempDir, fail: bool) -> (AppState, Rc<RefCell<Option<String>>>) {
+    dir.touch(&["a", "b"]);
+    let contents = Rc::new(RefCell::new(None));
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.system_clipboard = Box::new(FakeClipboard {
+        contents: contents.clone(),
+        fail,
+    });
+    (state, contents)
+}
+
+#[test]
+fn copy_path_copies_cursor_entry() {
+    let dir = TempDir::new("copy-path");
+    let (mut state, contents) = state_with_fake(&dir, false);
+
+    state.reduce(Action::CopyPath);
+
+    let expected = dir.path().join("a").to_string_lossy().into_owned();
+    assert_eq!(contents.borrow().as_deref(), Some(expected.as_str()));
+    assert_eq!(state.status.as_deref(), Some("Copied path"));
+}
+
+#[test]
+fn copy_path_joins_selection_with_newlines() {
+    let dir = TempDir::new("copy-paths");
+    let (mut state, contents) = state_with_fake(&dir, false);
+
+    state.reduce(Action::SelectAll);
+    state.reduce(Action::CopyPath);
+
+    let expected = format!(
+        "{}\n{}",
+        dir.path().join("a").display(),
+        dir.path().join("b").display()
+    );
+    assert_eq!(contents.borrow().as_deref(), Some(expected.as_str()));
+    assert_eq!(state.status.as_deref(), Some("Copied 2 paths"));
+}
+
+#[test]
+fn copy_path_reports_backend_failure() {
+    let dir = TempDir::new("copy-path-fail");
+    let (mut state, _) = state_with_fake(&dir, true);
+
+    state.reduce(Action::CopyPath);
+
+    assert_eq!(state.status.as_deref(), Some("Clipboard error: no backend"));
+}
+
+fn text_preview(content: String) -> Action {
+    Action::PreviewReady(PreviewContent::Text {
+        title: "notes".to_string(),
+        content,
+        encoding: None,
+    })
+}
+
+#[test]
+fn copy_preview_text_puts_the_contents_on_the_clipboard() {
+    let dir = TempDir::new("copy-preview");
+    let (mut state, contents) = state_with_fake(&dir, false);
+
+    // Nothing to copy without a text preview
+    state.reduce(Action::CopyPreviewText);
+    assert_eq!(*contents.borrow(), None);
+
+    state.reduce(text_preview("one\ntwo\n".to_string()));
+    state.reduce(Action::CopyPreviewText);
+    assert_eq!(contents.borrow().as_deref(), Some("one\ntwo\n"));
+    assert_eq!(state.status.as_deref(), Some("Copied 2 lines"));
+}
+
+#[test]
+fn copying_a_huge_preview_asks_first() {
+    let dir = TempDir::new("copy-preview-big");
+    let (mut state, contents) = state_with_fake(&dir, false);
+    state.reduce(text_preview("x".repeat(2 * 1024 * 1024)));
+
+    state.reduce(Action::CopyPreviewText);
+    assert!(matches!(state.popup, PopupState::ConfirmCopyText { .. }));
+    assert_eq!(*contents.borrow(), None);
+
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(contents.borrow().as_ref().map(String::len), Some(2 * 1024 * 1024));
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, Reducer, SortMode},
+    ops,
+};
+
+fn run(state: &mut AppState, line: &str) {
+    state.reduce(Action::StartCommand);
+    for c in line.chars() {
+        state.reduce(Action::CommandInput(c));
+    }
+    state.reduce(Action::CommandSubmit);
+}
+
+#[test]
+fn commands_change_dir_make_dirs_and_settings() {
+    let dir = TempDir::new("command-run");
+    fs::create_dir_all(dir.path().join("sub")).unwrap();
+    dir.touch(&[".hidden"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    run(&mut state, "mkdir new");
+    assert!(dir.path().join("new").is_dir());
+    assert_eq!(state.current_entry().unwrap().name, "new");
+
+    run(&mut state, "set hidden");
+    assert!(state.show_hidden);
+    run(&mut state, "sort size!");
+    assert_eq!(state.sort_mode, SortMode::Size);
+    assert!(state.sort_reversed);
+
+    run(&mut state, "cd sub");
+    assert_eq!(state.cwd, fs::canonicalize(dir.path().join("sub")).unwrap());
+    run(&mut state, "cd ..");
+    assert_eq!(state.cwd, fs::canonicalize(dir.path()).unwrap());
+    assert!(!state.command_mode);
+}
+
+#[test]
+fn errors_go_to_the_status_line() {
+    let dir = TempDir::new("command-errors");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    run(&mut state, "frobnicate now");
+    assert_eq!(state.status.as_deref(), Some("Not a command: frobnicate"));
+    run(&mut state, "sort colour");
+    assert!(state.status.as_deref().unwrap().starts_with("sort: unknown mode"));
+    run(&mut state, "cd missing");
+    assert!(state.status.as_deref().unwrap().starts_with("cd: missing: "));
+}
+
+#[test]
+fn up_and_down_walk_the_history() {
+    let dir = TempDir::new("command-history");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    run(&mut state, "set hidden");
+    run(&mut state, "sort size");
+    run(&mut state, "set hidden"); // moves to the end rather than repeating
+
+    state.reduce(Action::StartCommand);
+    state.reduce(Action::CommandHistoryPrev);
+    assert_eq!(state.command_input, "set hidden");
+    state.reduce(Action::CommandHistoryPrev);
+    assert_eq!(state.command_input, "sort size");
+    state.reduce(Action::CommandHistoryPrev);
+    assert_eq!(state.command_input, "sort size");
+    state.reduce(Action::CommandHistoryNext);
+    state.reduce(Action::CommandHistoryNext);
+    assert_eq!(state.command_input, "");
+}
+
+#[test]
+fn complete_path_lists_matches_in_the_partial_dir() {
+    let dir = TempDir::new("complete-path");
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    dir.touch(&["src/main.rs", "src/mod.rs", "src/lib.rs", "src/.hidden"]);
+    let src = dir.path().join("src");
+
+    let names = |partial: &str| -> Vec<String> {
+        ops::complete_path(&dir.path().join(partial))
+            .iter()
+            .map(|p| p.strip_prefix(&src).unwrap().to_string_lossy().into_owned())
+            .collect()
+    };
+    assert_eq!(names("src/m"), ["main.rs", "mod.rs"]);
+    assert_eq!(names("src/"), ["lib.rs", "main.rs", "mod.rs"]);
+    assert_eq!(names("src/."), [".hidden"]);
+    assert!(names("src/x").is_empty());
+    // Absolute partials need nothing special
+    assert_eq!(ops::complete_path(&src.join("li")), [src.join("lib.rs")]);
+}
+
+#[test]
+fn tab_completes_the_common_prefix_then_cycles() {
+    let dir = TempDir::new("complete-tab");
+    fs::create_dir_all(dir.path().join("photos-2023")).unwrap();
+    fs::create_dir_all(dir.path().join("photos-2024")).unwrap();
+    dir.touch(&["notes.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::StartCommand);
+    for c in "cd ph".chars() {
+        state.reduce(Action::CommandInput(c));
+    }
+    state.reduce(Action::CommandComplete);
+    assert_eq!(state.command_input, "cd photos-202");
+    state.reduce(Action::CommandComplete);
+    assert_eq!(state.command_input, "cd photos-2023/");
+    state.reduce(Action::CommandComplete);
+    assert_eq!(state.command_input, "cd photos-2024/");
+    state.reduce(Action::CommandComplete);
+    assert_eq!(state.command_input, "cd photos-2023/");
+
+    // A single match completes outright
+    state.reduce(Action::CommandCancel);
+    state.reduce(Action::StartCommand);
+    for c in "mkdir no".chars() {
+        state.reduce(Action::CommandInput(c));
+    }
+    state.reduce(Action::CommandComplete);
+    assert_eq!(state.command_input, "mkdir notes.txt");
+}
+
+#[test]
+fn glob_filter_keeps_directories_and_matching_files() {
+    let dir = TempDir::new("command-glob");
+    fs::create_dir_all(dir.path().join("assets")).unwrap();
+    dir.touch(&["a.png", "b.JPG", "c.rs", "d.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let names = |state: &AppState| -> Vec<String> {
+        state.visible_entries().iter().map(|e| e.name.clone()).collect()
+    };
+
+    run(&mut state, "glob *.{png,jpg}");
+    assert_eq!(names(&state), ["..", "assets", "a.png", "b.JPG"]);
+    assert_eq!(state.glob_filter.as_deref(), Some("*.{png,jpg}"));
+
+    run(&mut state, "glob [");
+    assert!(state.status.as_deref().unwrap().starts_with("Bad glob ["));
+    assert_eq!(state.glob_filter.as_deref(), Some("*.{png,jpg}"));
+
+    run(&mut state, "glob");
+    assert_eq!(names(&state).len(), 6);
+}
@@ -0,0 +1,61 @@
+// Shared by several test crates; not every crate uses every helper.
+#![allow(dead_code)]
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use file_management::app::{Action, AppState, Reducer};
+
+/// A scratch directory under the system temp dir, removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "file_management-{}-{}-{}",
+            name,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Creates an empty file for each name.
+    pub fn touch(&self, names: &[&str]) {
+        for name in names {
+            fs::write(self.0.join(name), b"").unwrap();
+        }
+    }
+
+    /// The names directly inside, sorted.
+    pub fn names(&self) -> Vec<OsString> {
+        let mut names: Vec<_> =
+            fs::read_dir(&self.0).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        names
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Types `text` into the open popup, a character at a time.
+pub fn type_popup(state: &mut AppState, text: &str) {
+    for c in text.chars() {
+        state.reduce(Action::PopupInput(c));
+    }
+}
@@ -0,0 +1,115 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use file_management::{
+    app::SortMode,
+    config::{self, Command},
+};
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+#[test]
+fn empty_file_gives_defaults() {
+    let config = config::parse_config("").unwrap();
+    assert!(!config.show_hidden);
+    assert_eq!(config.sort_mode, SortMode::Name);
+    assert!(config.dirs_first);
+    assert!(!config.per_dir_view);
+    assert!(!config.dry_run);
+    assert!(!config.confirm_overwrite);
+    assert!(!config.readonly);
+    assert!(config.trash);
+    assert!(!config.size_colors);
+    assert!(!config.clear_selection_on_nav);
+    assert_eq!(config.preview_page_step, 0);
+    assert_eq!(config.theme, config::DEFAULT_THEME);
+    assert_eq!(
+        config.keymap.lookup(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+        Some(Command::Quit)
+    );
+    assert_eq!(
+        config.keymap.lookup(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+        Some(Command::HalfPageDown)
+    );
+    assert_eq!(
+        config.keymap.lookup(&key(KeyCode::Char('b'), KeyModifiers::CONTROL)),
+        Some(Command::PageUp)
+    );
+    // ctrl+f pages the preview, and fuzzy finds everywhere else
+    let ctrl_f = key(KeyCode::Char('f'), KeyModifiers::CONTROL);
+    assert_eq!(config.keymap.lookup(&ctrl_f), Some(Command::FuzzyFind));
+    assert_eq!(config.keymap.lookup_in_preview(&ctrl_f), Some(Command::PageDown));
+    assert_eq!(
+        config.keymap.lookup_in_preview(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+        Some(Command::Quit)
+    );
+}
+
+#[test]
+fn options_and_rebinds_apply() {
+    let config = config::parse_config(
+        r#"
+        show_hidden = true
+        sort = "size"
+        dirs_first = false
+        per_dir_view = true
+        dry_run = true
+        confirm_overwrite = true
+        size_colors = true
+        clear_selection_on_nav = true
+        preview_page_step = 20
+        split_ratio = 95
+        theme = "InspiredGitHub"
+
+        [keys]
+        quit = "ctrl+q"
+        down = ["j", "ctrl+n"]
+
+        [previewers]
+        PDF = "pdftotext -layout $1 -"
+        "#,
+    )
+    .unwrap();
+    assert!(config.show_hidden);
+    assert_eq!(config.sort_mode, SortMode::Size);
+    assert!(!config.dirs_first);
+    assert!(config.per_dir_view);
+    assert!(config.dry_run);
+    assert!(config.confirm_overwrite);
+    assert!(config.size_colors);
+    assert!(config.clear_selection_on_nav);
+    assert_eq!(config.preview_page_step, 20);
+    assert_eq!(config.split_ratio, 80); // clamped
+    assert_eq!(config.theme, "InspiredGitHub");
+    assert_eq!(config.previewers["pdf"], "pdftotext -layout $1 -");
+
+    let keymap = &config.keymap;
+    assert_eq!(keymap.lookup(&key(KeyCode::Char('q'), KeyModifiers::NONE)), None);
+    assert_eq!(
+        keymap.lookup(&key(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+        Some(Command::Quit)
+    );
+    assert_eq!(
+        keymap.lookup(&key(KeyCode::Char('n'), KeyModifiers::CONTROL)),
+        Some(Command::Down)
+    );
+    // Rebinding `down` drops its old arrow-key binding
+    assert_eq!(keymap.lookup(&key(KeyCode::Down, KeyModifiers::NONE)), None);
+}
+
+#[test]
+fn shift_is_ignored_for_characters() {
+    let config = config::parse_config("").unwrap();
+    assert_eq!(
+        config.keymap.lookup(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+        Some(Command::Bottom)
+    );
+}
+
+#[test]
+fn bad_values_are_errors() {
+    assert!(config::parse_config("sort = \"colour\"").is_err());
+    assert!(config::parse_config("[keys]\nquit = \"hyper+q\"").is_err());
+    assert!(config::parse_config("[keys]\nlaunch_rockets = \"r\"").is_err());
+    assert!(config::parse_config("no_such_option = 1").is_err());
+}
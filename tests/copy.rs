@@ -0,0 +1,413 @@
+mod common;
+
+use std::{fs, sync::atomic::AtomicBool, time::Duration};
+
+use common::{TempDir, type_popup};
+use file_management::{
+    app::{Action, AppState, ConflictChoice, PopupState, Reducer},
+    ops,
+};
+
+fn wait_for_copy(state: &mut AppState) {
+    loop {
+        let action = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let finished = matches!(action, Action::CopyFinished { .. } | Action::MoveFinished { .. });
+        state.reduce(action);
+        if finished {
+            break;
+        }
+    }
+}
+
+#[test]
+fn paste_runs_in_the_background_and_reports_back() {
+    let dir = TempDir::new("paste-progress");
+    let src = dir.path().join("src");
+    fs::create_dir_all(src.join("sub")).unwrap();
+    fs::write(src.join("a"), b"a").unwrap();
+    fs::write(src.join("sub/b"), b"b").unwrap();
+    fs::create_dir_all(dir.path().join("dest")).unwrap();
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.selected.insert(src.clone());
+    state.reduce(Action::Yank);
+    state.reduce(Action::EnterDir); // into "dest", the first entry
+    state.reduce(Action::Paste);
+    assert!(matches!(state.popup, PopupState::Progress { .. }));
+
+    wait_for_copy(&mut state);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(state.toasts.last().unwrap().message, "Pasted 1 item(s)");
+    assert_eq!(fs::read(dir.path().join("dest/src/sub/b")).unwrap(), b"b");
+}
+
+#[test]
+fn paste_lands_on_the_first_pasted_item() {
+    let dir = TempDir::new("paste-reveal");
+    fs::create_dir_all(dir.path().join("from")).unwrap();
+    fs::create_dir_all(dir.path().join("to")).unwrap();
+    dir.touch(&["from/m.txt", "from/c.txt", "to/a.txt", "to/z.txt"]);
+
+    let mut state = AppState::new(dir.path().join("from")).unwrap();
+    state.reduce(Action::SelectAll);
+    state.reduce(Action::Yank);
+    state.reduce(Action::GoBack);
+    state.reduce(Action::CursorMoveDown);
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.cwd, dir.path().join("to"));
+    assert_eq!(state.current_entry().unwrap().name, "a.txt");
+    state.reduce(Action::Paste);
+
+    wait_for_copy(&mut state);
+    assert_eq!(state.current_entry().unwrap().name, "c.txt");
+    assert!(!state.select_entry_by_name("missing"));
+    assert!(state.select_entry_by_name("m.txt"));
+    assert_eq!(state.current_entry().unwrap().name, "m.txt");
+}
+
+#[test]
+fn cancelled_copy_stops_before_the_next_file() {
+    let dir = TempDir::new("copy-cancel");
+    dir.touch(&["a"]);
+    let mut copied = 0;
+
+    let result = ops::copy_with_progress(
+        &dir.path().join("a"),
+        &dir.path().join("b"),
+        &AtomicBool::new(true),
+        &mut |_| copied += 1,
+    );
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+    assert_eq!(copied, 0);
+    assert!(!dir.path().join("b").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_keeps_directory_mode_and_mtimes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("copy-metadata");
+    let src = dir.path().join("private");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("key"), b"secret").unwrap();
+    let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(src.join("key"), old).unwrap();
+    filetime::set_file_mtime(&src, old).unwrap();
+    fs::set_permissions(&src, fs::Permissions::from_mode(0o700)).unwrap();
+
+    let dst = dir.path().join("backup");
+    ops::copy_recursive(&src, &dst).unwrap();
+
+    let meta = fs::metadata(&dst).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o7777, 0o700);
+    assert_eq!(filetime::FileTime::from_last_modification_time(&meta), old);
+    let file_meta = fs::metadata(dst.join("key")).unwrap();
+    assert_eq!(filetime::FileTime::from_last_modification_time(&file_meta), old);
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_keeps_links_as_links() {
+    let dir = TempDir::new("copy-links");
+    let src = dir.path().join("src");
+    fs::create_dir_all(dir.path().join("big")).unwrap();
+    fs::write(dir.path().join("big/data"), b"lots").unwrap();
+    fs::create_dir(&src).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("big"), src.join("to-big")).unwrap();
+    std::os::unix::fs::symlink("nowhere", src.join("dangling")).unwrap();
+    // A link back up would never end if it were followed
+    std::os::unix::fs::symlink("..", src.join("up")).unwrap();
+
+    assert_eq!(ops::count_files(&src), 3);
+    let dst = dir.path().join("dst");
+    ops::copy_recursive(&src, &dst).unwrap();
+    assert_eq!(fs::read_link(dst.join("to-big")).unwrap(), dir.path().join("big"));
+    assert_eq!(fs::read_link(dst.join("dangling")).unwrap(), std::path::Path::new("nowhere"));
+    assert_eq!(fs::read_link(dst.join("up")).unwrap(), std::path::Path::new(".."));
+}
+
+#[test]
+fn duplicate_copies_next_to_the_original() {
+    let dir = TempDir::new("duplicate");
+    fs::create_dir_all(dir.path().join("site/css")).unwrap();
+    fs::write(dir.path().join("site/css/a.css"), "body {}").unwrap();
+    fs::write(dir.path().join("notes.md"), "hi").unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::CursorBottom); // notes.md
+    state.reduce(Action::Duplicate);
+    assert_eq!(state.current_entry().unwrap().name, "notes (copy).md");
+    assert_eq!(state.status.as_deref(), Some("Duplicated as notes (copy).md"));
+    state.reduce(Action::CursorBottom); // notes.md sorts after its copies
+    state.reduce(Action::Duplicate);
+    assert_eq!(state.current_entry().unwrap().name, "notes (copy 2).md");
+
+    state.reduce(Action::CursorTop);
+    state.reduce(Action::CursorMoveDown); // site
+    state.reduce(Action::Duplicate);
+    let copy = dir.path().join("site (copy)");
+    assert_eq!(fs::read_to_string(copy.join("css/a.css")).unwrap(), "body {}");
+
+    state.reduce(Action::Undo);
+    assert!(!copy.exists());
+    assert_eq!(ops::unique_copy_path(&dir.path().join(".env")), dir.path().join(".env (copy)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_popup_links_to_the_current_entry() {
+    let dir = TempDir::new("symlink");
+    fs::write(dir.path().join("notes.md"), "hi").unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let type_name = |state: &mut AppState, name: &str| {
+        type_popup(state, name);
+        state.reduce(Action::PopupSubmit);
+    };
+
+    state.reduce(Action::CursorBottom); // notes.md
+    state.reduce(Action::CreateSymlink);
+    type_name(&mut state, "notes.md");
+    assert!(matches!(&state.popup,
+        PopupState::CreateSymlink { error: Some(e), .. } if e == "notes.md already exists"));
+
+    state.reduce(Action::PopupCancel);
+    state.reduce(Action::CreateSymlink);
+    type_name(&mut state, "latest.md");
+    let link = dir.path().join("latest.md");
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(fs::read_link(&link).unwrap(), std::path::Path::new("notes.md"));
+    assert_eq!(fs::read_to_string(&link).unwrap(), "hi");
+    assert_eq!(state.current_entry().unwrap().name, "latest.md");
+
+    // The target vanishing while the prompt is open is reported, not linked
+    state.reduce(Action::CursorBottom);
+    state.reduce(Action::CreateSymlink);
+    fs::remove_file(dir.path().join("notes.md")).unwrap();
+    type_name(&mut state, "old.md");
+    assert!(matches!(&state.popup,
+        PopupState::CreateSymlink { error: Some(e), .. } if e.ends_with("no longer exists")));
+    assert!(fs::symlink_metadata(dir.path().join("old.md")).is_err());
+}
+
+#[test]
+fn move_popup_checks_the_folder_then_moves() {
+    let dir = TempDir::new("move-to");
+    fs::create_dir_all(dir.path().join("docs/old")).unwrap();
+    dir.touch(&["a.txt", "b.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let submit = |state: &mut AppState, typed: &str| {
+        while let PopupState::MoveTo { input, .. } = &state.popup
+            && !input.is_empty()
+        {
+            state.reduce(Action::PopupBackspace);
+        }
+        type_popup(state, typed);
+        state.reduce(Action::PopupSubmit);
+        match &state.popup {
+            PopupState::MoveTo { error, .. } => error.clone(),
+            _ => None,
+        }
+    };
+
+    // A folder can't go inside itself
+    state.select_entry_by_name("docs");
+    state.reduce(Action::MoveTo);
+    assert_eq!(submit(&mut state, "docs/old").as_deref(), Some("Can't move docs into itself"));
+    assert!(submit(&mut state, "a.txt").unwrap().ends_with("Not a directory"));
+    assert_eq!(submit(&mut state, ".").as_deref(), Some("Already in ."));
+    state.reduce(Action::PopupCancel);
+
+    state.select_entry_by_name("a.txt");
+    state.reduce(Action::ToggleSelect);
+    state.select_entry_by_name("b.txt");
+    state.reduce(Action::ToggleSelect);
+    state.reduce(Action::MoveTo);
+    assert_eq!(submit(&mut state, "docs/old"), None);
+    assert!(matches!(state.popup, PopupState::None));
+    assert!(dir.path().join("docs/old/a.txt").exists());
+    assert!(dir.path().join("docs/old/b.txt").exists());
+    assert!(!dir.path().join("a.txt").exists());
+    assert!(state.selected.is_empty());
+
+    state.reduce(Action::Undo);
+    assert!(dir.path().join("a.txt").exists() && dir.path().join("b.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn move_to_another_filesystem_runs_in_the_background() {
+    use std::os::unix::fs::MetadataExt;
+
+    // Needs a second filesystem; /dev/shm usually is one
+    let dir = TempDir::new("move-across");
+    let shm = std::path::Path::new("/dev/shm");
+    match fs::metadata(shm) {
+        Ok(meta) if meta.dev() != fs::metadata(dir.path()).unwrap().dev() => {}
+        _ => return,
+    }
+    let dest = shm.join(dir.path().file_name().unwrap());
+    fs::create_dir(&dest).unwrap();
+    fs::create_dir_all(dir.path().join("docs/sub")).unwrap();
+    fs::write(dir.path().join("docs/sub/b.txt"), b"across").unwrap();
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.select_entry_by_name("docs");
+    state.reduce(Action::MoveTo);
+    type_popup(&mut state, &dest.to_string_lossy());
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::Progress { .. }));
+    wait_for_copy(&mut state);
+    let moved = fs::read(dest.join("docs/sub/b.txt"));
+    let gone = !dir.path().join("docs").exists();
+    state.reduce(Action::Undo);
+    let back = fs::read(dir.path().join("docs/sub/b.txt"));
+    fs::remove_dir_all(&dest).unwrap();
+
+    assert_eq!(moved.unwrap(), b"across");
+    assert!(gone);
+    assert_eq!(back.unwrap(), b"across");
+    assert_eq!(state.toasts[state.toasts.len() - 2].message, "Moved 1 item(s)");
+}
+
+#[test]
+fn paste_into_targets_the_folder_under_the_cursor() {
+    let dir = TempDir::new("paste-into");
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    dir.touch(&["a.txt"]);
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.select_entry_by_name("a.txt");
+    state.reduce(Action::Yank);
+    state.select_entry_by_name("docs");
+    state.reduce(Action::PasteInto);
+    wait_for_copy(&mut state);
+    assert!(dir.path().join("docs/a.txt").exists());
+    assert_eq!(state.cwd, dir.path());
+
+    // Not into itself, though
+    state.reduce(Action::Yank);
+    state.reduce(Action::PasteInto);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(state.status.as_deref(), Some("Can't copy docs into itself"));
+}
+
+#[test]
+fn paste_asks_about_each_taken_name_when_confirming() {
+    let dir = TempDir::new("paste-conflict");
+    fs::create_dir_all(dir.path().join("from")).unwrap();
+    fs::create_dir_all(dir.path().join("to")).unwrap();
+    for name in ["a", "b", "c", "d", "e"] {
+        fs::write(dir.path().join("from").join(name), b"new").unwrap();
+    }
+    for name in ["a", "b", "c", "d"] {
+        fs::write(dir.path().join("to").join(name), b"old").unwrap();
+    }
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.confirm_overwrite = true;
+    state.select_entry_by_name("from");
+    state.reduce(Action::EnterDir);
+    state.reduce(Action::SelectAll);
+    state.reduce(Action::Yank);
+    state.reduce(Action::GoBack);
+    state.select_entry_by_name("to");
+    state.reduce(Action::EnterDir);
+
+    // One answer each for "a" to "c"; "all" on "c" settles "d" too
+    state.reduce(Action::Paste);
+    let answers = [ConflictChoice::Skip, ConflictChoice::Rename, ConflictChoice::OverwriteAll];
+    for (name, choice) in ["a", "b", "c"].into_iter().zip(answers) {
+        assert!(matches!(&state.popup, PopupState::Conflict { name: asked } if asked == name));
+        state.reduce(Action::ResolveConflict(choice));
+    }
+    assert!(matches!(state.popup, PopupState::Progress { .. }));
+    wait_for_copy(&mut state);
+    let to = dir.path().join("to");
+    assert_eq!(fs::read(to.join("a")).unwrap(), b"old");
+    assert_eq!(fs::read(to.join("b")).unwrap(), b"old");
+    assert_eq!(fs::read(to.join("b (copy)")).unwrap(), b"new");
+    assert_eq!(fs::read(to.join("c")).unwrap(), b"new");
+    assert_eq!(fs::read(to.join("d")).unwrap(), b"new");
+    assert_eq!(fs::read(to.join("e")).unwrap(), b"new");
+}
+
+#[test]
+fn paste_skipping_every_conflict_copies_nothing() {
+    let dir = TempDir::new("paste-conflict-none");
+    dir.touch(&["a.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.confirm_overwrite = true;
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/a.txt"), b"old").unwrap();
+    state.reload_entries();
+    state.select_entry_by_name("a.txt");
+    state.reduce(Action::Yank);
+    state.select_entry_by_name("sub");
+    state.reduce(Action::PasteInto);
+    assert!(matches!(state.popup, PopupState::Conflict { .. }));
+    state.reduce(Action::ResolveConflict(ConflictChoice::SkipAll));
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(state.toasts.last().unwrap().message, "Nothing pasted");
+    assert_eq!(fs::read(dir.path().join("sub/a.txt")).unwrap(), b"old");
+
+    // Esc drops the paste altogether
+    state.reduce(Action::PasteInto);
+    state.reduce(Action::PopupCancel);
+    assert!(state.pending_paste.is_none());
+    assert_eq!(state.toasts.last().unwrap().message, "Paste cancelled");
+}
+
+#[test]
+fn paste_into_its_own_folder_makes_a_copy() {
+    let dir = TempDir::new("paste-self");
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs/a.txt"), b"inside").unwrap();
+    fs::write(dir.path().join("notes.md"), b"hi").unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.selected.insert(dir.path().join("docs"));
+    state.selected.insert(dir.path().join("notes.md"));
+    state.reduce(Action::Yank);
+    state.reduce(Action::Paste);
+    wait_for_copy(&mut state);
+    assert_eq!(fs::read(dir.path().join("notes.md")).unwrap(), b"hi");
+    assert_eq!(fs::read(dir.path().join("notes (copy).md")).unwrap(), b"hi");
+    assert_eq!(fs::read(dir.path().join("docs/a.txt")).unwrap(), b"inside");
+    assert_eq!(fs::read(dir.path().join("docs (copy)/a.txt")).unwrap(), b"inside");
+
+    // The copy itself refuses too, whatever the caller
+    let notes = dir.path().join("notes.md");
+    let err = ops::copy_recursive(&notes, &dir.path().join("docs/../notes.md")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read(&notes).unwrap(), b"hi");
+}
+
+#[test]
+fn quitting_during_a_copy_asks_first() {
+    let dir = TempDir::new("quit-copy");
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    dir.touch(&["a.txt", "sub/a.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.select_entry_by_name("a.txt");
+    state.reduce(Action::Yank);
+    state.select_entry_by_name("sub");
+    state.reduce(Action::PasteInto);
+    assert!(state.copy_cancel.is_some());
+
+    state.reduce(Action::RequestQuit);
+    assert!(matches!(&state.popup,
+        PopupState::ConfirmQuit { reason } if reason == "A copy or move is still running."));
+    assert!(!state.should_quit);
+    state.reduce(Action::PopupCancel);
+    assert!(matches!(state.popup, PopupState::Progress { .. }));
+    wait_for_copy(&mut state);
+
+    // Likewise while a conflict is waiting on an answer, which staying keeps
+    state.confirm_overwrite = true;
+    state.reduce(Action::PasteInto);
+    state.reduce(Action::RequestQuit);
+    assert!(matches!(state.popup, PopupState::ConfirmQuit { .. }));
+    state.reduce(Action::PopupCancel);
+    assert!(matches!(state.popup, PopupState::Conflict { .. }));
+    assert!(state.pending_paste.is_some());
+}
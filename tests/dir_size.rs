@@ -0,0 +1,113 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use common::TempDir;
+use file_management::app::{Action, AppState, PopupState, Reducer, Tally};
+
+#[test]
+fn dir_size_is_summed_in_the_background_and_cached() {
+    let dir = TempDir::new("dir-size");
+    let sub = dir.path().join("sub");
+    fs::create_dir_all(sub.join("deeper")).unwrap();
+    fs::write(sub.join("a"), [0; 100]).unwrap();
+    fs::write(sub.join("deeper/b"), [0; 23]).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::ComputeDirSize);
+    assert_eq!(state.dir_sizes.get(&sub), Some(&None));
+
+    let done = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    state.reduce(done);
+    assert_eq!(state.dir_sizes.get(&sub), Some(&Some(123)));
+}
+
+#[test]
+fn selection_size_counts_files_and_summed_dirs() {
+    let dir = TempDir::new("selection-size");
+    let sub = dir.path().join("sub");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(sub.join("a"), [0; 100]).unwrap();
+    fs::write(dir.path().join("b"), [0; 7]).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::SelectAll);
+    // "sub" hasn't been summed yet
+    assert_eq!(state.selection_size(), 7);
+
+    state.reduce(Action::ComputeDirSize);
+    let done = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    state.reduce(done);
+    assert_eq!(state.selection_size(), 107);
+
+    // Selected files stay counted after leaving their directory
+    state.selected.remove(&sub);
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.cwd, sub);
+    assert_eq!(state.selection_size(), 7);
+}
+
+#[test]
+fn selection_summary_totals_everything_and_caches_it() {
+    let dir = TempDir::new("selection-summary");
+    let sub = dir.path().join("sub");
+    fs::create_dir_all(sub.join("deeper")).unwrap();
+    fs::write(sub.join("a"), [0; 100]).unwrap();
+    fs::write(sub.join("deeper/b"), [0; 23]).unwrap();
+    fs::write(dir.path().join("c"), [0; 7]).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let expected = Tally { files: 3, dirs: 2, bytes: 130 };
+
+    state.reduce(Action::SelectAll);
+    state.reduce(Action::SummarizeSelection);
+    assert!(matches!(state.popup, PopupState::Summary { items: 2, running: true, .. }));
+    let done = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    state.reduce(done);
+    let PopupState::Summary { cached, running, .. } = state.popup else {
+        panic!("expected the summary, got {:?}", state.popup);
+    };
+    assert!(!running);
+    assert_eq!(cached, expected);
+    assert_eq!(state.dir_sizes.get(&sub), Some(&Some(123)));
+
+    // The second time it's all from the cache
+    state.reduce(Action::PopupCancel);
+    state.reduce(Action::SummarizeSelection);
+    let PopupState::Summary { cached, running, .. } = state.popup else {
+        panic!("expected the summary, got {:?}", state.popup);
+    };
+    assert!(!running);
+    assert_eq!(cached, expected);
+}
+
+#[test]
+fn summaries_are_redone_after_a_change() {
+    let dir = TempDir::new("summary-stale");
+    let sub = dir.path().join("sub");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(sub.join("a"), [0; 10]).unwrap();
+    dir.touch(&["c"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let summarize = |state: &mut AppState| {
+        state.reduce(Action::SummarizeSelection);
+        let done = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        state.reduce(done);
+        let PopupState::Summary { cached, .. } = state.popup else {
+            panic!("expected the summary, got {:?}", state.popup);
+        };
+        state.reduce(Action::PopupCancel);
+        cached
+    };
+
+    state.reduce(Action::SelectAll);
+    assert_eq!(summarize(&mut state), Tally { files: 2, dirs: 1, bytes: 10 });
+
+    // A new folder in `sub` makes its summed size stale
+    state.reduce(Action::StartCommand);
+    for c in "mkdir sub/more".chars() {
+        state.reduce(Action::CommandInput(c));
+    }
+    state.reduce(Action::CommandSubmit);
+    assert!(!state.tallies.contains_key(&sub));
+    assert_eq!(summarize(&mut state), Tally { files: 2, dirs: 2, bytes: 10 });
+}
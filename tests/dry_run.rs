@@ -0,0 +1,43 @@
+mod common;
+
+use std::fs;
+
+use common::{TempDir, type_popup};
+use file_management::app::{Action, AppState, PopupState, Reducer};
+
+fn last_toast(state: &AppState) -> &str {
+    &state.toasts.last().unwrap().message
+}
+
+#[test]
+fn dry_run_reports_instead_of_touching_files() {
+    let dir = TempDir::new("dry-run");
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    dir.touch(&["a.txt", "b.txt"]);
+    let listing = dir.names();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.dry_run = true;
+
+    state.reduce(Action::CursorBottom); // b.txt
+    state.reduce(Action::Delete);
+    assert_eq!(last_toast(&state), "[dry-run] would delete 1 item(s)");
+
+    state.reduce(Action::Duplicate);
+    assert!(last_toast(&state).ends_with("as b (copy).txt"), "{}", last_toast(&state));
+
+    state.selected.insert(dir.path().join("a.txt"));
+    state.reduce(Action::Yank);
+    state.reduce(Action::Paste);
+    assert!(matches!(state.popup, PopupState::None)); // no copy started
+    assert!(last_toast(&state).starts_with("[dry-run] would copy 1 item(s) to "));
+
+    state.reduce(Action::BulkRename);
+    type_popup(&mut state, "{n}.txt");
+    state.reduce(Action::PopupSubmit);
+    assert_eq!(last_toast(&state), "[dry-run] would rename 1 item(s)");
+    assert!(matches!(state.popup, PopupState::None));
+
+    assert_eq!(dir.names(), listing);
+    state.reduce(Action::Undo);
+    assert_eq!(state.status.as_deref(), Some("Nothing to undo"));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use common::TempDir;
+use file_management::app::{Action, AppState, Reducer};
+
+#[test]
+fn panes_navigate_independently() {
+    let dir = TempDir::new("dual-switch");
+    fs::create_dir_all(dir.path().join("sub")).unwrap();
+    dir.touch(&["a.txt"]);
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::ToggleDualPane);
+    state.reduce(Action::EnterDir); // left pane into "sub"
+    state.reduce(Action::SwitchPane);
+    assert_eq!(state.active_pane, 1);
+    assert_eq!(state.cwd, dir.path());
+    assert_eq!(state.other_pane_cwd(), Some(dir.path().join("sub").as_path()));
+
+    state.reduce(Action::SwitchPane);
+    assert_eq!(state.cwd, dir.path().join("sub"));
+
+    // Leaving dual-pane mode keeps the active listing
+    state.reduce(Action::ToggleDualPane);
+    assert!(state.other_pane.is_none());
+    assert_eq!(state.active_pane, 0);
+}
+
+#[test]
+fn copy_goes_to_the_other_pane() {
+    let dir = TempDir::new("dual-copy");
+    fs::create_dir_all(dir.path().join("dest")).unwrap();
+    fs::write(dir.path().join("a.txt"), "alpha").unwrap();
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::ToggleDualPane);
+    state.reduce(Action::SwitchPane);
+    state.reduce(Action::EnterDir); // right pane into "dest"
+    state.reduce(Action::SwitchPane);
+    state.reduce(Action::CursorMoveDown); // a.txt
+    state.reduce(Action::CopyToPane);
+
+    loop {
+        let action = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let finished = matches!(action, Action::CopyFinished { .. });
+        state.reduce(action);
+        if finished {
+            break;
+        }
+    }
+    assert_eq!(fs::read_to_string(dir.path().join("dest/a.txt")).unwrap(), "alpha");
+    let other = &state.other_pane.as_ref().unwrap().entries;
+    assert!(other.iter().any(|e| e.name == "a.txt"));
+}
+
+#[test]
+fn move_to_the_other_pane_can_be_undone() {
+    let dir = TempDir::new("dual-move");
+    fs::create_dir_all(dir.path().join("dest")).unwrap();
+    dir.touch(&["a.txt", "b.txt"]);
+    fs::write(dir.path().join("dest/b.txt"), "").unwrap();
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::ToggleDualPane);
+    state.reduce(Action::SwitchPane);
+    state.reduce(Action::EnterDir);
+    state.reduce(Action::SwitchPane);
+    state.reduce(Action::SelectAll);
+    state.selected.remove(&dir.path().join("dest"));
+    state.reduce(Action::MoveToPane);
+
+    // b.txt is already there and stays put
+    assert!(dir.path().join("dest/a.txt").exists());
+    assert!(dir.path().join("b.txt").exists());
+    assert_eq!(
+        state.toasts.last().unwrap().message,
+        "Moved 1 item(s), 1 failed (first: b.txt: already exists)"
+    );
+
+    state.reduce(Action::Undo);
+    assert!(dir.path().join("a.txt").exists());
+    assert!(!dir.path().join("dest/a.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn a_dangling_link_still_takes_the_name() {
+    let dir = TempDir::new("dual-move-link");
+    fs::create_dir_all(dir.path().join("dest")).unwrap();
+    dir.touch(&["a.txt"]);
+    std::os::unix::fs::symlink("nowhere", dir.path().join("dest/a.txt")).unwrap();
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::ToggleDualPane);
+    state.reduce(Action::SwitchPane);
+    state.reduce(Action::EnterDir);
+    state.reduce(Action::SwitchPane);
+    state.select_entry_by_name("a.txt");
+    state.reduce(Action::MoveToPane);
+
+    assert!(dir.path().join("a.txt").exists());
+    assert!(fs::read_link(dir.path().join("dest/a.txt")).is_ok());
+    assert_eq!(
+        state.toasts.last().unwrap().message,
+        "Moved 0 item(s), 1 failed (first: a.txt: already exists)"
+    );
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::sync::Mutex;
+
+use common::{TempDir, type_popup};
+use file_management::{
+    app::{Action, AppState, ExternalCommand, PopupState, Reducer},
+    config,
+};
+
+// Tests here change environment variables, so they take turns
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn open_with_requests_the_command_and_remembers_it() {
+    let dir = TempDir::new("open-with");
+    let config_home = TempDir::new("open-with-config");
+    let _env = ENV_LOCK.lock().unwrap();
+    // SAFETY: ENV_LOCK keeps the other tests here off the environment
+    unsafe { std::env::set_var("XDG_CONFIG_HOME", config_home.path()) };
+    dir.touch(&["notes.txt"]);
+    let file = dir.path().join("notes.txt").to_string_lossy().into_owned();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::OpenWith);
+    type_popup(&mut state, "vim -R");
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(
+        state.run_request.take(),
+        Some(ExternalCommand {
+            program: "vim".to_string(),
+            args: vec!["-R".to_string(), file.clone()],
+            background: false,
+        })
+    );
+
+    // A trailing & detaches
+    state.reduce(Action::OpenWith);
+    type_popup(&mut state, "feh &");
+    state.reduce(Action::PopupSubmit);
+    assert_eq!(
+        state.run_request.take(),
+        Some(ExternalCommand {
+            program: "feh".to_string(),
+            args: vec![file],
+            background: true,
+        })
+    );
+
+    // Newest first, and saved for next time
+    assert_eq!(state.recent_commands, ["feh &", "vim -R"]);
+    assert_eq!(config::load_recent_commands(), ["feh &", "vim -R"]);
+
+    // Down picks from the history
+    state.reduce(Action::OpenWith);
+    state.reduce(Action::PopupDown);
+    state.reduce(Action::PopupDown);
+    match &state.popup {
+        PopupState::OpenWith { input, .. } => assert_eq!(input, "vim -R"),
+        other => panic!("expected the open-with popup, got {:?}", other),
+    }
+
+    // Up while typing keeps the text; leaving the list brings it back
+    state.reduce(Action::PopupCancel);
+    state.reduce(Action::OpenWith);
+    type_popup(&mut state, "less");
+    state.reduce(Action::PopupUp);
+    state.reduce(Action::PopupDown);
+    state.reduce(Action::PopupUp);
+    match &state.popup {
+        PopupState::OpenWith { input, cursor_idx, .. } => {
+            assert_eq!((input.as_str(), *cursor_idx), ("less", None));
+        }
+        other => panic!("expected the open-with popup, got {:?}", other),
+    }
+}
+
+#[test]
+fn edit_runs_editor_with_its_arguments_in_the_foreground() {
+    let dir = TempDir::new("edit");
+    dir.touch(&["notes.txt"]);
+    let file = dir.path().join("notes.txt").to_string_lossy().into_owned();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    let _env = ENV_LOCK.lock().unwrap();
+    // SAFETY: ENV_LOCK keeps the other tests here off the environment
+    unsafe { std::env::set_var("EDITOR", "code --wait") };
+    state.reduce(Action::EditFile);
+    assert_eq!(
+        state.run_request,
+        Some(ExternalCommand {
+            program: "code".to_string(),
+            args: vec!["--wait".to_string(), file],
+            background: false,
+        })
+    );
+}
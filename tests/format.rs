@@ -0,0 +1,122 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use file_management::app::{
+    FsEntry, format_permissions, format_relative_time, format_size, icon_and_color, size_color,
+};
+use ratatui::style::Color;
+
+#[test]
+fn format_size_bytes() {
+    assert_eq!(format_size(0), "0B");
+    assert_eq!(format_size(1023), "1023B");
+}
+
+#[test]
+fn format_size_kilobytes() {
+    assert_eq!(format_size(1024), "1.0K");
+    assert_eq!(format_size(4096), "4.0K");
+    assert_eq!(format_size(1536), "1.5K");
+}
+
+#[test]
+fn format_size_megabytes() {
+    assert_eq!(format_size(1024 * 1024), "1.0M");
+    assert_eq!(format_size(1258291), "1.2M");
+}
+
+#[test]
+fn format_size_gigabytes() {
+    assert_eq!(format_size(1024 * 1024 * 1024), "1.0G");
+    assert_eq!(format_size(3650722201), "3.4G");
+}
+
+#[test]
+fn size_color_runs_green_to_red_on_a_log_scale() {
+    let gb = 1 << 30;
+    assert_eq!(size_color(0, gb), Color::Rgb(0, 255, 0));
+    assert_eq!(size_color(gb, gb), Color::Rgb(255, 0, 0));
+    // Halfway in orders of magnitude is about yellow
+    let Color::Rgb(red, green, 0) = size_color(1 << 15, gb) else { panic!() };
+    assert!(red == 255 && green > 240, "{} {}", red, green);
+    assert_eq!(size_color(0, 0), Color::Rgb(0, 255, 0));
+}
+
+#[test]
+fn format_relative_time_recent_ranges() {
+    let ago = |secs| SystemTime::now() - Duration::from_secs(secs);
+    assert_eq!(format_relative_time(ago(5)), "5s ago");
+    assert_eq!(format_relative_time(ago(5 * 60)), "5m ago");
+    assert_eq!(format_relative_time(ago(2 * 3600)), "2h ago");
+    assert_eq!(format_relative_time(ago(3 * 86_400)), "3d ago");
+}
+
+#[test]
+fn format_relative_time_future_is_just_now() {
+    let future = SystemTime::now() + Duration::from_secs(3600);
+    assert_eq!(format_relative_time(future), "0s ago");
+}
+
+#[test]
+fn format_relative_time_older_shows_date() {
+    let recent = format_relative_time(SystemTime::now() - Duration::from_secs(60 * 86_400));
+    let (month, day) = recent.split_once(' ').unwrap();
+    assert_eq!(month.len(), 3);
+    assert!(day.parse::<u32>().is_ok_and(|d| (1..=31).contains(&d)));
+
+    // 2023-11-14T22:13:20Z
+    let old = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    assert_eq!(format_relative_time(old), "Nov 2023");
+}
+
+#[test]
+fn format_permissions_common_modes() {
+    assert_eq!(format_permissions(0o755, true), "drwxr-xr-x");
+    assert_eq!(format_permissions(0o755, false), "-rwxr-xr-x");
+    assert_eq!(format_permissions(0o644, false), "-rw-r--r--");
+    assert_eq!(format_permissions(0o000, false), "----------");
+}
+
+#[test]
+fn format_permissions_special_bits() {
+    assert_eq!(format_permissions(0o4755, false), "-rwsr-xr-x");
+    assert_eq!(format_permissions(0o2750, true), "drwxr-s---");
+    assert_eq!(format_permissions(0o1777, true), "drwxrwxrwt");
+    // Special bit without the execute bit under it
+    assert_eq!(format_permissions(0o4644, false), "-rwSr--r--");
+    assert_eq!(format_permissions(0o1666, true), "drw-rw-rwT");
+}
+
+fn entry(name: &str, is_dir: bool, permissions: &str) -> FsEntry {
+    FsEntry {
+        path: PathBuf::from(name),
+        name: name.to_string(),
+        is_dir,
+        size: 0,
+        modified: None,
+        permissions: permissions.to_string(),
+        symlink_target: None,
+        uid: None,
+        gid: None,
+    }
+}
+
+#[test]
+fn icons_follow_type_then_extension() {
+    let color = |e: FsEntry| icon_and_color(&e).1;
+    assert_eq!(color(entry("src", true, "drwxr-xr-x")), Color::Blue);
+    assert_eq!(color(entry("build.sh", false, "-rwxr-xr-x")), Color::Green);
+    assert_eq!(color(entry("setuid", false, "-rwsr--r--")), Color::Green);
+    assert_eq!(color(entry("Photo.JPG", false, "-rw-r--r--")), Color::Magenta);
+    assert_eq!(color(entry("notes", false, "-rw-r--r--")), Color::White);
+    assert_eq!(color(entry(".rs", false, "-rw-r--r--")), Color::White);
+    // A capital S/T means the bit is set without execute
+    assert_eq!(color(entry("data", false, "-rwSr--r-T")), Color::White);
+
+    let mut link = entry("lib.rs", false, "-rw-r--r--");
+    assert_ne!(icon_and_color(&link).0, icon_and_color(&entry("notes", false, "")).0);
+    link.symlink_target = Some(PathBuf::from("elsewhere"));
+    assert_eq!(icon_and_color(&link).1, Color::Cyan);
+}
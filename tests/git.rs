@@ -0,0 +1,67 @@
+mod common;
+
+use std::{fs, process::Command, time::Duration};
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, Reducer},
+    git::{self, GitState},
+};
+
+#[test]
+fn porcelain_is_mapped_to_entry_names() {
+    let output = " M src/app.rs\0?? src/new.rs\0A  src/ui/mod.rs\0?? src/ui/x.rs\0\
+                  R  src/old.rs\0src/older.rs\0 M README.md\0";
+    let states = git::parse_porcelain(output, "src/");
+    assert_eq!(states.get("app.rs"), Some(&GitState::Modified));
+    assert_eq!(states.get("new.rs"), Some(&GitState::Untracked));
+    assert_eq!(states.get("old.rs"), Some(&GitState::Renamed));
+    // A directory with mixed changes is just modified
+    assert_eq!(states.get("ui"), Some(&GitState::Modified));
+    // Outside `src/`, and the rename's old path isn't a record of its own
+    assert_eq!(states.len(), 4);
+
+    let states = git::parse_porcelain("?? build/\0UU merge.txt\0", "");
+    assert_eq!(states.get("build"), Some(&GitState::Untracked));
+    assert_eq!(states.get("merge.txt"), Some(&GitState::Conflicted));
+}
+
+#[test]
+fn status_is_queried_in_the_background() {
+    let dir = TempDir::new("git-status");
+    let git = |args: &[&str]| {
+        let ok = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@t", "-C"])
+            .arg(dir.path())
+            .args(args)
+            .output()
+            .is_ok_and(|out| out.status.success());
+        assert!(ok, "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    dir.touch(&["tracked.txt"]);
+    git(&["add", "."]);
+    git(&["commit", "-qm", "init"]);
+    fs::write(dir.path().join("tracked.txt"), "changed").unwrap();
+    dir.touch(&["new.txt"]);
+
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.refresh_git_status();
+    loop {
+        let action = state.action_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        let done = matches!(action, Action::GitStatusReady { .. });
+        state.reduce(action);
+        if done {
+            break;
+        }
+    }
+    let status = state.git_status.as_ref().unwrap();
+    assert_eq!(status.get("tracked.txt"), Some(&GitState::Modified));
+    assert_eq!(status.get("new.txt"), Some(&GitState::Untracked));
+}
+
+#[test]
+fn missing_directories_have_no_status() {
+    let dir = TempDir::new("git-none");
+    assert!(git::status(&dir.path().join("missing")).is_none());
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::{fs, sync::atomic::AtomicBool, time::Duration};
+
+use common::{TempDir, type_popup};
+use file_management::{
+    app::{Action, AppState, PopupState, PreviewContent, Reducer},
+    grep::{self, Matcher},
+};
+
+fn tree(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+    fs::create_dir_all(dir.path().join("src/deep")).unwrap();
+    fs::create_dir_all(dir.path().join(".git")).unwrap();
+    fs::write(dir.path().join("notes.txt"), "nothing here\nTODO: write more\n").unwrap();
+    fs::write(dir.path().join("src/deep/lib.rs"), "fn a() {}\n// todo later\nfn b() {}\n").unwrap();
+    fs::write(dir.path().join(".git/config"), "todo in git\n").unwrap();
+    fs::write(dir.path().join("blob.bin"), b"todo\0\0\0binary").unwrap();
+    dir
+}
+
+#[test]
+fn search_skips_binaries_and_git() {
+    let dir = tree("grep-search");
+    let mut found = Vec::new();
+    let matcher = Matcher::new("todo", false).unwrap();
+    let truncated = grep::search(dir.path(), &matcher, &AtomicBool::new(false), |m| {
+        found.extend(m)
+    });
+    assert!(!truncated);
+    let hits: Vec<String> =
+        found.iter().map(|m| format!("{}:{}: {}", m.path.display(), m.line, m.text)).collect();
+    assert_eq!(hits, ["notes.txt:2: TODO: write more", "src/deep/lib.rs:2: // todo later"]);
+
+    let matcher = Matcher::new(r"^fn \w\(", true).unwrap();
+    let mut lines = Vec::new();
+    grep::search(dir.path(), &matcher, &AtomicBool::new(false), |m| {
+        lines.extend(m.into_iter().map(|m| m.line))
+    });
+    assert_eq!(lines, [1, 3]);
+    assert!(Matcher::new("(", true).is_err());
+}
+
+fn submit_and_wait(state: &mut AppState) {
+    state.reduce(Action::PopupSubmit);
+    loop {
+        let action = state.action_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let done = matches!(action, Action::GrepDone { .. });
+        state.reduce(action);
+        if done {
+            break;
+        }
+    }
+}
+
+#[test]
+fn picking_a_result_reveals_the_file_at_the_line() {
+    let dir = tree("grep-pick");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::Grep);
+    type_popup(&mut state, "later");
+    submit_and_wait(&mut state);
+    let PopupState::Grep { results, .. } = &state.popup else {
+        panic!("{:?}", state.popup);
+    };
+    assert_eq!(results.len(), 1);
+
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(state.cwd, dir.path().join("src/deep"));
+    assert_eq!(state.current_entry().unwrap().name, "lib.rs");
+
+    // What `run_app` does next: load the preview, which opens at the match
+    state.reduce(Action::PreviewReady(PreviewContent::Text {
+        title: "lib.rs".to_string(),
+        content: "fn a() {}\n// todo later\nfn b() {}\n".to_string(),
+        encoding: None,
+    }));
+    assert_eq!(state.preview_scroll, 1);
+    assert_eq!(state.preview_search.as_ref().unwrap().matches, [1]);
+}
+
+#[test]
+fn the_jump_is_dropped_when_its_preview_never_comes() {
+    let dir = tree("grep-stale-jump");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let pick = |state: &mut AppState| {
+        state.reduce(Action::Grep);
+        type_popup(state, "later");
+        submit_and_wait(state);
+        state.reduce(Action::PopupSubmit);
+    };
+    let text = || {
+        Action::PreviewReady(PreviewContent::Text {
+            title: "notes.txt".to_string(),
+            content: "a\nb\nc\n".to_string(),
+            encoding: None,
+        })
+    };
+
+    // The file couldn't be previewed
+    pick(&mut state);
+    let path = dir.path().join("src/deep/lib.rs");
+    state.reduce(Action::RequestPreview(path.clone()));
+    state.reduce(Action::PreviewError { path, error: "denied".to_string() });
+    assert!(state.preview_jump.is_none());
+
+    // Moved on to another file before the preview loaded
+    pick(&mut state);
+    state.reduce(Action::RequestPreview(dir.path().join("notes.txt")));
+    state.reduce(text());
+    assert_eq!(state.preview_scroll, 0);
+    assert!(state.preview_search.is_none());
+}
+
+#[test]
+fn a_bad_regex_is_shown_in_the_popup() {
+    let dir = tree("grep-bad");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::Grep);
+    state.reduce(Action::PopupToggle);
+    state.reduce(Action::PopupInput('['));
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::Grep { error: Some(_), running: false, .. }));
+}
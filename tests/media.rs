@@ -0,0 +1,50 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use file_management::{
+    app::{DefaultPreviewLoader, PreviewContent, PreviewLoader},
+    media,
+};
+
+#[test]
+fn ffprobe_output_becomes_fields() {
+    let json = r#"{
+        "streams": [
+            {"codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080},
+            {"codec_type": "audio", "codec_name": "aac", "sample_rate": "48000", "channels": 2}
+        ],
+        "format": {
+            "format_name": "mov,mp4",
+            "format_long_name": "QuickTime / MOV",
+            "duration": "3725.40",
+            "bit_rate": "5000000",
+            "tags": {"title": "Holiday", "ALBUM_ARTIST": "Someone"}
+        }
+    }"#;
+    let fields = media::parse_ffprobe(json).unwrap();
+    let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    assert_eq!(get("Container"), Some("QuickTime / MOV"));
+    assert_eq!(get("Duration"), Some("1:02:05"));
+    assert_eq!(get("Bitrate"), Some("5000 kb/s"));
+    assert_eq!(get("Video"), Some("h264, 1920x1080"));
+    assert_eq!(get("Audio"), Some("aac, 48000 Hz, 2 ch"));
+    assert_eq!(get("Title"), Some("Holiday"));
+    assert_eq!(get("Album artist"), Some("Someone"));
+
+    assert!(media::parse_ffprobe("{}").is_none());
+}
+
+#[test]
+fn unreadable_media_falls_back_to_binary() {
+    let dir = TempDir::new("media-junk");
+    let path = dir.path().join("broken.mp3");
+    fs::write(&path, [0u8, 1, 2, 3, 0xff, 0xfb]).unwrap();
+
+    assert!(media::is_media(&path));
+    assert!(matches!(
+        DefaultPreviewLoader::default().load(path).unwrap(),
+        PreviewContent::Binary { size: 6, .. }
+    ));
+}
@@ -0,0 +1,342 @@
+mod common;
+
+use common::{TempDir, type_popup};
+use file_management::app::{Action, AppState, PopupState, Reducer, SPLIT_MIN, expand_tilde, ui};
+use ratatui::{Terminal, backend::TestBackend};
+
+fn state_with_files(dir: &TempDir, names: &[&str]) -> AppState {
+    dir.touch(names);
+    AppState::new(dir.path().to_path_buf()).unwrap()
+}
+
+#[test]
+fn cursor_clamps_at_boundaries_by_default() {
+    let dir = TempDir::new("clamp");
+    let mut state = state_with_files(&dir, &["a", "b", "c"]);
+
+    state.cursor = 0; // `..`
+    state.reduce(Action::CursorMoveUp);
+    assert_eq!(state.cursor, 0);
+
+    state.cursor = 3;
+    state.reduce(Action::CursorMoveDown);
+    assert_eq!(state.cursor, 3);
+}
+
+#[test]
+fn cursor_wraps_at_boundaries_when_enabled() {
+    let dir = TempDir::new("wrap");
+    let mut state = state_with_files(&dir, &["a", "b", "c"]);
+    state.wrap_navigation = true;
+    state.cursor = 0;
+
+    state.reduce(Action::CursorMoveUp);
+    assert_eq!(state.cursor, 3);
+
+    state.reduce(Action::CursorMoveDown);
+    assert_eq!(state.cursor, 0);
+}
+
+#[test]
+fn wrapping_an_empty_list_keeps_cursor_at_zero() {
+    let dir = TempDir::new("wrap-empty");
+    let mut state = state_with_files(&dir, &[]);
+    state.wrap_navigation = true;
+
+    state.reduce(Action::CursorMoveUp);
+    state.reduce(Action::CursorMoveDown);
+    assert_eq!(state.cursor, 0);
+}
+
+#[test]
+fn deleting_moves_the_cursor_to_the_next_file() {
+    let dir = TempDir::new("delete-cursor");
+    let mut state = state_with_files(&dir, &["a", "b", "c", "d", "e"]);
+    state.cursor = 3; // "c"
+
+    state.reduce(Action::Delete);
+    assert_eq!(state.entries.len(), 5);
+    assert_eq!(state.current_entry().unwrap().name, "d");
+}
+
+#[test]
+fn reload_keeps_the_cursor_on_the_same_file() {
+    let dir = TempDir::new("reload-cursor");
+    let mut state = state_with_files(&dir, &["b", "c"]);
+    state.cursor = 2;
+
+    dir.touch(&["a"]);
+    state.reduce(Action::ToggleSortReverse);
+    assert_eq!(state.current_entry().unwrap().name, "c");
+}
+
+#[test]
+fn parent_entry_goes_up_and_is_never_operated_on() {
+    let dir = TempDir::new("parent-entry");
+    std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+    dir.touch(&["sub/a"]);
+    let mut state = AppState::new(dir.path().join("sub")).unwrap();
+    assert_eq!(state.entries[0].name, "..");
+    assert_eq!(state.current_entry().unwrap().name, "a");
+
+    state.reduce(Action::SelectAll);
+    assert_eq!(state.selected.len(), 1);
+    state.selected.clear();
+
+    state.reduce(Action::CursorTop);
+    state.reduce(Action::ToggleSelect);
+    state.reduce(Action::Delete);
+    assert!(state.selected.is_empty());
+    assert!(dir.path().join("sub").exists());
+
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.cwd, dir.path());
+}
+
+#[test]
+fn the_root_has_no_parent_entry() {
+    let state = AppState::new(std::path::PathBuf::from("/")).unwrap();
+    assert!(state.entries.iter().all(|e| e.name != ".."));
+}
+
+#[test]
+fn go_to_path_jumps_or_explains_why_not() {
+    let dir = TempDir::new("go-to-path");
+    std::fs::create_dir_all(dir.path().join("deep/er")).unwrap();
+    let mut state = state_with_files(&dir, &["file.txt"]);
+    let go = |state: &mut AppState, path: &str| {
+        state.reduce(Action::GoToPath);
+        type_popup(state, path);
+        state.reduce(Action::PopupSubmit);
+    };
+
+    go(&mut state, "file.txt");
+    match &state.popup {
+        PopupState::GoToPath { error: Some(e), .. } => assert_eq!(e, "file.txt: Not a directory"),
+        other => panic!("{:?}", other),
+    }
+    state.reduce(Action::PopupCancel);
+
+    let target = dir.path().join("deep/er");
+    go(&mut state, &target.to_string_lossy());
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(state.cwd, std::fs::canonicalize(&target).unwrap());
+}
+
+#[test]
+fn tilde_expands_to_home_only_on_its_own() {
+    let home = expand_tilde("~").unwrap();
+    assert!(home.is_absolute());
+    assert_eq!(expand_tilde("~/notes").unwrap(), home.join("notes"));
+    assert_eq!(expand_tilde("~user/x").unwrap(), std::path::Path::new("~user/x"));
+    assert_eq!(expand_tilde("/tmp").unwrap(), std::path::Path::new("/tmp"));
+}
+
+#[test]
+fn deleting_a_big_tree_asks_first() {
+    let dir = TempDir::new("delete-guard");
+    let names: Vec<String> = (0..60).map(|i| format!("big/f{}", i)).collect();
+    std::fs::create_dir_all(dir.path().join("big")).unwrap();
+    std::fs::create_dir_all(dir.path().join("empty")).unwrap();
+    dir.touch(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::Delete); // "big"
+    assert!(matches!(state.popup, PopupState::ConfirmDelete { .. }));
+    state.reduce(Action::PopupCancel);
+    assert!(dir.path().join("big/f0").exists());
+
+    state.reduce(Action::Delete);
+    state.reduce(Action::PopupSubmit);
+    assert!(!dir.path().join("big").exists());
+
+    // An empty folder just goes
+    assert_eq!(state.current_entry().unwrap().name, "empty");
+    state.reduce(Action::Delete);
+    assert!(matches!(state.popup, PopupState::None));
+    assert!(!dir.path().join("empty").exists());
+}
+
+#[test]
+fn flat_view_lists_the_tree_and_entering_a_folder_leaves_it() {
+    let dir = TempDir::new("flatten");
+    std::fs::create_dir_all(dir.path().join("src/ui")).unwrap();
+    dir.touch(&["src/ui/list.rs", "src/main.rs", "README"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::ToggleFlatten);
+    let sep = std::path::MAIN_SEPARATOR.to_string();
+    let names: Vec<String> = state.entries.iter().map(|e| e.name.replace(&sep, "/")).collect();
+    assert_eq!(names, ["..", "src", "src/ui", "src/ui/list.rs", "src/main.rs", "README"]);
+    assert_eq!(state.entries[3].path, dir.path().join("src/ui/list.rs"));
+
+    assert!(state.select_entry_by_name(&format!("src{}ui", sep)));
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.cwd, dir.path().join("src/ui"));
+    assert!(!state.flatten);
+    assert_eq!(state.entries.last().unwrap().name, "list.rs");
+}
+
+#[test]
+fn per_dir_view_restores_each_directorys_settings() {
+    let dir = TempDir::new("per-dir-view");
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.per_dir_view = true;
+
+    state.reduce(Action::ToggleHidden);
+    state.reduce(Action::CycleSort);
+    let root_view = state.view();
+    assert!(state.select_entry_by_name("sub"));
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.view(), root_view); // a new directory starts from the last one's
+
+    state.reduce(Action::ToggleHidden);
+    state.reduce(Action::GoBack);
+    assert_eq!(state.view(), root_view);
+    state.reduce(Action::HistoryBack);
+    assert!(!state.show_hidden);
+
+    // Off, the settings stay put across directories
+    state.per_dir_view = false;
+    state.reduce(Action::GoBack);
+    assert!(!state.show_hidden);
+}
+
+#[test]
+fn split_resizes_within_bounds() {
+    let dir = TempDir::new("split");
+    let mut state = state_with_files(&dir, &["a"]);
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+
+    for _ in 0..3 {
+        state.reduce(Action::ResizeSplit { grow: true });
+    }
+    assert_eq!(state.split_ratio, 65);
+    terminal.draw(|f| ui(f, &mut state)).unwrap();
+    assert_eq!(state.list_area.width, 65);
+
+    for _ in 0..20 {
+        state.reduce(Action::ResizeSplit { grow: false });
+    }
+    assert_eq!(state.split_ratio, SPLIT_MIN);
+}
+
+#[test]
+fn parent_column_follows_navigation() {
+    let dir = TempDir::new("miller");
+    std::fs::create_dir_all(dir.path().join("sub/inner")).unwrap();
+    dir.touch(&["sub/notes.txt", "top.txt"]);
+    let mut state = AppState::new(dir.path().join("sub")).unwrap();
+    assert!(state.parent_entries.is_empty());
+
+    state.reduce(Action::ToggleMiller);
+    let names = |state: &AppState| -> Vec<String> {
+        state.parent_entries.iter().map(|e| e.name.clone()).collect()
+    };
+    assert_eq!(names(&state), ["sub", "top.txt"]);
+
+    assert!(state.select_entry_by_name("inner"));
+    state.reduce(Action::EnterDir);
+    assert_eq!(names(&state), ["inner", "notes.txt"]);
+
+    state.reduce(Action::ToggleMiller);
+    assert!(state.parent_entries.is_empty());
+}
+
+#[test]
+fn recent_dirs_jump_back_and_drop_dead_ones() {
+    let dir = TempDir::new("recent-dirs");
+    let root = dir.path().to_path_buf();
+    for sub in ["a", "b", "gone"] {
+        std::fs::create_dir(root.join(sub)).unwrap();
+    }
+    let mut state = AppState::new(root.clone()).unwrap();
+    state.reduce(Action::OpenRecentDirs);
+    assert!(matches!(state.popup, PopupState::None)); // nowhere else yet
+
+    for sub in ["a", "..", "gone", "..", "b"] {
+        state.select_entry_by_name(sub);
+        state.reduce(Action::EnterDir);
+    }
+    // Newest first, each once
+    let expected = [root.join("b"), root.clone(), root.join("gone"), root.join("a")];
+    assert_eq!(state.recent_dirs, expected);
+
+    // Starts on the previous directory
+    state.reduce(Action::OpenRecentDirs);
+    state.reduce(Action::PopupSubmit);
+    assert_eq!(state.cwd, root);
+
+    std::fs::remove_dir(root.join("gone")).unwrap();
+    state.reduce(Action::OpenRecentDirs);
+    state.reduce(Action::PopupDown);
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::RecentDirs { cursor_idx: 2 }));
+    assert!(state.status.as_deref().unwrap().ends_with("gone no longer exists"));
+    assert!(!state.recent_dirs.contains(&root.join("gone")));
+    state.reduce(Action::PopupSubmit);
+    assert_eq!(state.cwd, root.join("a"));
+}
+
+#[test]
+fn selection_follows_across_enter_dir() {
+    let dir = TempDir::new("select-across");
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    dir.touch(&["top.txt", "sub/inner.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.select_entry_by_name("top.txt");
+    state.reduce(Action::ToggleSelect);
+    state.select_entry_by_name("sub");
+    state.reduce(Action::EnterDir);
+    state.select_entry_by_name("inner.txt");
+    state.reduce(Action::ToggleSelect);
+    assert_eq!(state.selected.len(), 2);
+    let selected: Vec<_> = state.selected.iter().cloned().collect();
+    assert_eq!(state.outside_listing(&selected), 1);
+
+    // Deleting what's out of sight always asks, and says so
+    state.reduce(Action::Delete);
+    let PopupState::ConfirmDelete { paths } = &state.popup else {
+        panic!("expected a delete confirmation, got {:?}", state.popup);
+    };
+    assert_eq!(paths.len(), 2);
+    state.reduce(Action::PopupCancel);
+    assert!(dir.path().join("top.txt").exists());
+}
+
+#[test]
+fn selection_clears_on_nav_when_enabled() {
+    let dir = TempDir::new("select-clear");
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    dir.touch(&["top.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.clear_selection_on_nav = true;
+
+    state.select_entry_by_name("top.txt");
+    state.reduce(Action::ToggleSelect);
+    state.select_entry_by_name("sub");
+    state.reduce(Action::EnterDir);
+    assert!(state.selected.is_empty());
+}
+
+#[test]
+fn clicks_only_hit_rows_with_an_entry() {
+    let dir = TempDir::new("click");
+    let mut state = state_with_files(&dir, &["a", "b"]);
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+    terminal.draw(|f| ui(f, &mut state)).unwrap();
+    let area = state.list_area;
+    let click = |state: &mut AppState, row| {
+        state.reduce(Action::MouseClick { column: area.x + 2, row });
+        state.clicked_entry
+    };
+
+    assert_eq!(click(&mut state, area.y + 3), Some(1)); // border, header, `..`, a
+    assert_eq!(state.cursor, 1);
+    assert_eq!(click(&mut state, area.y + 1), None); // the header
+    assert_eq!(click(&mut state, area.y + 6), None); // empty, below the list
+    assert_eq!(click(&mut state, area.bottom() - 1), None); // the border
+    assert_eq!(state.cursor, 1);
+}
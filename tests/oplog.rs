@@ -0,0 +1,55 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, Reducer},
+    oplog,
+};
+
+#[test]
+fn lines_are_tab_separated() {
+    let time = UNIX_EPOCH + Duration::from_secs(86_400 + 3661);
+    let ok: Result<(), String> = Ok(());
+    assert_eq!(
+        oplog::format_line(time, "move", Path::new("/a/x"), Some(Path::new("/b/x")), &ok),
+        "1970-01-02 01:01:01 UTC\tmove\t/a/x\t/b/x\tok"
+    );
+    let failed: Result<(), &str> = Err("permission denied");
+    assert_eq!(
+        oplog::format_line(time, "delete", Path::new("/a/y"), None, &failed),
+        "1970-01-02 01:01:01 UTC\tdelete\t/a/y\t-\terror: permission denied"
+    );
+}
+
+#[test]
+fn operations_append_to_the_log_when_enabled() {
+    let dir = TempDir::new("ops-log");
+    dir.touch(&["a.txt", "b.txt"]);
+    let log = dir.path().join("state/ops.log");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::CursorBottom); // b.txt
+    state.reduce(Action::Delete);
+    assert!(!log.exists()); // off by default
+
+    state.ops_log = Some(log.clone());
+    state.reduce(Action::CursorBottom); // a.txt
+    state.reduce(Action::Duplicate);
+    state.reduce(Action::Delete);
+    let text = fs::read_to_string(&log).unwrap();
+    let lines: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').skip(1).collect()).collect();
+    let (a, copy) = (dir.path().join("a.txt"), dir.path().join("a (copy).txt"));
+    assert_eq!(
+        lines,
+        [
+            vec!["copy", &*a.to_string_lossy(), &*copy.to_string_lossy(), "ok"],
+            vec!["delete", &*copy.to_string_lossy(), "-", "ok"],
+        ]
+    );
+}
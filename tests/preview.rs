@@ -0,0 +1,420 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use common::TempDir;
+use file_management::{
+    app::{
+        Action, AppState, DefaultPreviewLoader, PreviewContent, PreviewLoader, PreviewState,
+        Reducer, TREE_HUGE_DIR, TREE_MAX_LINES, parse_delimited, ui,
+    },
+    preview_cache::{self, PreviewCache},
+    preview_command::{self, CommandPreviewLoader},
+    thumbnail::ImageArt,
+};
+use ratatui::{Terminal, backend::TestBackend};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+#[test]
+fn latin1_file_previews_as_text() {
+    match DefaultPreviewLoader::default().load(fixture("latin1.txt")).unwrap() {
+        PreviewContent::Text {
+            content, encoding, ..
+        } => {
+            assert!(content.starts_with("Café crème brûlée"), "{:?}", content);
+            assert_eq!(encoding.as_deref(), Some("windows-1252"));
+        }
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn utf8_file_has_no_encoding_note() {
+    let dir = TempDir::new("preview-utf8");
+    let path = dir.path().join("plain.txt");
+    fs::write(&path, "héllo\n").unwrap();
+
+    match DefaultPreviewLoader::default().load(path).unwrap() {
+        PreviewContent::Text { encoding, .. } => assert_eq!(encoding, None),
+        other => panic!("expected text, got {:?}", other),
+    }
+}
+
+#[test]
+fn binary_file_stays_binary() {
+    let dir = TempDir::new("preview-binary");
+    let path = dir.path().join("blob.bin");
+    fs::write(&path, [0x7f, b'E', b'L', b'F', 0, 0, 0xff, 0xfe, 0x01, 0x02]).unwrap();
+
+    assert!(matches!(
+        DefaultPreviewLoader::default().load(path).unwrap(),
+        PreviewContent::Binary { size: 10, .. }
+    ));
+}
+
+#[test]
+fn valid_utf8_with_nuls_is_binary() {
+    let dir = TempDir::new("preview-nul");
+    let path = dir.path().join("data.dat");
+    let mut bytes = b"header\0".repeat(100);
+    bytes.extend_from_slice("plain text after".as_bytes());
+    fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        DefaultPreviewLoader::default().load(path).unwrap(),
+        PreviewContent::Binary { .. }
+    ));
+
+    // UTF-16 is full of NULs but still text
+    let path = dir.path().join("utf16.txt");
+    let mut bytes = vec![0xff, 0xfe];
+    bytes.extend("hello".encode_utf16().flat_map(u16::to_le_bytes));
+    fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        DefaultPreviewLoader::default().load(path).unwrap(),
+        PreviewContent::Text { .. }
+    ));
+}
+
+#[test]
+fn zip_lists_its_entries() {
+    use std::io::Write;
+
+    let dir = TempDir::new("preview-zip");
+    let path = dir.path().join("bundle.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    zip.add_directory("docs/", options).unwrap();
+    zip.start_file("docs/readme.txt", options).unwrap();
+    zip.write_all(b"hello").unwrap();
+    zip.finish().unwrap();
+
+    match DefaultPreviewLoader::default().load(path).unwrap() {
+        PreviewContent::Archive {
+            entries, truncated, ..
+        } => {
+            assert!(!truncated);
+            let listed: Vec<_> =
+                entries.iter().map(|e| (e.path.as_str(), e.size, e.is_dir)).collect();
+            assert_eq!(listed, [("docs/", 0, true), ("docs/readme.txt", 5, false)]);
+        }
+        other => panic!("expected archive, got {:?}", other),
+    }
+}
+
+#[test]
+fn tar_gz_lists_its_entries() {
+    let dir = TempDir::new("preview-tgz");
+    let path = dir.path().join("bundle.tgz");
+    let gz = flate2::write::GzEncoder::new(
+        fs::File::create(&path).unwrap(),
+        flate2::Compression::default(),
+    );
+    let mut tar = tar::Builder::new(gz);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(3);
+    header.set_cksum();
+    tar.append_data(&mut header, "a.txt", &b"abc"[..]).unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    match DefaultPreviewLoader::default().load(path).unwrap() {
+        PreviewContent::Archive { entries, .. } => {
+            assert_eq!(entries.len(), 1);
+            assert_eq!((entries[0].path.as_str(), entries[0].size), ("a.txt", 3));
+        }
+        other => panic!("expected archive, got {:?}", other),
+    }
+}
+
+#[test]
+fn folder_tree_is_capped_and_skips_huge_folders() {
+    let dir = TempDir::new("preview-tree");
+    let big = dir.path().join("big");
+    fs::create_dir(&big).unwrap();
+    for i in 0..=TREE_HUGE_DIR {
+        fs::write(big.join(i.to_string()), b"").unwrap();
+    }
+    let tree = match DefaultPreviewLoader::default().load(dir.path().to_path_buf()).unwrap() {
+        PreviewContent::Text { content, .. } => content,
+        other => panic!("expected text, got {:?}", other),
+    };
+    assert_eq!(tree, format!("|-- big ({}+ items, not opened)\n", TREE_HUGE_DIR));
+
+    // Under the threshold it's opened, up to the line cap
+    fs::remove_file(big.join("0")).unwrap();
+    let tree = match DefaultPreviewLoader::default().load(dir.path().to_path_buf()).unwrap() {
+        PreviewContent::Text { content, .. } => content,
+        other => panic!("expected text, got {:?}", other),
+    };
+    let lines: Vec<&str> = tree.lines().collect();
+    assert_eq!(lines.len(), TREE_MAX_LINES + 1);
+    assert_eq!(lines[0], "|-- big");
+    assert_eq!(lines[TREE_MAX_LINES], format!("…and {} more", TREE_HUGE_DIR + 1 - TREE_MAX_LINES));
+}
+
+#[test]
+fn image_renders_as_letterboxed_text() {
+    let dir = TempDir::new("preview-image");
+    let path = dir.path().join("wide.png");
+    // White on the left half, black on the right
+    image::RgbImage::from_fn(40, 10, |x, _| image::Rgb([if x < 20 { 255 } else { 0 }; 3]))
+        .save(&path)
+        .unwrap();
+
+    let thumbnail = match DefaultPreviewLoader::default().load(path).unwrap() {
+        PreviewContent::Image { format, thumbnail, .. } => {
+            assert_eq!(format, "PNG");
+            thumbnail.expect("decoded")
+        }
+        other => panic!("expected image, got {:?}", other),
+    };
+    // 20x10 cells hold 20x20 square pixels; a 4:1 image fills 20x5 of them
+    let lines = thumbnail.render(20, 10, ImageArt::Mono);
+    let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    let art: Vec<&str> = text.iter().map(|l| l.as_str()).filter(|l| !l.is_empty()).collect();
+    assert_eq!(art.len(), 3); // 5 pixel rows, two to a cell
+    assert!(text[..3].iter().all(|l| l.is_empty()), "{:?}", text); // centered
+    assert_eq!(text.len(), 6);
+    assert!(art.iter().all(|l| l.starts_with("@@@@@") && l.ends_with("     ")));
+
+    let lines = thumbnail.render(20, 10, ImageArt::Color);
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[3].spans.len(), 21); // padding, then a half block per column
+    assert!(thumbnail.render(20, 10, ImageArt::Off).is_empty());
+}
+
+#[test]
+fn text_scroll_stops_at_the_last_line_wrapped_or_not() {
+    let dir = TempDir::new("preview-scroll");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::PreviewReady(PreviewContent::Text {
+        title: "long.txt".to_string(),
+        content: format!("{}\nshort\nlast", "x".repeat(500)),
+        encoding: None,
+    }));
+    state.reduce(Action::SwitchFocus);
+
+    state.reduce(Action::ScrollPreviewPageDown);
+    assert_eq!(state.preview_scroll, 2);
+    state.reduce(Action::TogglePreviewWrap);
+    assert!(state.preview_wrap);
+    state.reduce(Action::ScrollPreviewDown);
+    assert_eq!(state.preview_scroll, 2);
+}
+
+#[test]
+fn paging_follows_the_preview_height() {
+    let dir = TempDir::new("preview-page");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.reduce(Action::PreviewReady(PreviewContent::Text {
+        title: "long.txt".to_string(),
+        content: "line\n".repeat(200),
+        encoding: None,
+    }));
+    state.reduce(Action::SwitchFocus);
+    assert_eq!(state.preview_page(), 10); // not drawn yet
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+    terminal.draw(|f| ui(f, &mut state)).unwrap();
+    let page = state.preview_height as usize - 2;
+    assert!(page > 10);
+    state.reduce(Action::ScrollPreviewPageDown);
+    assert_eq!(state.preview_scroll, page);
+    state.reduce(Action::ScrollPreviewPageUp);
+    assert_eq!(state.preview_scroll, 0);
+
+    state.reduce(Action::ScrollPreviewHalfPageDown);
+    assert_eq!(state.preview_scroll, state.preview_height as usize / 2);
+    state.reduce(Action::ScrollPreviewHalfPageUp);
+    assert_eq!(state.preview_scroll, 0);
+
+    state.preview_page_step = 7;
+    state.reduce(Action::ScrollPreviewPageDown);
+    assert_eq!(state.preview_scroll, 7);
+
+    // Both clamp at the last line
+    for _ in 0..100 {
+        state.reduce(Action::ScrollPreviewHalfPageDown);
+    }
+    assert_eq!(state.preview_scroll, 199);
+}
+
+#[test]
+fn text_pans_sideways_up_to_the_longest_line() {
+    let dir = TempDir::new("preview-hscroll");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let text = |content: &str| {
+        Action::PreviewReady(PreviewContent::Text {
+            title: "wide.txt".to_string(),
+            content: content.to_string(),
+            encoding: None,
+        })
+    };
+    state.reduce(text("short\n0123456789"));
+    state.reduce(Action::SwitchFocus);
+
+    for _ in 0..5 {
+        state.reduce(Action::ScrollPreviewRight);
+    }
+    assert_eq!(state.preview_hscroll, 9); // the "9" stays in view
+    state.reduce(Action::ScrollPreviewLeft);
+    assert_eq!(state.preview_hscroll, 5);
+
+    // A new file starts back at the left edge
+    state.reduce(Action::RequestPreview(dir.path().join("other.txt")));
+    state.reduce(text("0123456789"));
+    assert_eq!(state.preview_hscroll, 0);
+
+    state.reduce(Action::TogglePreviewWrap);
+    state.reduce(Action::ScrollPreviewRight);
+    assert_eq!(state.preview_hscroll, 0);
+}
+
+#[test]
+fn preview_cache_drops_edited_files_and_the_least_recent() {
+    let dir = TempDir::new("preview-cache");
+    let loader = DefaultPreviewLoader::default();
+    let text = |content: Option<PreviewContent>| match content {
+        Some(PreviewContent::Text { content, .. }) => Some(content),
+        _ => None,
+    };
+    let mut cache = PreviewCache::default();
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, "first").unwrap();
+    cache.insert(&notes, loader.load(notes.clone()).unwrap());
+    assert_eq!(text(cache.get(&notes)).as_deref(), Some("first"));
+
+    // A different size gives the edit away even within one mtime tick
+    fs::write(&notes, "second draft").unwrap();
+    assert!(cache.get(&notes).is_none());
+    assert!(cache.is_empty());
+
+    for idx in 0..=preview_cache::MAX_ENTRIES {
+        if idx == preview_cache::MAX_ENTRIES {
+            cache.get(&dir.path().join("0.txt")); // now the most recent, so it's kept
+        }
+        let path = dir.path().join(format!("{}.txt", idx));
+        fs::write(&path, idx.to_string()).unwrap();
+        cache.insert(&path, loader.load(path.clone()).unwrap());
+    }
+    assert_eq!(cache.len(), preview_cache::MAX_ENTRIES);
+    assert!(cache.get(&dir.path().join("0.txt")).is_some());
+    assert!(cache.get(&dir.path().join("1.txt")).is_none());
+
+    // Folders aren't cached
+    cache.insert(dir.path(), PreviewContent::Binary { title: String::new(), size: 0 });
+    assert!(cache.get(dir.path()).is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn preview_commands_match_by_type_and_fall_back() {
+    let dir = TempDir::new("preview-command");
+    fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+    fs::write(dir.path().join("data.csv"), "a,b").unwrap();
+    fs::write(dir.path().join("page.html"), "<p>").unwrap();
+    let commands = [
+        ("txt", "printf '\\033[1m'; tr a-z A-Z <"), // file appended as the last argument
+        ("text/*", "echo 'unreachable' >&2; exit 3"),
+        ("text/html", "sleep 5; cat \"$1\""),
+    ];
+    let loader = CommandPreviewLoader {
+        commands: commands.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        timeout: Duration::from_millis(200),
+        fallback: DefaultPreviewLoader::default(),
+    };
+    let text = |name: &str| match loader.load(dir.path().join(name)).unwrap() {
+        PreviewContent::Text { content, .. } => content,
+        other => panic!("expected text, got {:?}", other),
+    };
+
+    assert_eq!(text("notes.txt"), "HELLO"); // color codes stripped
+    // A failing or slow command gets the built-in preview
+    assert!(matches!(
+        loader.load(dir.path().join("data.csv")).unwrap(),
+        PreviewContent::Table { .. }
+    ));
+    assert_eq!(text("page.html"), "<p>");
+
+    let err = preview_command::run("echo oops >&2; false", dir.path(), Duration::from_secs(1), 10);
+    assert_eq!(err.unwrap_err(), "oops");
+    let capped = preview_command::run("yes y #", dir.path(), Duration::from_secs(5), 10).unwrap();
+    assert_eq!(capped, "y\ny\ny\ny\ny\n");
+    // A background process holding stdout open doesn't hold up the preview
+    let started = Instant::now();
+    let left_open =
+        preview_command::run("echo hi; sleep 5 & #", dir.path(), Duration::from_millis(300), 10);
+    assert_eq!(left_open.unwrap(), "hi\n");
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(loader.command_for(dir.path()).is_none());
+    assert!(CommandPreviewLoader {
+        commands: HashMap::new(),
+        ..loader
+    }
+    .command_for(&dir.path().join("notes.txt"))
+    .is_none());
+}
+
+#[test]
+fn two_selected_files_diff_in_the_preview() {
+    let dir = TempDir::new("preview-diff");
+    fs::write(dir.path().join("a.conf"), "one\ntwo\nthree\n").unwrap();
+    fs::write(dir.path().join("b.conf"), "one\n2\nthree\n").unwrap();
+    fs::write(dir.path().join("c.bin"), b"\0\x01").unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let diff_of = |state: &mut AppState, names: &[&str]| {
+        state.selected = names.iter().map(|n| dir.path().join(n)).collect();
+        state.reduce(Action::Diff);
+        match &state.preview {
+            PreviewState::Ready(PreviewContent::Diff { title, lines }) => {
+                let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+                (title.clone(), text)
+            }
+            other => panic!("expected a diff, got {:?}", other),
+        }
+    };
+
+    let (title, lines) = diff_of(&mut state, &["b.conf", "a.conf"]);
+    assert_eq!(title, "a.conf vs b.conf");
+    assert_eq!(
+        lines,
+        ["--- a.conf", "+++ b.conf", "@@ -1,3 +1,3 @@", " one", "-two", "+2", " three"]
+    );
+    let (_, lines) = diff_of(&mut state, &["a.conf", "c.bin"]);
+    assert_eq!(lines, ["Binary files a.conf and c.bin differ"]);
+
+    state.selected.clear();
+    state.reduce(Action::Diff);
+    assert_eq!(state.status.as_deref(), Some("Select two files to compare"));
+}
+
+#[test]
+fn delimited_text_honours_quotes() {
+    let (rows, truncated) =
+        parse_delimited("name,note\r\n\"Smith, J\",\"says \"\"hi\"\"\nthere\"\n", ',', 10);
+    assert_eq!(rows, [vec!["name", "note"], vec!["Smith, J", "says \"hi\"\nthere"]]);
+    assert!(!truncated);
+
+    // No trailing newline; empty fields stay
+    let (rows, _) = parse_delimited("a\t\tc\nd", '\t', 10);
+    assert_eq!(rows, [vec!["a", "", "c"], vec!["d"]]);
+}
+
+#[test]
+fn delimited_text_stops_at_max_rows() {
+    let (rows, truncated) = parse_delimited("1\n2\n3\n", ',', 2);
+    assert_eq!(rows, [["1"], ["2"]]);
+    assert!(truncated);
+
+    // Exactly `max_rows` isn't truncated
+    let (rows, truncated) = parse_delimited("1\n2\n", ',', 2);
+    assert_eq!(rows.len(), 2);
+    assert!(!truncated);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use file_management::app::{Action, AppState, PopupState, Reducer};
+
+const REFUSED: &str = "Readonly mode: nothing can be changed";
+
+#[test]
+fn readonly_refuses_every_file_operation() {
+    let dir = TempDir::new("readonly");
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    dir.touch(&["a.txt", "b.txt"]);
+    let before = dir.names();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.readonly = true;
+
+    state.reduce(Action::CursorBottom); // b.txt
+    let actions = [
+        Action::Delete,
+        Action::Duplicate,
+        Action::Chmod,
+        Action::BulkRename,
+        Action::MoveTo,
+        Action::CompressSelection,
+        Action::EditFile,
+        Action::Undo,
+        Action::CopyToPane,
+        Action::MoveToPane,
+        Action::Chown,
+        Action::CreateSymlink,
+        Action::ExtractArchive,
+    ];
+    for action in actions {
+        state.status = None;
+        state.reduce(action.clone());
+        assert_eq!(state.status.as_deref(), Some(REFUSED), "{:?}", action);
+        assert!(matches!(state.popup, PopupState::None), "{:?}", action);
+    }
+
+    // Copying to the clipboard is fine; pasting it isn't
+    state.reduce(Action::Yank);
+    assert!(state.clipboard.is_some());
+    state.status = None;
+    state.reduce(Action::Paste);
+    assert_eq!(state.status.as_deref(), Some(REFUSED));
+
+    state.reduce(Action::StartCommand);
+    for c in "mkdir new".chars() {
+        state.reduce(Action::CommandInput(c));
+    }
+    state.reduce(Action::CommandSubmit);
+    assert_eq!(state.status.as_deref(), Some(REFUSED));
+
+    // Getting around still works
+    state.select_entry_by_name("sub");
+    state.reduce(Action::EnterDir);
+    assert_eq!(state.cwd, dir.path().join("sub"));
+    assert_eq!(dir.names(), before);
+}
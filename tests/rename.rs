@@ -0,0 +1,104 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{TempDir, type_popup};
+use file_management::{
+    app::{Action, AppState, PopupState, Reducer},
+    rename::{self, RenamePattern},
+};
+
+fn renamed(pattern: &str, names: &[&str]) -> Vec<String> {
+    let pattern = RenamePattern::parse(pattern).unwrap();
+    names.iter().enumerate().map(|(idx, name)| pattern.apply(name, idx + 1)).collect()
+}
+
+#[test]
+fn templates_fill_in_counter_name_and_extension() {
+    assert_eq!(renamed("img_{n:03}.jpg", &["a.jpg", "b.jpg"]), ["img_001.jpg", "img_002.jpg"]);
+    assert_eq!(renamed("{n}-{name}.{ext}", &["notes.tar.gz"]), ["1-notes.tar.gz"]);
+    assert_eq!(renamed("{name}_old", &[".env"]), [".env_old"]);
+    assert_eq!(renamed("{{{n}}}", &["x"]), ["{1}"]);
+    // Wider numbers than the padding aren't cut
+    assert_eq!(RenamePattern::parse("{n:2}").unwrap().apply("x", 123), "123");
+
+    for (bad, error) in [
+        ("{n", "unclosed '{'"),
+        ("a}", "unmatched '}' (write '}}' for a brace)"),
+        ("{size}", "unknown placeholder {size}"),
+        ("{n:x}", "bad counter width 'x'"),
+    ] {
+        assert_eq!(RenamePattern::parse(bad).unwrap_err(), error);
+    }
+}
+
+#[test]
+fn substitutions_use_regex_groups() {
+    assert_eq!(renamed("s/IMG_(\\d+)/photo-$1/", &["IMG_42.JPG"]), ["photo-42.JPG"]);
+    assert_eq!(renamed("s/a/o/", &["banana"]), ["bonana"]);
+    assert_eq!(renamed("s/a/o/g", &["banana"]), ["bonono"]);
+    assert_eq!(renamed("s/ /\\//", &["a b"]), ["a/b"]);
+    assert!(RenamePattern::parse("s/(/x/").is_err());
+    assert_eq!(RenamePattern::parse("s/a/b/x").unwrap_err(), "unknown flag 'x' (only g)");
+    assert_eq!(RenamePattern::parse("s/a").unwrap_err(), "expected s/find/replace/");
+}
+
+#[test]
+fn check_refuses_clashes_before_anything_moves() {
+    let dir = TempDir::new("rename-check");
+    dir.touch(&["a.txt", "b.txt", "keep.md"]);
+    let paths: Vec<PathBuf> = ["a.txt", "b.txt"].iter().map(|n| dir.path().join(n)).collect();
+    let plan = |pattern| rename::plan(&paths, &RenamePattern::parse(pattern).unwrap());
+
+    assert_eq!(
+        rename::check(&plan("same.txt")).unwrap_err(),
+        "more than one file would be named same.txt"
+    );
+    assert_eq!(rename::check(&plan("keep.md")).unwrap_err(), "keep.md already exists");
+    assert_eq!(
+        rename::check(&plan("s/a/x\\/y/")).unwrap_err(),
+        "a.txt -> x/y.txt: names can't contain '/'"
+    );
+    // b.txt is still there when a.txt would take its name
+    assert_eq!(rename::check(&plan("s/a/b/")).unwrap_err(), "b.txt already exists");
+    assert_eq!(
+        rename::check(&plan("{n}.txt")).unwrap(),
+        [(paths[0].clone(), dir.path().join("1.txt")), (paths[1].clone(), dir.path().join("2.txt"))]
+    );
+}
+
+#[test]
+fn bulk_rename_popup_renames_the_selection_and_undoes() {
+    let dir = TempDir::new("bulk-rename");
+    dir.touch(&["b.jpg", "a.jpg", "other.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.selected.insert(dir.path().join("a.jpg"));
+    state.selected.insert(dir.path().join("b.jpg"));
+
+    state.reduce(Action::BulkRename);
+    type_popup(&mut state, "new.txt");
+    match &state.popup {
+        PopupState::BulkRename { renames, error, .. } => {
+            assert_eq!(renames.len(), 2);
+            assert_eq!(error.as_deref(), Some("more than one file would be named new.txt"));
+        }
+        other => panic!("{:?}", other),
+    }
+    state.reduce(Action::PopupSubmit);
+    assert!(dir.path().join("a.jpg").exists()); // nothing moved
+
+    (0..7).for_each(|_| state.reduce(Action::PopupBackspace));
+    type_popup(&mut state, "trip_{n:02}.jpg");
+    if let PopupState::BulkRename { renames, .. } = &state.popup {
+        let expected = [("a.jpg", "trip_01.jpg"), ("b.jpg", "trip_02.jpg")];
+        assert_eq!(renames, &expected.map(|(a, b)| (a.to_string(), b.to_string())));
+    }
+    state.reduce(Action::PopupSubmit);
+    assert!(matches!(state.popup, PopupState::None));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    assert!(dir.path().join("trip_02.jpg").exists());
+    assert_eq!(state.current_entry().unwrap().name, "trip_01.jpg");
+
+    state.reduce(Action::Undo);
+    assert!(dir.path().join("a.jpg").exists() && dir.path().join("b.jpg").exists());
+}
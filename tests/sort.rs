@@ -0,0 +1,105 @@
+mod common;
+
+use std::{cmp::Ordering, collections::HashSet, fs};
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, Reducer, SortMode, natural_cmp, read_entries},
+    config,
+};
+
+fn sorted(names: &[&str]) -> Vec<String> {
+    let mut names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    names.sort_by(|a, b| natural_cmp(a, b));
+    names
+}
+
+#[test]
+fn digit_runs_compare_by_value() {
+    assert_eq!(sorted(&["img10", "img2", "img1"]), ["img1", "img2", "img10"]);
+    assert_eq!(
+        sorted(&["log-2024-10.txt", "log-2024-9.txt", "log-2023-12.txt"]),
+        ["log-2023-12.txt", "log-2024-9.txt", "log-2024-10.txt"]
+    );
+    // Longer than any integer type
+    assert_eq!(
+        natural_cmp("a99999999999999999999999", "a100000000000000000000000"),
+        Ordering::Less
+    );
+}
+
+#[test]
+fn letters_ignore_case() {
+    assert_eq!(sorted(&["banana", "Apple", "cherry"]), ["Apple", "banana", "cherry"]);
+    // Same name in different case still has a fixed order
+    assert_eq!(natural_cmp("File", "file"), Ordering::Less);
+}
+
+#[test]
+fn leading_zeros() {
+    assert_eq!(sorted(&["img010", "img9", "img02"]), ["img02", "img9", "img010"]);
+    assert_eq!(sorted(&["01", "1", "001"]), ["1", "01", "001"]);
+    assert_eq!(natural_cmp("a01b", "a1c"), Ordering::Less);
+}
+
+fn listed(dir: &TempDir, reversed: bool, dirs_first: bool) -> Vec<String> {
+    read_entries(dir.path(), false, SortMode::Name, reversed, dirs_first, &HashSet::new())
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect()
+}
+
+#[test]
+fn directories_go_first_unless_mixed_in() {
+    let dir = TempDir::new("dirs-first");
+    fs::create_dir(dir.path().join("b_dir")).unwrap();
+    fs::create_dir(dir.path().join("d_dir")).unwrap();
+    dir.touch(&["a.txt", "c.txt"]);
+
+    assert_eq!(listed(&dir, false, true), ["..", "b_dir", "d_dir", "a.txt", "c.txt"]);
+    assert_eq!(listed(&dir, true, true), ["..", "d_dir", "b_dir", "c.txt", "a.txt"]);
+    assert_eq!(listed(&dir, false, false), ["..", "a.txt", "b_dir", "c.txt", "d_dir"]);
+    // `..` stays on top either way
+    assert_eq!(listed(&dir, true, false), ["..", "d_dir", "c.txt", "b_dir", "a.txt"]);
+}
+
+#[test]
+fn pinned_entries_go_first_even_before_directories() {
+    let dir = TempDir::new("pinned");
+    fs::create_dir(dir.path().join("b_dir")).unwrap();
+    dir.touch(&["a.txt", "z.txt"]);
+    let pinned = HashSet::from([dir.path().join("z.txt")]);
+    let listed = |reversed| -> Vec<String> {
+        read_entries(dir.path(), false, SortMode::Name, reversed, true, &pinned)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    };
+    assert_eq!(listed(false), ["..", "z.txt", "b_dir", "a.txt"]);
+    assert_eq!(listed(true), ["..", "z.txt", "b_dir", "a.txt"]);
+}
+
+#[test]
+fn toggling_a_pin_resorts_and_is_saved() {
+    let dir = TempDir::new("pin-toggle");
+    let config_home = TempDir::new("pin-toggle-config");
+    // SAFETY: no other test in this crate reads the environment
+    unsafe { std::env::set_var("XDG_CONFIG_HOME", config_home.path()) };
+    dir.touch(&["a.txt", "b.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let names = |state: &AppState| -> Vec<String> {
+        state.entries.iter().map(|e| e.name.clone()).collect()
+    };
+
+    state.select_entry_by_name("b.txt");
+    state.reduce(Action::TogglePin);
+    assert_eq!(names(&state), ["..", "b.txt", "a.txt"]);
+    assert_eq!(state.current_entry().unwrap().name, "b.txt");
+    assert_eq!(config::load_pinned(), HashSet::from([dir.path().join("b.txt")]));
+
+    state.reduce(Action::TogglePin);
+    assert_eq!(names(&state), ["..", "a.txt", "b.txt"]);
+    assert!(config::load_pinned().is_empty());
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TempDir;
+use file_management::app::{Action, AppState, Reducer, ToastKind};
+
+#[test]
+fn toasts_expire_and_errors_linger() {
+    let dir = TempDir::new("toast-expire");
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.toast(ToastKind::Info, "done".to_string());
+    state.toast(ToastKind::Error, "failed".to_string());
+
+    let start = state.toasts[0].created_at;
+    state.expire_toasts(start + Duration::from_secs(1));
+    assert_eq!(state.toasts.len(), 2);
+    state.expire_toasts(start + Duration::from_secs(5));
+    assert_eq!(state.toasts.len(), 1);
+    assert_eq!(state.toasts[0].kind, ToastKind::Error);
+    state.expire_toasts(Instant::now() + Duration::from_secs(60));
+    assert!(state.toasts.is_empty());
+}
+
+#[test]
+fn operations_report_through_toasts() {
+    let dir = TempDir::new("toast-ops");
+    dir.touch(&["a.txt", "b.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+
+    state.reduce(Action::Delete);
+    assert_eq!(state.toasts.last().unwrap().message, "Deleted 1 item(s)");
+    assert_eq!(state.toasts.last().unwrap().kind, ToastKind::Info);
+
+    // The undo stack only holds the irreversible delete, which isn't a toast
+    state.reduce(Action::Undo);
+    assert_eq!(state.toasts.len(), 1);
+    assert!(state.status.as_deref().unwrap().starts_with("Nothing to undo"));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use file_management::app::{Action, AppState, Reducer, UNDO_LIMIT, UndoOp, UndoStack};
+
+#[test]
+fn trashed_files_come_back_on_undo() {
+    let dir = TempDir::new("undo-trash");
+    let trash = TempDir::new("undo-trash-bin");
+    dir.touch(&["a.txt", "b.txt"]);
+    fs::write(dir.path().join("a.txt"), b"keep me").unwrap();
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.trash = Some(trash.path().to_path_buf());
+
+    state.selected.insert(dir.path().join("a.txt"));
+    state.reduce(Action::Delete);
+    assert!(!dir.path().join("a.txt").exists());
+    assert_eq!(fs::read(trash.path().join("files/a.txt")).unwrap(), b"keep me");
+    let info = fs::read_to_string(trash.path().join("info/a.txt.trashinfo")).unwrap();
+    assert!(info.contains(&format!("Path={}", dir.path().join("a.txt").display())), "{}", info);
+    let today = chrono::Local::now().format("%Y-%m-%d");
+    assert!(info.contains(&format!("DeletionDate={}T", today)), "{}", info);
+    assert_eq!(state.toasts.last().unwrap().message, "Trashed 1 item(s)");
+
+    state.reduce(Action::Undo);
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"keep me");
+    assert!(!trash.path().join("files/a.txt").exists());
+    assert!(!trash.path().join("info/a.txt.trashinfo").exists());
+    assert_eq!(state.toasts.last().unwrap().message, "Undone");
+}
+
+#[test]
+fn undone_copies_go_to_the_trash() {
+    let dir = TempDir::new("undo-copy-trash");
+    let trash = TempDir::new("undo-copy-trash-bin");
+    dir.touch(&["copy.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.trash = Some(trash.path().to_path_buf());
+    state.undo_stack.push(UndoOp::Copy { created: vec![dir.path().join("copy.txt")] });
+
+    state.reduce(Action::Undo);
+    assert!(!dir.path().join("copy.txt").exists());
+    assert!(trash.path().join("files/copy.txt").exists());
+    assert_eq!(state.toasts.last().unwrap().message, "Undone");
+}
+
+#[test]
+fn failed_undo_keeps_what_is_left() {
+    let dir = TempDir::new("undo-partial");
+    dir.touch(&["b-moved.txt"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+    let a_moved = dir.path().join("a-moved.txt");
+    let moves = vec![(a.clone(), a_moved.clone()), (b.clone(), dir.path().join("b-moved.txt"))];
+    state.undo_stack.push(UndoOp::Move { moves });
+
+    // b comes back first; a's moved copy is gone, so that one fails
+    state.reduce(Action::Undo);
+    assert!(b.exists());
+    assert!(state.toasts.last().unwrap().message.starts_with("Undo failed"));
+    match state.undo_stack.pop() {
+        Some(UndoOp::Move { moves }) => assert_eq!(moves, vec![(a, a_moved)]),
+        other => panic!("expected the rest of the move, got {:?}", other),
+    }
+}
+
+#[test]
+fn undo_stack_drops_the_oldest_past_its_limit() {
+    let mut stack = UndoStack::default();
+    for n in 0..UNDO_LIMIT + 5 {
+        stack.push(UndoOp::Irreversible { description: n.to_string() });
+    }
+    assert_eq!(stack.len(), UNDO_LIMIT);
+    let mut last = None;
+    while let Some(UndoOp::Irreversible { description }) = stack.pop() {
+        last = Some(description);
+    }
+    assert_eq!(last.as_deref(), Some("5"));
+    assert!(stack.is_empty());
+}
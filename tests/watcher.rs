@@ -0,0 +1,71 @@
+mod common;
+
+use std::{
+    fs,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::TempDir;
+use file_management::{
+    app::{Action, AppState, Reducer},
+    watcher::DirWatcher,
+};
+
+#[test]
+fn burst_of_changes_is_reported_once() {
+    let dir = TempDir::new("watch-burst");
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = DirWatcher::new(tx).unwrap();
+    watcher.sync(dir.path());
+
+    for i in 0..20 {
+        fs::write(dir.path().join(format!("f{}", i)), b"x").unwrap();
+    }
+
+    match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+        Action::DirChanged(changed) => assert_eq!(changed, dir.path()),
+        other => panic!("expected DirChanged, got {:?}", other),
+    }
+    assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+}
+
+#[test]
+fn steady_writes_still_report_changes() {
+    let dir = TempDir::new("watch-steady");
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = DirWatcher::new(tx).unwrap();
+    watcher.sync(dir.path());
+
+    // Like a download: never quiet for long enough to settle
+    let log = dir.path().join("download.part");
+    let started = Instant::now();
+    let writer = thread::spawn(move || {
+        while started.elapsed() < Duration::from_secs(3) {
+            fs::write(&log, b"x").unwrap();
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+    assert!(matches!(rx.recv_timeout(Duration::from_secs(2)), Ok(Action::DirChanged(_))));
+    assert!(started.elapsed() < Duration::from_secs(3));
+    writer.join().unwrap();
+}
+
+#[test]
+fn dir_changed_reloads_and_keeps_the_cursor_on_its_file() {
+    let dir = TempDir::new("watch-reload");
+    dir.touch(&["b", "c"]);
+    let mut state = AppState::new(dir.path().to_path_buf()).unwrap();
+    state.cursor = 2; // on "c"
+
+    dir.touch(&["a"]);
+    state.reduce(Action::DirChanged(dir.path().to_path_buf()));
+    assert_eq!(state.entries.len(), 4);
+    assert_eq!(state.current_entry().unwrap().name, "c");
+
+    // Events for another directory are ignored
+    dir.touch(&["d"]);
+    state.reduce(Action::DirChanged(dir.path().join("elsewhere")));
+    assert_eq!(state.entries.len(), 4);
+}